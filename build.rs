@@ -0,0 +1,214 @@
+//! Generates the `game::blocks` module from the data generator report in `data/blocks.json`.
+//!
+//! The report is the `reports/blocks.json` file written by the vanilla server's data generator:
+//!  `java -DbundlerMainClass=net.minecraft.data.Main -jar server.jar --reports`.
+//!  Only the `properties` of each block and the `properties` of its default state are read.
+
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::{ env, fs };
+use std::path::PathBuf;
+use serde_json::Value;
+
+
+const REPORT : &str = "data/blocks.json";
+
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+    println!("cargo::rerun-if-changed={REPORT}");
+    let report = fs::read_to_string(REPORT).unwrap_or_else(|err| panic!("failed to read {REPORT}: {err}"));
+    let blocks = parse_report(&report);
+    let out    = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is not set")).join("blocks.rs");
+    fs::write(&out, generate(&blocks)).unwrap_or_else(|err| panic!("failed to write {}: {err}", out.display()));
+}
+
+
+/// A block in the report.
+struct ReportBlock {
+    id         : String,
+    /// Each property, and its possible values and default value.
+    properties : Vec<(String, Vec<String>, String,)>
+}
+
+fn parse_report(report : &str) -> Vec<ReportBlock> {
+    let root = serde_json::from_str::<Value>(report).unwrap_or_else(|err| panic!("{REPORT}: {err}"));
+    let Value::Object(root) = root else { panic!("{REPORT}: expected an object of blocks") };
+    let mut blocks = Vec::new();
+    for (id, block,) in root {
+        assert!(id.starts_with("minecraft:"), "{REPORT}: block {id} is not in the minecraft namespace");
+        let defaults = block.get("states").and_then(Value::as_array).into_iter().flatten()
+            .find(|state| state.get("default").and_then(Value::as_bool) == Some(true))
+            .unwrap_or_else(|| panic!("{REPORT}: block {id} has no default state"));
+        let mut properties = Vec::new();
+        if let Some(props) = block.get("properties").and_then(Value::as_object) {
+            for (name, values,) in props {
+                let values = values.as_array().unwrap_or_else(|| panic!("{REPORT}: {id}[{name}] is not an array"))
+                    .iter().map(|value| value.as_str().unwrap_or_else(|| panic!("{REPORT}: {id}[{name}] has a value which is not a string")).to_string())
+                    .collect::<Vec<_>>();
+                let default = defaults.get("properties").and_then(|props| props.get(name)).and_then(Value::as_str)
+                    .unwrap_or_else(|| panic!("{REPORT}: the default state of {id} has no {name}"));
+                assert!(values.iter().any(|value| value == default), "{REPORT}: the default {name} of {id} is not one of its values");
+                properties.push((name.clone(), values, default.to_string(),));
+            }
+        }
+        properties.sort_by(|a, b| a.0.cmp(&b.0));
+        blocks.push(ReportBlock { id, properties });
+    }
+    blocks.sort_by(|a, b| a.id.cmp(&b.id));
+    blocks
+}
+
+
+/// The kind of Rust type generated for a property.
+enum PropertyKind {
+    /// Every block has the values `true` and `false`.
+    Bool,
+    /// Every value is a whole number.
+    Int { min : u8, max : u8 },
+    /// Anything else, with every value any block has, in the order they first appear.
+    Enum(Vec<String>)
+}
+
+fn property_kinds(blocks : &[ReportBlock]) -> BTreeMap<String, PropertyKind> {
+    let mut values = BTreeMap::<String, Vec<&Vec<String>>>::new();
+    for block in blocks {
+        for (name, block_values, _,) in &block.properties {
+            values.entry(name.clone()).or_default().push(block_values);
+        }
+    }
+    values.into_iter().map(|(name, sets,)| {
+        let kind = if (sets.iter().all(|set| set.len() == 2 && set.contains(&"true".to_string()) && set.contains(&"false".to_string()))) {
+            PropertyKind::Bool
+        } else if let Some(ints) = sets.iter().flat_map(|set| set.iter()).map(|value| value.parse::<u8>().ok().filter(|int| int.to_string() == *value)).collect::<Option<Vec<_>>>() {
+            PropertyKind::Int { min : *ints.iter().min().unwrap(), max : *ints.iter().max().unwrap() }
+        } else {
+            let mut all = Vec::<String>::new();
+            for value in sets.iter().flat_map(|set| set.iter()) {
+                if (! all.contains(value)) {
+                    all.push(value.clone());
+                }
+            }
+            PropertyKind::Enum(all)
+        };
+        (name, kind,)
+    }).collect()
+}
+
+
+fn generate(blocks : &[ReportBlock]) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated by build.rs from {REPORT}. Do not edit.").unwrap();
+    writeln!(out).unwrap();
+
+    for (name, kind,) in property_kinds(blocks) {
+        let ty = pascal_case(&name);
+        writeln!(out).unwrap();
+        match (kind) {
+            PropertyKind::Bool => {
+                writeln!(out, "/// The `{name}` block property.").unwrap();
+                writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]").unwrap();
+                writeln!(out, "pub struct {ty}(pub bool);").unwrap();
+                writeln!(out, "impl Property for {ty} {{").unwrap();
+                writeln!(out, "    const NAME : &'static str = {name:?};").unwrap();
+                writeln!(out, "    fn as_str(&self) -> &'static str {{ if (self.0) {{ \"true\" }} else {{ \"false\" }} }}").unwrap();
+                writeln!(out, "    fn parse(value : &str) -> Option<Self> {{").unwrap();
+                writeln!(out, "        match (value) {{ \"true\" => Some(Self(true)), \"false\" => Some(Self(false)), _ => None }}").unwrap();
+                writeln!(out, "    }}").unwrap();
+                writeln!(out, "}}").unwrap();
+            },
+            PropertyKind::Int { min, max } => {
+                let values = (min..=max).map(|value| format!("\"{value}\"")).collect::<Vec<_>>().join(", ");
+                writeln!(out, "/// The `{name}` block property, from `{min}` to `{max}`.").unwrap();
+                writeln!(out, "///").unwrap();
+                writeln!(out, "/// Most blocks only allow some of this range.").unwrap();
+                writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]").unwrap();
+                writeln!(out, "pub struct {ty}(u8);").unwrap();
+                writeln!(out, "impl {ty} {{").unwrap();
+                writeln!(out, "    /// The smallest value of this property on any block.").unwrap();
+                writeln!(out, "    pub const MIN : u8 = {min};").unwrap();
+                writeln!(out, "    /// The largest value of this property on any block.").unwrap();
+                writeln!(out, "    pub const MAX : u8 = {max};").unwrap();
+                writeln!(out, "    const VALUES : &'static [&'static str] = &[{values}];").unwrap();
+                writeln!(out, "    /// Returns a `{ty}` of `value`, or `None` if it is not between [`{ty}::MIN`] and [`{ty}::MAX`].").unwrap();
+                writeln!(out, "    pub fn new(value : u8) -> Option<Self> {{ (Self::MIN..=Self::MAX).contains(&value).then_some(Self(value)) }}").unwrap();
+                writeln!(out, "    /// Returns the value of this property.").unwrap();
+                writeln!(out, "    #[inline]").unwrap();
+                writeln!(out, "    pub fn get(&self) -> u8 {{ self.0 }}").unwrap();
+                writeln!(out, "}}").unwrap();
+                writeln!(out, "impl Property for {ty} {{").unwrap();
+                writeln!(out, "    const NAME : &'static str = {name:?};").unwrap();
+                writeln!(out, "    fn as_str(&self) -> &'static str {{ Self::VALUES[(self.0 - Self::MIN) as usize] }}").unwrap();
+                writeln!(out, "    fn parse(value : &str) -> Option<Self> {{").unwrap();
+                writeln!(out, "        Self::VALUES.iter().position(|v| *v == value).map(|index| Self(Self::MIN + (index as u8)))").unwrap();
+                writeln!(out, "    }}").unwrap();
+                writeln!(out, "}}").unwrap();
+            },
+            PropertyKind::Enum(values) => {
+                let variants = values.iter().map(|value| (value, variant_name(value),)).collect::<Vec<_>>();
+                writeln!(out, "/// The `{name}` block property.").unwrap();
+                writeln!(out, "///").unwrap();
+                writeln!(out, "/// This has every value the property has on any block. Most blocks only allow some of them.").unwrap();
+                writeln!(out, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]").unwrap();
+                writeln!(out, "pub enum {ty} {{").unwrap();
+                for (i, (value, variant,),) in variants.iter().enumerate() {
+                    writeln!(out, "    /// `{value}`.").unwrap();
+                    writeln!(out, "    {variant}{}", if (i + 1 < variants.len()) { "," } else { "" }).unwrap();
+                }
+                writeln!(out, "}}").unwrap();
+                writeln!(out, "impl Property for {ty} {{").unwrap();
+                writeln!(out, "    const NAME : &'static str = {name:?};").unwrap();
+                writeln!(out, "    fn as_str(&self) -> &'static str {{").unwrap();
+                writeln!(out, "        match (self) {{").unwrap();
+                for (value, variant,) in &variants {
+                    writeln!(out, "            Self::{variant} => {value:?},").unwrap();
+                }
+                writeln!(out, "        }}").unwrap();
+                writeln!(out, "    }}").unwrap();
+                writeln!(out, "    fn parse(value : &str) -> Option<Self> {{").unwrap();
+                writeln!(out, "        match (value) {{").unwrap();
+                for (value, variant,) in &variants {
+                    writeln!(out, "            {value:?} => Some(Self::{variant}),").unwrap();
+                }
+                writeln!(out, "            _ => None").unwrap();
+                writeln!(out, "        }}").unwrap();
+                writeln!(out, "    }}").unwrap();
+                writeln!(out, "}}").unwrap();
+            }
+        }
+    }
+
+    for block in blocks {
+        let name = block.id.trim_start_matches("minecraft:").to_uppercase();
+        writeln!(out).unwrap();
+        writeln!(out, "/// `{}`.", block.id).unwrap();
+        writeln!(out, "pub const {name} : BlockType = BlockType {{ id : {:?}, properties : &[", block.id).unwrap();
+        for (prop, values, default,) in &block.properties {
+            writeln!(out, "    BlockProperty {{ name : {prop:?}, values : &{values:?}, default : {default:?} }},").unwrap();
+        }
+        writeln!(out, "] }};").unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "/// Every block in the report, sorted by ID.").unwrap();
+    writeln!(out, "pub static ALL : &[BlockType] = &[").unwrap();
+    for block in blocks {
+        writeln!(out, "    {},", block.id.trim_start_matches("minecraft:").to_uppercase()).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    out
+}
+
+fn pascal_case(name : &str) -> String {
+    name.split('_').filter(|part| ! part.is_empty()).map(|part| {
+        let mut chars = part.chars();
+        chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+    }).collect()
+}
+
+fn variant_name(value : &str) -> String {
+    let name = pascal_case(value);
+    if (name.starts_with(|ch : char| ch.is_ascii_digit())) { format!("N{name}") } else { name }
+}
+
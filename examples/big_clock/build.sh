@@ -1 +1 @@
-cargo build --target=wasm32-unknown-unknown --release
+cargo rustc --target=wasm32-unknown-unknown --release -- -C link-arg=--export-table
@@ -6,17 +6,16 @@
 
 
 use flywheel_sys::prelude::*;
+use flywheel_sys::game::blocks;
 use std::collections::BTreeMap;
 
 
 const CHARS : &str = "0123456789:";
 
 
-#[unsafe(no_mangle)]
-pub fn flywheel_main() {
-    App::new()
-        .on_start(plot_started)
-        .run();
+#[flywheel_sys::main]
+fn main(app : &mut App) {
+    app.on_start(plot_started);
 }
 
 
@@ -48,11 +47,7 @@ async fn plot_started() {
 
         let mut batch_set = world.batch_set();
 
-        for y in 0..10 {
-            for x in 0..57 {
-                batch_set.put(BlockPos::new(x, 0, y), Block::new("minecraft:tinted_glass"));
-            }
-        }
+        batch_set.fill(BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(56, 0, 9)), &blocks::TINTED_GLASS.block());
 
         for (i, ch,) in (Instant::now() - Duration::from_hours(4))
             .as_chrono().format("%H:%M:%S").to_string()
@@ -62,7 +57,7 @@ async fn plot_started() {
             for (x, y,) in faces.get(&ch).unwrap() {
                 let x = x + (7 * i) + 1;
                 let y = y + 1;
-                batch_set.put(BlockPos::new(x as _, 0, y as _), Block::new("minecraft:white_concrete"));
+                batch_set.put(BlockPos::new(x as _, 0, y as _), blocks::WHITE_CONCRETE.block());
             }
         }
 
@@ -1,12 +1,10 @@
 use flywheel_sys::prelude::*;
 
 
-#[unsafe(no_mangle)]
-pub fn flywheel_main() {
-    App::new()
-        .on_start(plot_started)
-        .on_player_joined(player_joined)
-        .run();
+#[flywheel_sys::main]
+fn main(app : &mut App) {
+    app.on_start(plot_started)
+        .on_player_joined(player_joined);
 }
 
 
@@ -17,11 +15,10 @@ async fn plot_started() {
 
 async fn player_joined(player : Player) {
     info!("WASM detected session {} joined", player.session_id());
-    if let Some(profile) = player.profile() {
-        player.send_chat(&format!(
-            "<green>Hello, {}!</green>\n <yellow>Your UUID is {}.",
-            profile.name,
-            profile.uuid
-        ));
+    if let Some(profile) = player.fetch_profile() {
+        player.send_chat(Text::empty()
+            .append(Text::literal(format!("Hello, {}!\n ", profile.name)).color(Color::Green))
+            .append(Text::literal(format!("Your UUID is {}.", profile.uuid)).color(Color::Yellow))
+        );
     }
 }
@@ -1,20 +1,18 @@
 use flywheel_sys::prelude::*;
 
 
-#[unsafe(no_mangle)]
-pub fn flywheel_main() {
-    App::new()
-        .on_player_joined(player_joined)
-        .on_world_chunk_loading(load_chunk)
-        .run();
+#[flywheel_sys::main]
+fn main(app : &mut App) {
+    app.on_player_joined(player_joined)
+        .on_world_chunk_loading(load_chunk);
 }
 
 
 async fn player_joined(player : Player) {
     if let Some(profile) = player.fetch_profile() {
         player.send_title(
-            "<orange><b><u>Sine World</></></>",
-            &format!("<yellow>Welcome, {}!</>", profile.name),
+            Text::markup("<orange><b><u>Sine World</></></>"),
+            Text::literal(format!("Welcome, {}!", profile.name)).color(Color::Yellow),
             Duration::ZERO, Duration::from_ticks(50), Duration::from_ticks(20)
         );
     }
@@ -24,21 +22,16 @@ async fn player_joined(player : Player) {
 const SINE_FREQ : f32 = 0.0625;
 const SINE_AMP  : f32 = 5.0;
 async fn load_chunk(player : Player, chunk : ChunkPos) {
+    let border        = Block::new("minecraft:black_concrete");
+    let inner         = Block::new("minecraft:white_concrete");
     let mut batch_set = player.world().batch_set();
-    let min = chunk.min_block();
-    for dx in 0..16 {
-        for dz in 0..16 {
-            let x  = min.x + dx;
-            let z  = min.z + dz;
-            let hx = (x as f32) * SINE_FREQ;
-            let hz = (z as f32) * SINE_FREQ;
-            let h  = ((hx.sin() * hz.sin() + 1.0) * SINE_AMP) as i64;
-            let mat = if (dx == 0 || dz == 0 || dx == 15 || dz == 15)
-                { "minecraft:black_concrete" } else { "minecraft:white_concrete" };
-            for y in 0..=h {
-                batch_set.put(BlockPos::new(x, y, z), Block::new(mat));
-            }
-        }
+    for column in chunk.blocks(0, 0) {
+        let local = column.local_in_chunk();
+        let hx    = (column.x as f32) * SINE_FREQ;
+        let hz    = (column.z as f32) * SINE_FREQ;
+        let h     = ((hx.sin() * hz.sin() + 1.0) * SINE_AMP) as i64;
+        let block = if (local.x == 0 || local.z == 0 || local.x == 15 || local.z == 15) { &border } else { &inner };
+        batch_set.fill(BlockBox::new(column, column.up(h)), block);
     }
     batch_set.submit();
     player.world().mark_ready(chunk);
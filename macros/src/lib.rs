@@ -0,0 +1,99 @@
+//! Procedural macros for [`flywheel-sys`](https://github.com/FlywheelMC/flywheel-sys-rust).
+//!
+//! These are re-exported by `flywheel-sys`, and should not be depended on directly.
+
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{ parse_macro_input, Error, FnArg, ItemFn, ReturnType };
+
+
+/// The name of the function which the host calls to start a plot.
+const MAIN_EXPORT : &str = "flywheel_main";
+
+
+/// Marks the entry point of a plot.
+///
+/// This exports the function which the host calls to start the plot, creates an
+///  [`App`](https://docs.rs/flywheel-sys/latest/flywheel_sys/struct.App.html), and runs it.
+///  Panics are logged to the console from the start, if the host supports the
+///  `flywheel_selfhosted` capability. On other hosts, panics are dropped and only seen as a trap.
+///
+/// The entry point can either set up the `App` itself:
+/// ```ignore
+/// #[flywheel_sys::main]
+/// fn main(app : &mut App) {
+///     app.on_player_joined(player_joined);
+/// }
+/// ```
+///
+/// Or it can be an `async fn` taking no arguments, which is run as an `on_start` callback:
+/// ```ignore
+/// #[flywheel_sys::main]
+/// async fn main() {
+///     pass!("Plot started!");
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main(attr : TokenStream, item : TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    match (expand_main(attr.into(), item)) {
+        Ok(out)  => out.into(),
+        Err(err) => err.into_compile_error().into()
+    }
+}
+
+fn expand_main(attr : proc_macro2::TokenStream, item : ItemFn) -> Result<proc_macro2::TokenStream, Error> {
+    if (! attr.is_empty()) {
+        return Err(Error::new_spanned(attr, "`#[flywheel_sys::main]` does not take any arguments"));
+    }
+    let sig = &item.sig;
+    if (! sig.generics.params.is_empty() || sig.generics.where_clause.is_some()) {
+        return Err(Error::new_spanned(&sig.generics, "the entry point can not be generic"));
+    }
+    if let Some(abi) = &sig.abi {
+        return Err(Error::new_spanned(abi, "the entry point can not have an explicit ABI"));
+    }
+    if let Some(variadic) = &sig.variadic {
+        return Err(Error::new_spanned(variadic, "the entry point can not be variadic"));
+    }
+    if let Some(unsafety) = &sig.unsafety {
+        return Err(Error::new_spanned(unsafety, "the entry point can not be `unsafe`"));
+    }
+    if let Some(FnArg::Receiver(receiver)) = sig.inputs.first() {
+        return Err(Error::new_spanned(receiver, "the entry point can not take `self`"));
+    }
+
+    let ident  = &sig.ident;
+    let export = syn::Ident::new(MAIN_EXPORT, Span::call_site());
+    let run    = if (sig.asyncness.is_some()) {
+        if (! sig.inputs.is_empty()) {
+            return Err(Error::new_spanned(&sig.inputs, "an `async` entry point can not take any arguments"));
+        }
+        // The return type is checked by `App::on_start`, which accepts `()` and `Result<(), E>`.
+        quote!{ app.on_start(#ident); }
+    } else {
+        if (sig.inputs.len() != 1) {
+            return Err(Error::new_spanned(&sig.ident, "the entry point must take exactly one `&mut App` argument"));
+        }
+        if let ReturnType::Type(_, ty) = &sig.output {
+            return Err(Error::new_spanned(ty, "the entry point can not return anything"));
+        }
+        quote!{
+            let #ident : fn(&mut ::flywheel_sys::App) = #ident;
+            #ident(&mut app);
+        }
+    };
+
+    Ok(quote!{
+        #[unsafe(no_mangle)]
+        pub fn #export() {
+            #item
+            ::flywheel_sys::__private::init();
+            let mut app = ::flywheel_sys::App::new();
+            #run
+            app.run();
+        }
+    })
+}
@@ -0,0 +1,425 @@
+//! Events sent by the host, and decoding of their payloads.
+//!
+//! Callbacks for an event are registered with [`App::on`](crate::App::on), or
+//!  [`App::on_event`](crate::App::on_event) for events without a [`FlywheelEvent`] type.
+
+
+use crate::game::{ Player, ChatMessage };
+use crate::task::TaskScope;
+use crate::game::data::{ ChunkPos, Block };
+use crate::time::{ Duration, Instant };
+use crate::uuid::Uuid;
+use core::fmt;
+use core::error::Error;
+
+
+/// An event which can be sent by the host.
+pub trait FlywheelEvent : Sized + 'static {
+
+    /// The ID the host sends this event with.
+    const ID : &'static str;
+
+    /// Decodes this event from its argument payload.
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError>;
+
+    /// Returns the scope which callbacks for this event are spawned in, if any.
+    ///
+    /// See [`task::spawn_in`](crate::task::spawn_in).
+    fn scope(&self) -> Option<TaskScope> { None }
+
+}
+
+
+/// A player joined the server.
+///
+/// Callbacks for this event are cancelled if the player leaves before they finish.
+#[derive(Clone, Copy)]
+pub struct PlayerJoined {
+    /// The player who joined.
+    pub player : Player
+}
+impl FlywheelEvent for PlayerJoined {
+    const ID : &'static str = "flywheel_player_joined";
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        Ok(Self { player : args.player()? })
+    }
+    fn scope(&self) -> Option<TaskScope> { Some(TaskScope::Player(self.player)) }
+}
+
+/// A player left the server.
+#[derive(Clone, Copy)]
+pub struct PlayerLeft {
+    /// The player who left.
+    pub player : Player
+}
+impl FlywheelEvent for PlayerLeft {
+    const ID : &'static str = "flywheel_player_left";
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        Ok(Self { player : args.player()? })
+    }
+}
+
+/// A player sent a chat message.
+///
+/// The host holds the message back until every callback has finished with it, so that
+///  callbacks can cancel it, rewrite it, or restrict who receives it. See [`ChatMessage`].
+///  If there are no callbacks for this event, the message is sent on unchanged.
+///
+/// Callbacks for this event are cancelled if the player leaves before they finish.
+#[derive(Clone)]
+pub struct PlayerChat {
+    /// The player who sent the message.
+    pub player  : Player,
+    /// The message which was sent.
+    pub message : ChatMessage
+}
+impl FlywheelEvent for PlayerChat {
+    const ID : &'static str = "flywheel_player_chat";
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        let player = args.player()?;
+        let id     = args.u64()?;
+        let text   = args.str()?;
+        Ok(Self { player, message : ChatMessage::pending(player, id, text) })
+    }
+    fn scope(&self) -> Option<TaskScope> { Some(TaskScope::Player(self.player)) }
+}
+
+/// A player ran a command registered with [`App::command`](crate::App::command).
+///
+/// The command is parsed and its handler is run automatically, so callbacks for this
+///  event are only needed to watch which commands are run.
+#[derive(Clone)]
+pub struct PlayerCommand {
+    /// The player who ran the command.
+    pub player  : Player,
+    /// The command, as the player typed it.
+    pub command : String
+}
+impl FlywheelEvent for PlayerCommand {
+    const ID : &'static str = "flywheel_player_command";
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        Ok(Self { player : args.player()?, command : args.str()?.to_string() })
+    }
+    fn scope(&self) -> Option<TaskScope> { Some(TaskScope::Player(self.player)) }
+}
+
+/// A chunk in a player's world needs to be generated.
+///
+/// Once generated, the chunk should be marked as ready with
+///  [`World::mark_ready`](crate::game::prelude::World::mark_ready). If there are no
+///  callbacks for this event, the chunk is marked as ready automatically.
+///
+/// Callbacks for this event are cancelled if the chunk is unloaded before they finish.
+#[derive(Clone, Copy)]
+pub struct WorldChunkLoading {
+    /// The player owning the world.
+    pub player : Player,
+    /// The chunk being loaded.
+    pub pos    : ChunkPos
+}
+impl FlywheelEvent for WorldChunkLoading {
+    const ID : &'static str = "flywheel_world_chunk_loading";
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        Ok(Self { player : args.player()?, pos : args.chunk_pos()? })
+    }
+    fn scope(&self) -> Option<TaskScope> { Some(TaskScope::Chunk(self.player, self.pos)) }
+}
+
+/// A chunk in a player's world was unloaded.
+#[derive(Clone, Copy)]
+pub struct WorldChunkUnloaded {
+    /// The player owning the world.
+    pub player : Player,
+    /// The chunk which was unloaded.
+    pub pos    : ChunkPos
+}
+impl FlywheelEvent for WorldChunkUnloaded {
+    const ID : &'static str = "flywheel_world_chunk_unloaded";
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        Ok(Self { player : args.player()?, pos : args.chunk_pos()? })
+    }
+}
+
+
+/// A bounds-checked reader over the argument payload of a host event.
+///
+/// All values are little-endian. Each read advances the reader past the value read,
+///  and fails with an [`EventArgsError`] instead of reading out of bounds.
+///
+/// Trailing bytes are allowed, so that newer hosts can add fields to the end of an event.
+///
+/// `EventArgs` does not call into the host, so it can be used (and fuzzed) natively.
+#[derive(Clone, Debug)]
+pub struct EventArgs<'l> {
+    data   : &'l [u8],
+    offset : usize
+}
+
+impl<'l> EventArgs<'l> {
+
+    /// Creates a new `EventArgs` reading from the start of `data`.
+    #[inline]
+    pub const fn new(data : &'l [u8]) -> Self { Self { data, offset : 0 } }
+
+    /// Returns the number of bytes read so far.
+    #[inline]
+    pub const fn offset(&self) -> usize { self.offset }
+
+    /// Returns the bytes which have not been read yet.
+    #[inline]
+    pub fn remaining(&self) -> &'l [u8] { &self.data[self.offset..] }
+
+    /// Returns `true` if every byte has been read.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.remaining().is_empty() }
+
+    /// Reads `len` raw bytes.
+    pub fn slice(&mut self, len : usize) -> Result<&'l [u8], EventArgsError> {
+        let out = self.offset.checked_add(len)
+            .and_then(|end| self.data.get(self.offset..end))
+            .ok_or(EventArgsError::UnexpectedEnd { offset : self.offset, len })?;
+        self.offset += len;
+        Ok(out)
+    }
+
+    /// Reads `N` raw bytes.
+    pub fn bytes<const N : usize>(&mut self) -> Result<[u8; N], EventArgsError> {
+        let (chunk, _,) = self.slice(N)?.as_chunks::<N>();
+        Ok(chunk[0])
+    }
+
+    /// Reads a `u8`.
+    #[inline]
+    pub fn u8(&mut self) -> Result<u8, EventArgsError> { self.bytes().map(u8::from_le_bytes) }
+
+    /// Reads a `u32`.
+    #[inline]
+    pub fn u32(&mut self) -> Result<u32, EventArgsError> { self.bytes().map(u32::from_le_bytes) }
+
+    /// Reads a `u64`.
+    #[inline]
+    pub fn u64(&mut self) -> Result<u64, EventArgsError> { self.bytes().map(u64::from_le_bytes) }
+
+    /// Reads an `i32`.
+    #[inline]
+    pub fn i32(&mut self) -> Result<i32, EventArgsError> { self.bytes().map(i32::from_le_bytes) }
+
+    /// Reads an `i64`.
+    #[inline]
+    pub fn i64(&mut self) -> Result<i64, EventArgsError> { self.bytes().map(i64::from_le_bytes) }
+
+    /// Reads an `f32`.
+    #[inline]
+    pub fn f32(&mut self) -> Result<f32, EventArgsError> { self.bytes().map(f32::from_le_bytes) }
+
+    /// Reads an `f64`.
+    #[inline]
+    pub fn f64(&mut self) -> Result<f64, EventArgsError> { self.bytes().map(f64::from_le_bytes) }
+
+    /// Reads a `bool`, encoded as a single byte which must be `0` or `1`.
+    pub fn bool(&mut self) -> Result<bool, EventArgsError> {
+        let offset = self.offset;
+        match (self.u8()?) {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(EventArgsError::InvalidValue { offset, what : "bool" })
+        }
+    }
+
+    /// Reads a UTF-8 string, prefixed by its length in bytes as a `u32`.
+    pub fn str(&mut self) -> Result<&'l str, EventArgsError> {
+        let len    = self.u32()? as usize;
+        let offset = self.offset;
+        str::from_utf8(self.slice(len)?).map_err(|_| EventArgsError::InvalidUtf8 { offset })
+    }
+
+    /// Reads a [`Uuid`], encoded as a `u128`.
+    #[inline]
+    pub fn uuid(&mut self) -> Result<Uuid, EventArgsError> {
+        self.bytes().map(|bytes| Uuid::from_u128_le(u128::from_le_bytes(bytes)))
+    }
+
+    /// Reads a [`Player`], encoded as its session ID.
+    #[inline]
+    pub fn player(&mut self) -> Result<Player, EventArgsError> {
+        self.u64().map(|session_id| unsafe { Player::from_session_id(session_id) })
+    }
+
+    /// Reads a [`ChunkPos`], encoded as its `x` and `z` values.
+    pub fn chunk_pos(&mut self) -> Result<ChunkPos, EventArgsError> {
+        Ok(ChunkPos::new(self.i32()?, self.i32()?))
+    }
+
+    /// Reads a [`Block`], encoded as its ID, followed by a `u8` count of its states
+    ///  and the name and value of each state.
+    pub fn block(&mut self) -> Result<Block, EventArgsError> {
+        let mut block = Block::new(self.str()?);
+        let offset    = self.offset;
+        let states    = self.u8()?;
+        if (states >= 16) {
+            return Err(EventArgsError::InvalidValue { offset, what : "block state count" });
+        }
+        for _ in 0..states {
+            let state = self.str()?;
+            block.set(state, self.str()?);
+        }
+        Ok(block)
+    }
+
+    /// Reads an [`Instant`], encoded as the whole seconds since the Unix epoch as a `u64`,
+    ///  followed by the remaining nanoseconds as a `u32`.
+    pub fn instant(&mut self) -> Result<Instant, EventArgsError> {
+        let start  = self.offset;
+        let secs   = self.u64()?;
+        let offset = self.offset;
+        let nanos  = self.u32()?;
+        if (nanos >= 1_000_000_000) {
+            return Err(EventArgsError::InvalidValue { offset, what : "nanoseconds" });
+        }
+        Instant::UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+            .ok_or(EventArgsError::InvalidValue { offset : start, what : "instant" })
+    }
+
+}
+
+
+/// An error returned by [`EventArgs`] if an event payload is malformed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventArgsError {
+    /// The payload ended before a value could be read.
+    UnexpectedEnd {
+        /// The offset of the value in the payload.
+        offset : usize,
+        /// The number of bytes needed to read the value.
+        len    : usize
+    },
+    /// A string was not valid UTF-8.
+    InvalidUtf8 {
+        /// The offset of the string in the payload, after the length prefix.
+        offset : usize
+    },
+    /// A value was out of range for its type.
+    InvalidValue {
+        /// The offset of the value in the payload.
+        offset : usize,
+        /// What kind of value was being read.
+        what   : &'static str
+    }
+}
+
+impl fmt::Display for EventArgsError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::UnexpectedEnd { offset, len } => write!(f, "expected {len} more bytes at offset {offset}"),
+            Self::InvalidUtf8 { offset }        => write!(f, "invalid UTF-8 in string at offset {offset}"),
+            Self::InvalidValue { offset, what } => write!(f, "invalid {what} at offset {offset}")
+        }
+    }
+}
+
+impl Error for EventArgsError { }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `value` with a `u32` length prefix.
+    fn str(value : &[u8]) -> Vec<u8> {
+        let mut out = (value.len() as u32).to_le_bytes().to_vec();
+        out.extend(value);
+        out
+    }
+
+    /// Encodes a block with `states` states.
+    fn block(states : u8) -> Vec<u8> {
+        let mut out = str(b"minecraft:note_block");
+        out.push(states);
+        for i in 0..states {
+            out.extend(str(format!("s{i}").as_bytes()));
+            out.extend(str(b"true"));
+        }
+        out
+    }
+
+    fn instant(secs : u64, nanos : u32) -> Vec<u8> {
+        let mut out = secs.to_le_bytes().to_vec();
+        out.extend(nanos.to_le_bytes());
+        out
+    }
+
+    #[test]
+    fn truncated_values_are_errors() {
+        type Read = fn(&mut EventArgs<'_>) -> Result<(), EventArgsError>;
+        let player = 7u64.to_le_bytes().to_vec();
+        let uuid   = [1; 16].to_vec();
+        let table : [(&str, Vec<u8>, Read,); 15] = [
+            ("u8",        vec![ 1 ],             |args| args.u8().map(drop)),
+            ("u32",       vec![ 1; 4 ],          |args| args.u32().map(drop)),
+            ("u64",       vec![ 1; 8 ],          |args| args.u64().map(drop)),
+            ("i32",       vec![ 1; 4 ],          |args| args.i32().map(drop)),
+            ("i64",       vec![ 1; 8 ],          |args| args.i64().map(drop)),
+            ("f32",       vec![ 1; 4 ],          |args| args.f32().map(drop)),
+            ("f64",       vec![ 1; 8 ],          |args| args.f64().map(drop)),
+            ("bool",      vec![ 1 ],             |args| args.bool().map(drop)),
+            ("bytes",     vec![ 1; 3 ],          |args| args.bytes::<3>().map(drop)),
+            ("str",       str(b"hello"),         |args| args.str().map(drop)),
+            ("uuid",      uuid,                  |args| args.uuid().map(drop)),
+            ("player",    player,                |args| args.player().map(drop)),
+            ("chunk_pos", vec![ 1; 8 ],          |args| args.chunk_pos().map(drop)),
+            ("block",     block(2),              |args| args.block().map(drop)),
+            ("instant",   instant(5, 6),         |args| args.instant().map(drop))
+        ];
+        for (name, data, read,) in table {
+            let mut args = EventArgs::new(&data);
+            assert_eq!(read(&mut args), Ok(()), "{name}");
+            assert!(args.is_empty(), "{name} did not read every byte");
+            for len in 0..data.len() {
+                let mut args = EventArgs::new(&data[..len]);
+                let     err  = read(&mut args).unwrap_err();
+                let EventArgsError::UnexpectedEnd { offset, len : needed } = err else {
+                    panic!("{name} with {len} bytes failed with {err:?}");
+                };
+                assert!(offset <= len && len < offset + needed, "{name} with {len} bytes failed with {err:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_have_fewer_than_16_states() {
+        let data = block(15);
+        assert_eq!(EventArgs::new(&data).block().unwrap().get("s14"), Some("true"));
+        let data = block(16);
+        assert_eq!(EventArgs::new(&data).block().unwrap_err(), EventArgsError::InvalidValue { offset : 24, what : "block state count" });
+    }
+
+    #[test]
+    fn strings_must_be_utf8() {
+        let mut data = vec![ 9 ];
+        data.extend(str(b"ab\xFFcd"));
+        let mut args = EventArgs::new(&data);
+        args.u8().unwrap();
+        assert_eq!(args.str().unwrap_err(), EventArgsError::InvalidUtf8 { offset : 5 });
+        assert_eq!(EventArgs::new(&str("é".as_bytes())).str(), Ok("é"));
+        assert_eq!(EventArgs::new(&str(&"é".as_bytes()[..1])).str().unwrap_err(), EventArgsError::InvalidUtf8 { offset : 4 });
+    }
+
+    #[test]
+    fn instants_are_checked() {
+        let max = EventArgs::new(&instant(u64::MAX, 999_999_999)).instant().unwrap();
+        assert_eq!(max.duration_since(Instant::UNIX_EPOCH), Duration::MAX);
+        let mut data = vec![ 0 ];
+        data.extend(instant(1, 1_000_000_000));
+        let mut args = EventArgs::new(&data);
+        args.u8().unwrap();
+        assert_eq!(args.instant().err(), Some(EventArgsError::InvalidValue { offset : 9, what : "nanoseconds" }));
+    }
+
+    #[test]
+    fn bools_are_0_or_1() {
+        assert_eq!(EventArgs::new(&[0]).bool(), Ok(false));
+        assert_eq!(EventArgs::new(&[1]).bool(), Ok(true));
+        assert_eq!(EventArgs::new(&[2]).bool().unwrap_err(), EventArgsError::InvalidValue { offset : 0, what : "bool" });
+    }
+
+}
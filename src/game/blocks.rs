@@ -0,0 +1,208 @@
+//! Every vanilla block, and the properties of its states.
+//!
+//! This module is generated from the data generator report in `data/blocks.json`. There is a
+//!  [`BlockType`] constant for each block, such as [`OAK_STAIRS`], and a [`Property`] type for
+//!  each block property, such as [`Facing`] and [`Waterlogged`].
+//!
+//! The checked-in report is trimmed to the properties and default state of each block, and only
+//!  covers common blocks, so blocks like `minecraft:sculk` are reported as
+//!  [`BlockError::UnknownId`]. It can be replaced with the `reports/blocks.json` file written by
+//!  the vanilla server's data generator for a specific version, which is read as it is.
+//!
+//! Blocks built with [`Block::new`] and [`Block::with`] are not checked against this module.
+//!  Use [`Block::try_with`] or [`Block::validate`] to check them.
+
+
+use super::data::Block;
+use core::fmt;
+use std::error::Error;
+
+
+/// A type of block, such as `minecraft:oak_stairs`, and the properties its states have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockType {
+    id         : &'static str,
+    properties : &'static [BlockProperty]
+}
+
+impl BlockType {
+
+    /// Returns the ID of this block type, such as `minecraft:oak_stairs`.
+    #[inline]
+    pub const fn id(&self) -> &'static str { self.id }
+
+    /// Returns the properties of this block type, sorted by name.
+    #[inline]
+    pub const fn properties(&self) -> &'static [BlockProperty] { self.properties }
+
+    /// Returns the property of this block type named `name`, if it has one.
+    pub fn property(&self, name : &str) -> Option<&'static BlockProperty> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+
+    /// Returns a [`Block`] of this type with no properties set, which the host gives its default state.
+    #[inline]
+    pub fn block(&self) -> Block { Block::new(self.id) }
+
+    /// Returns a [`Block`] of this type with every property set to its default value.
+    pub fn default_state(&self) -> Block {
+        let mut block = self.block();
+        for property in self.properties {
+            block.set(property.name, property.default);
+        }
+        block
+    }
+
+}
+
+
+/// A property of a [`BlockType`], such as `facing`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockProperty {
+    name    : &'static str,
+    values  : &'static [&'static str],
+    default : &'static str
+}
+
+impl BlockProperty {
+
+    /// Returns the name of this property.
+    #[inline]
+    pub const fn name(&self) -> &'static str { self.name }
+
+    /// Returns every value this property can have on its block.
+    #[inline]
+    pub const fn values(&self) -> &'static [&'static str] { self.values }
+
+    /// Returns the value this property has in the default state of its block.
+    #[inline]
+    pub const fn default(&self) -> &'static str { self.default }
+
+    /// Returns `true` if `value` is one of [`BlockProperty::values`].
+    pub fn allows(&self, value : &str) -> bool { self.values.contains(&value) }
+
+}
+
+
+/// A typed block property, which can be set with [`Block::with_property`].
+///
+/// Each type has every value its property has on any block, so not every value is valid on
+///  every block. For example, [`Facing::Up`] is valid on [`BARREL`] but not on [`OAK_STAIRS`].
+pub trait Property : Copy + 'static {
+
+    /// The name of this property, such as `facing`.
+    const NAME : &'static str;
+
+    /// Returns this value as it is written in a block state, such as `north`.
+    fn as_str(&self) -> &'static str;
+
+    /// Parses a value as it is written in a block state.
+    fn parse(value : &str) -> Option<Self>;
+
+}
+
+
+/// Returns the block type with the ID `id`.
+///
+/// IDs without a namespace are in the `minecraft` namespace.
+pub fn get(id : &str) -> Option<&'static BlockType> {
+    let path = match (id.split_once(':')) {
+        Some(("minecraft", path,)) => path,
+        Some(_)                    => { return None; },
+        None                       => id
+    };
+    ALL.binary_search_by(|ty| ty.id["minecraft:".len()..].cmp(path)).ok().map(|index| &ALL[index])
+}
+
+
+/// Checks that the block `id` has a property `name` which allows `value`.
+pub(crate) fn check(id : &str, name : &str, value : &str) -> Result<(), BlockError> {
+    let ty       = get(id).ok_or_else(|| BlockError::UnknownId { id : id.to_string() })?;
+    let property = ty.property(name).ok_or_else(|| BlockError::UnknownProperty { id : id.to_string(), property : name.to_string() })?;
+    if (! property.allows(value)) {
+        return Err(BlockError::InvalidValue { id : id.to_string(), property : name.to_string(), value : value.to_string() });
+    }
+    Ok(())
+}
+
+
+/// An error returned when a [`Block`] is not a valid vanilla block state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockError {
+    /// There is no block with this ID.
+    UnknownId {
+        /// The block ID.
+        id : String
+    },
+    /// The block does not have this property.
+    UnknownProperty {
+        /// The block ID.
+        id       : String,
+        /// The name of the property.
+        property : String
+    },
+    /// The property can not have this value on the block.
+    InvalidValue {
+        /// The block ID.
+        id       : String,
+        /// The name of the property.
+        property : String,
+        /// The value which is not allowed.
+        value    : String
+    }
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::UnknownId { id } => write!(f, "unknown block {id:?}"),
+            Self::UnknownProperty { id, property } => {
+                write!(f, "block {id:?} has no property {property:?}")?;
+                match (get(id).map(BlockType::properties)) {
+                    Some([])         => write!(f, " (it has no properties)"),
+                    Some(properties) => write!(f, " (expected one of: {})", properties.iter().map(BlockProperty::name).collect::<Vec<_>>().join(", ")),
+                    None             => Ok(())
+                }
+            },
+            Self::InvalidValue { id, property, value } => {
+                write!(f, "invalid value {value:?} for property {property:?} of block {id:?}")?;
+                match (get(id).and_then(|ty| ty.property(property))) {
+                    Some(property) => write!(f, " (expected one of: {})", property.values.join(", ")),
+                    None           => Ok(())
+                }
+            }
+        }
+    }
+}
+
+impl Error for BlockError { }
+
+
+include!(concat!(env!("OUT_DIR"), "/blocks.rs"));
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_with_checks_values() {
+        let block = Block::new(OAK_LOG.id()).try_with("axis", "y").unwrap();
+        assert_eq!(block.get("axis"), Some("y"));
+        assert_eq!(Block::new(OAK_LOG.id()).try_with("axis", "w"), Err(BlockError::InvalidValue {
+            id       : "minecraft:oak_log".to_string(),
+            property : "axis".to_string(),
+            value    : "w".to_string()
+        }));
+        assert!(matches!(Block::new(OAK_LOG.id()).try_with("facing", "north"), Err(BlockError::UnknownProperty { .. })));
+        assert!(matches!(Block::new("minecraft:not_a_block").try_with("axis", "y"), Err(BlockError::UnknownId { .. })));
+    }
+
+    #[test]
+    fn default_states_are_valid() {
+        for ty in ALL {
+            assert_eq!(ty.default_state().validate(), Ok(()), "{}", ty.id());
+        }
+    }
+
+}
@@ -0,0 +1,347 @@
+use super::CommandError;
+use crate::game::Player;
+use crate::game::data::BlockPos;
+use core::ops::RangeInclusive;
+
+
+/// A type of command argument, which parses part of a command into an [`ArgumentType::Output`].
+///
+/// The argument is also described to the client as a Brigadier parser, so that the client can
+///  complete and highlight it. Arguments which the client does not have a parser for can use
+///  `brigadier:string` with [`ArgumentType::suggestions`].
+pub trait ArgumentType : 'static {
+
+    /// The value this argument parses to, which can be taken from [`CommandArgs`](super::CommandArgs).
+    type Output : 'static;
+
+    /// Parses this argument, starting at the cursor of `reader`.
+    ///
+    /// On success, the cursor should be left after the end of the argument.
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<Self::Output, CommandError>;
+
+    /// Returns the ID of the Brigadier parser the client uses for this argument, such as `brigadier:string`.
+    fn parser(&self) -> &'static str;
+
+    /// Writes the properties of the Brigadier parser, in the same format as the vanilla protocol.
+    fn properties(&self, out : &mut Vec<u8>) { let _ = out; }
+
+    /// Returns a fixed list of values which the client suggests for this argument.
+    fn suggestions(&self) -> Vec<String> { Vec::new() }
+
+}
+
+
+/// A cursor over a command which is being parsed.
+#[derive(Clone, Debug)]
+pub struct CommandReader<'l> {
+    input  : &'l str,
+    cursor : usize
+}
+
+impl<'l> CommandReader<'l> {
+
+    /// Creates a new `CommandReader` reading from the start of `input`.
+    #[inline]
+    pub const fn new(input : &'l str) -> Self { Self { input, cursor : 0 } }
+
+    /// Returns the whole command.
+    #[inline]
+    pub fn input(&self) -> &'l str { self.input }
+
+    /// Returns the byte offset which is read next.
+    #[inline]
+    pub fn cursor(&self) -> usize { self.cursor }
+
+    /// Moves the cursor to the byte offset `cursor`, such as to go back to the start of an argument.
+    ///
+    /// ### Panics
+    /// Panics if `cursor` is not on a character boundary of the command.
+    #[track_caller]
+    pub fn set_cursor(&mut self, cursor : usize) {
+        assert!(self.input.is_char_boundary(cursor), "cursor {cursor} is not on a character boundary");
+        self.cursor = cursor;
+    }
+
+    /// Returns the part of the command which has not been read yet.
+    #[inline]
+    pub fn remaining(&self) -> &'l str { &self.input[self.cursor..] }
+
+    /// Returns `true` if the whole command has been read.
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.remaining().is_empty() }
+
+    /// Returns the next character, without reading it.
+    #[inline]
+    pub fn peek(&self) -> Option<char> { self.remaining().chars().next() }
+
+    /// Skips the next character.
+    pub fn skip(&mut self) {
+        if let Some(ch) = self.peek() {
+            self.cursor += ch.len_utf8();
+        }
+    }
+
+    /// Reads up to the next space, or the end of the command.
+    pub fn read_word(&mut self) -> &'l str {
+        let remaining = self.remaining();
+        let len       = remaining.find(' ').unwrap_or(remaining.len());
+        self.cursor += len;
+        &remaining[..len]
+    }
+
+    /// Reads the rest of the command.
+    pub fn read_rest(&mut self) -> &'l str {
+        let remaining = self.remaining();
+        self.cursor = self.input.len();
+        remaining
+    }
+
+    /// Reads a whole word as an `i64`.
+    pub fn read_i64(&mut self) -> Result<i64, CommandError> {
+        let start = self.cursor;
+        let word  = self.read_word();
+        if (word.is_empty()) {
+            return Err(CommandError::new("Expected an integer", start));
+        }
+        word.parse().map_err(|_| CommandError::new(format!("Invalid integer {word:?}"), start))
+    }
+
+    /// Creates a [`CommandError`] pointing at the cursor.
+    #[inline]
+    pub fn error(&self, message : impl Into<String>) -> CommandError {
+        CommandError::new(message, self.cursor)
+    }
+
+}
+
+
+/// A whole number, parsed to an `i64`.
+#[derive(Clone, Debug)]
+pub struct IntegerArg {
+    min : i64,
+    max : i64
+}
+
+impl IntegerArg {
+
+    /// Accepts any `i64`.
+    #[inline]
+    pub const fn new() -> Self { Self { min : i64::MIN, max : i64::MAX } }
+
+    /// Only accepts numbers in `range`.
+    #[inline]
+    pub const fn range(range : RangeInclusive<i64>) -> Self { Self { min : *range.start(), max : *range.end() } }
+
+}
+
+impl Default for IntegerArg {
+    fn default() -> Self { Self::new() }
+}
+
+impl ArgumentType for IntegerArg {
+    type Output = i64;
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<i64, CommandError> {
+        let start = reader.cursor();
+        let value = reader.read_i64()?;
+        if (value < self.min) {
+            return Err(CommandError::new(format!("Integer must not be less than {}, found {value}", self.min), start));
+        }
+        if (value > self.max) {
+            return Err(CommandError::new(format!("Integer must not be more than {}, found {value}", self.max), start));
+        }
+        Ok(value)
+    }
+    fn parser(&self) -> &'static str { "brigadier:long" }
+    fn properties(&self, out : &mut Vec<u8>) {
+        out.push(0b11);
+        out.extend(self.min.to_be_bytes());
+        out.extend(self.max.to_be_bytes());
+    }
+}
+
+
+/// A single word, parsed to a `String`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WordArg;
+
+impl ArgumentType for WordArg {
+    type Output = String;
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<String, CommandError> {
+        let word = reader.read_word();
+        if (word.is_empty()) {
+            return Err(reader.error("Expected a word"));
+        }
+        Ok(word.to_string())
+    }
+    fn parser(&self) -> &'static str { "brigadier:string" }
+    fn properties(&self, out : &mut Vec<u8>) { out.push(0); }
+}
+
+
+/// The rest of the command, including spaces, parsed to a `String`.
+///
+/// As this reads everything, it can only be used on the last node of a command.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GreedyStringArg;
+
+impl ArgumentType for GreedyStringArg {
+    type Output = String;
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<String, CommandError> {
+        let rest = reader.read_rest();
+        if (rest.is_empty()) {
+            return Err(reader.error("Expected some text"));
+        }
+        Ok(rest.to_string())
+    }
+    fn parser(&self) -> &'static str { "brigadier:string" }
+    fn properties(&self, out : &mut Vec<u8>) { out.push(2); }
+}
+
+
+/// Three whole numbers separated by spaces, parsed to a [`BlockPos`].
+///
+/// Relative (`~`) and local (`^`) coordinates are not supported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockPosArg;
+
+impl ArgumentType for BlockPosArg {
+    type Output = BlockPos;
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<BlockPos, CommandError> {
+        let mut coords = [0; 3];
+        for (i, coord,) in coords.iter_mut().enumerate() {
+            if (i > 0) {
+                if (reader.peek() != Some(' ')) {
+                    return Err(reader.error("Expected three coordinates"));
+                }
+                reader.skip();
+            }
+            if let Some('~' | '^') = reader.peek() {
+                return Err(reader.error("Relative coordinates are not supported"));
+            }
+            *coord = reader.read_i64()?;
+        }
+        let [x, y, z] = coords;
+        Ok(BlockPos::new(x, y, z))
+    }
+    fn parser(&self) -> &'static str { "minecraft:block_pos" }
+}
+
+
+/// The name of a player on the server, parsed to a [`Player`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlayerArg;
+
+impl ArgumentType for PlayerArg {
+    type Output = Player;
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<Player, CommandError> {
+        let start = reader.cursor();
+        let name  = reader.read_word();
+        if (name.is_empty()) {
+            return Err(reader.error("Expected a player name"));
+        }
+        Player::from_name(name).ok_or_else(|| CommandError::new(format!("No player named {name:?} was found"), start))
+    }
+    fn parser(&self) -> &'static str { "minecraft:game_profile" }
+}
+
+
+/// One of a fixed set of words, each parsed to a value of `T`.
+///
+/// This can be used for enums:
+/// ```ignore
+/// Command::argument("mode", OneOf::new([
+///     ("survival", GameMode::Survival),
+///     ("creative", GameMode::Creative)
+/// ]))
+/// ```
+#[derive(Clone, Debug)]
+pub struct OneOf<T> {
+    choices : Vec<(String, T,)>
+}
+
+impl<T : Clone + 'static> OneOf<T> {
+
+    /// Creates a new `OneOf` from each word and the value it is parsed to.
+    pub fn new<S : Into<String>>(choices : impl IntoIterator<Item = (S, T,)>) -> Self {
+        Self { choices : choices.into_iter().map(|(word, value,)| (word.into(), value,)).collect() }
+    }
+
+}
+
+impl<T : Clone + 'static> ArgumentType for OneOf<T> {
+    type Output = T;
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<T, CommandError> {
+        let start = reader.cursor();
+        let word  = reader.read_word();
+        self.choices.iter()
+            .find(|(choice, _,)| choice == word)
+            .map(|(_, value,)| value.clone())
+            .ok_or_else(|| CommandError::new(format!("Expected one of: {}", self.suggestions().join(", ")), start))
+    }
+    fn parser(&self) -> &'static str { "brigadier:string" }
+    fn properties(&self, out : &mut Vec<u8>) { out.push(0); }
+    fn suggestions(&self) -> Vec<String> { self.choices.iter().map(|(choice, _,)| choice.clone()).collect() }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<A : ArgumentType>(argument : A, input : &str) -> Result<(A::Output, usize,), CommandError> {
+        let mut reader = CommandReader::new(input);
+        argument.parse(&mut reader).map(|value| (value, reader.cursor(),))
+    }
+
+    #[test]
+    fn integers_are_checked_against_their_range() {
+        let arg = || IntegerArg::range(-5..=64);
+        assert_eq!(parse(arg(), "-5 x"), Ok((-5, 2,)));
+        assert_eq!(parse(arg(), "64"), Ok((64, 2,)));
+        assert_eq!(parse(arg(), "-6"), Err(CommandError::new("Integer must not be less than -5, found -6", 0)));
+        assert_eq!(parse(arg(), "65"), Err(CommandError::new("Integer must not be more than 64, found 65", 0)));
+        assert_eq!(parse(arg(), "6x"), Err(CommandError::new("Invalid integer \"6x\"", 0)));
+        assert_eq!(parse(arg(), ""), Err(CommandError::new("Expected an integer", 0)));
+        assert_eq!(parse(IntegerArg::new(), "-9223372036854775808"), Ok((i64::MIN, 20,)));
+        assert_eq!(parse(IntegerArg::new(), "9223372036854775808"), Err(CommandError::new("Invalid integer \"9223372036854775808\"", 0)));
+    }
+
+    #[test]
+    fn block_positions_need_three_absolute_coordinates() {
+        assert_eq!(parse(BlockPosArg, "1 -2 3 x"), Ok((BlockPos::new(1, -2, 3), 6,)));
+        assert_eq!(parse(BlockPosArg, "1 2"), Err(CommandError::new("Expected three coordinates", 3)));
+        assert_eq!(parse(BlockPosArg, "1 2 "), Err(CommandError::new("Expected an integer", 4)));
+        assert_eq!(parse(BlockPosArg, "1  2 3"), Err(CommandError::new("Expected an integer", 2)));
+        assert_eq!(parse(BlockPosArg, "~ 2 3"), Err(CommandError::new("Relative coordinates are not supported", 0)));
+        assert_eq!(parse(BlockPosArg, "1 ~1 3"), Err(CommandError::new("Relative coordinates are not supported", 2)));
+        assert_eq!(parse(BlockPosArg, "1 2 ^"), Err(CommandError::new("Relative coordinates are not supported", 4)));
+        assert_eq!(parse(BlockPosArg, "1 y 3"), Err(CommandError::new("Invalid integer \"y\"", 2)));
+    }
+
+    #[test]
+    fn one_of_lists_its_choices() {
+        let arg = || OneOf::new([ ("survival", 0), ("creative", 1) ]);
+        assert_eq!(parse(arg(), "creative x"), Ok((1, 8,)));
+        assert_eq!(parse(arg(), "Creative"), Err(CommandError::new("Expected one of: survival, creative", 0)));
+        assert_eq!(parse(arg(), ""), Err(CommandError::new("Expected one of: survival, creative", 0)));
+        assert_eq!(arg().suggestions(), [ "survival", "creative" ]);
+    }
+
+    #[test]
+    fn greedy_strings_read_the_rest() {
+        assert_eq!(parse(GreedyStringArg, "hello  wörld "), Ok(("hello  wörld ".to_string(), 14,)));
+        assert_eq!(parse(GreedyStringArg, ""), Err(CommandError::new("Expected some text", 0)));
+        let mut reader = CommandReader::new("say hi there");
+        reader.read_word();
+        reader.skip();
+        assert_eq!(GreedyStringArg.parse(&mut reader), Ok("hi there".to_string()));
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn words_end_at_spaces() {
+        assert_eq!(parse(WordArg, "héllo world"), Ok(("héllo".to_string(), 6,)));
+        assert_eq!(parse(WordArg, " world"), Err(CommandError::new("Expected a word", 0)));
+    }
+
+}
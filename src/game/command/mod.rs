@@ -0,0 +1,590 @@
+//! Commands which players can run from chat, such as `/spawn`.
+//!
+//! A command is a tree of [`Command`] nodes, like the commands of a vanilla server. Each node is
+//!  either a literal word, or an argument parsed by an [`ArgumentType`]. Nodes with a handler
+//!  can be executed, and the handler receives the parsed arguments as [`CommandArgs`]:
+//! ```ignore
+//! app.command(Command::new("give")
+//!     .requires(|player : Player| is_admin(player))
+//!     .then(Command::argument("target", PlayerArg)
+//!         .then(Command::argument("count", IntegerArg::range(1..=64))
+//!             .executes(give)
+//!         )
+//!     )
+//! );
+//!
+//! async fn give(player : Player, args : CommandArgs) -> Result<(), String> {
+//!     let target = args.get::<Player>("target");
+//!     let count  = args.get::<i64>("count");
+//!     ...
+//! }
+//! ```
+//!
+//! The tree is sent to each player's client when they join, so that their client can complete
+//!  commands as they are typed. If a command can not be parsed, the player is shown what went wrong
+//!  and how the command is used. If a handler returns an error, the error is shown to the player,
+//!  and passed to [`App::on_error`](crate::App::on_error) like the errors of other callbacks.
+
+
+use crate::{ AppContext, Handler };
+use crate::event::{ self, FlywheelEvent };
+use crate::game::{ Player, optional_imports };
+use crate::game::text::{ Text, Color, ClickEvent };
+use crate::task::{ self, TaskScope };
+use core::any::Any;
+use core::fmt;
+use core::error::Error;
+use core::pin::Pin;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+
+mod arg;
+pub use arg::{ ArgumentType, CommandReader, IntegerArg, WordArg, GreedyStringArg, BlockPosArg, PlayerArg, OneOf };
+
+
+optional_imports! {
+    fn flywheel_player_set_commands(session_id : u64, in_tree : usize, tree_len : u32);
+}
+
+
+/// Node kinds, as sent to the host.
+const NODE_ROOT     : u8 = 0;
+const NODE_LITERAL  : u8 = 1;
+const NODE_ARGUMENT : u8 = 2;
+
+
+type Requirement = dyn Fn(Player) -> bool;
+
+type Executor = dyn Fn(&AppContext, (Player, CommandArgs,)) -> Pin<Box<dyn Future<Output = Result<(), String>>>>;
+
+
+/// A node in a command tree.
+///
+/// See the [module documentation](self).
+pub struct Command {
+    name     : String,
+    argument : Option<Box<dyn ErasedArgument>>,
+    requires : Option<Rc<Requirement>>,
+    executes : Option<Rc<Executor>>,
+    children : Vec<Command>
+}
+
+impl Command {
+
+    /// Creates a new command named `name`, which players run with `/name`.
+    #[inline]
+    pub fn new(name : impl Into<String>) -> Self { Self::literal(name) }
+
+    /// Creates a new node which matches the word `name` exactly.
+    pub fn literal(name : impl Into<String>) -> Self {
+        Self { name : name.into(), argument : None, requires : None, executes : None, children : Vec::new() }
+    }
+
+    /// Creates a new node which parses an argument with `argument`.
+    ///
+    /// The parsed value can be taken from [`CommandArgs`] by `name`.
+    pub fn argument<A : ArgumentType>(name : impl Into<String>, argument : A) -> Self {
+        Self { argument : Some(Box::new(argument)), ..Self::literal(name) }
+    }
+
+    /// Only lets players for whom `predicate` returns `true` use this node and its children.
+    ///
+    /// Other players do not see the node at all. The predicate is checked when the tree is sent to
+    ///  a player and whenever they run a command, so [`AppContext::send_commands`] should be called
+    ///  when its result changes.
+    pub fn requires<F>(mut self, predicate : F) -> Self
+    where
+        F : Fn(Player) -> bool + 'static
+    {
+        self.requires = Some(Rc::new(predicate));
+        self
+    }
+
+    /// Sets the handler which is called when a command ends at this node.
+    ///
+    /// The handler takes the player who ran the command and the parsed [`CommandArgs`], followed by
+    ///  any [`FromContext`](crate::FromContext) arguments. It is cancelled if the player leaves.
+    pub fn executes<H, M>(mut self, handler : H) -> Self
+    where
+        H : Handler<(Player, CommandArgs,), M>
+    {
+        self.executes = Some(Rc::new(move |ctx, args| handler.call(ctx, args)));
+        self
+    }
+
+    /// Adds a child node, which is parsed after this one.
+    ///
+    /// Children are tried in the order they were added.
+    pub fn then(mut self, child : Command) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Returns the name of this node.
+    #[inline]
+    pub fn name(&self) -> &str { &self.name }
+
+    /// Returns `true` if this node matches a literal word, rather than parsing an argument.
+    #[inline]
+    pub fn is_literal(&self) -> bool { self.argument.is_none() }
+
+    fn can_use(&self, player : Player) -> bool {
+        self.requires.as_ref().is_none_or(|requires| requires(player))
+    }
+
+    /// Returns how this command is used by `player`, with one line for each way it can be run.
+    pub fn usage(&self, player : Player) -> Vec<String> {
+        let mut out = Vec::new();
+        if (self.can_use(player)) {
+            self.write_usage(player, &mut format!("/{}", self.name), &mut out);
+        }
+        out
+    }
+
+    fn write_usage(&self, player : Player, prefix : &mut String, out : &mut Vec<String>) {
+        if (self.executes.is_some()) {
+            out.push(prefix.clone());
+        }
+        for child in self.children.iter().filter(|child| child.can_use(player)) {
+            let len = prefix.len();
+            if (child.is_literal()) {
+                prefix.push_str(&format!(" {}", child.name));
+            } else {
+                prefix.push_str(&format!(" <{}>", child.name));
+            }
+            child.write_usage(player, prefix, out);
+            prefix.truncate(len);
+        }
+    }
+
+    /// Parses the rest of a command after this node, returning the node it ends at.
+    fn parse(&self, player : Player, reader : &mut CommandReader<'_>, args : &mut CommandArgs) -> Result<&Self, Failure> {
+        if (reader.is_empty()) {
+            return if (self.executes.is_some()) { Ok(self) } else {
+                Err(Failure::literal(reader.error("Incomplete command")))
+            };
+        }
+        if (reader.peek() != Some(' ')) {
+            return Err(Failure::argument(reader.error("Expected a space to end the argument")));
+        }
+        reader.skip();
+        let start = reader.cursor();
+        let mut best : Option<Failure> = None;
+        for child in self.children.iter().filter(|child| child.can_use(player)) {
+            reader.set_cursor(start);
+            let len    = args.values.len();
+            let result = child.parse_token(reader, args)
+                .and_then(|_| child.parse(player, reader, args));
+            match (result) {
+                Ok(node) => { return Ok(node); },
+                Err(failure) => {
+                    args.values.truncate(len);
+                    if (best.as_ref().is_none_or(|best| failure.outranks(best))) {
+                        best = Some(failure);
+                    }
+                }
+            }
+        }
+        reader.set_cursor(start);
+        Err(best.unwrap_or_else(|| Failure::literal(reader.error("Unexpected argument"))))
+    }
+
+    /// Parses the word or argument of this node.
+    fn parse_token(&self, reader : &mut CommandReader<'_>, args : &mut CommandArgs) -> Result<(), Failure> {
+        match (&self.argument) {
+            None => {
+                let start = reader.cursor();
+                if (reader.read_word() == self.name) { Ok(()) } else {
+                    reader.set_cursor(start);
+                    Err(Failure::literal(reader.error("Unknown or incomplete command")))
+                }
+            },
+            Some(argument) => {
+                let value = argument.parse(reader).map_err(Failure::argument)?;
+                args.values.push((Rc::from(self.name.as_str()), value,));
+                Ok(())
+            }
+        }
+    }
+
+    /// Encodes this node and its children for the host, returning the index of this node.
+    fn encode(&self, player : Player, nodes : &mut Vec<Vec<u8>>) -> u32 {
+        let index = nodes.len();
+        nodes.push(Vec::new());
+        let children = self.children.iter()
+            .filter(|child| child.can_use(player))
+            .map(|child| child.encode(player, nodes))
+            .collect::<Vec<_>>();
+        let out = &mut nodes[index];
+        out.push(if (self.is_literal()) { NODE_LITERAL } else { NODE_ARGUMENT });
+        out.push(self.executes.is_some() as u8);
+        encode_children(out, &children);
+        encode_str(out, &self.name);
+        if let Some(argument) = &self.argument {
+            encode_str(out, argument.parser());
+            let mut properties = Vec::new();
+            argument.properties(&mut properties);
+            out.extend((properties.len() as u32).to_le_bytes());
+            out.extend(properties);
+            let suggestions = argument.suggestions();
+            out.extend((suggestions.len() as u32).to_le_bytes());
+            for suggestion in &suggestions {
+                encode_str(out, suggestion);
+            }
+        }
+        index as u32
+    }
+
+}
+
+
+/// The arguments parsed from a command, by the name of their node.
+#[derive(Clone)]
+pub struct CommandArgs {
+    input  : Rc<str>,
+    values : Vec<(Rc<str>, Rc<dyn Any>,)>
+}
+
+impl CommandArgs {
+
+    /// Returns the command the player typed, without the leading `/`.
+    #[inline]
+    pub fn input(&self) -> &str { &self.input }
+
+    /// Returns the value of the argument `name`.
+    ///
+    /// ### Panics
+    /// Panics if there is no argument `name`, or if its [`ArgumentType::Output`] is not `T`.
+    #[track_caller]
+    pub fn get<T : Clone + 'static>(&self, name : &str) -> T {
+        match (self.try_get(name)) {
+            Some(value) => value,
+            None        => panic!("command has no argument {name:?} of type `{}`", core::any::type_name::<T>())
+        }
+    }
+
+    /// Returns the value of the argument `name`, or `None` if there is no argument `name` of type `T`.
+    ///
+    /// Arguments on a branch of the tree which the command did not take are not present.
+    pub fn try_get<T : Clone + 'static>(&self, name : &str) -> Option<T> {
+        self.values.iter()
+            .find(|(arg_name, _,)| &**arg_name == name)
+            .and_then(|(_, value,)| value.downcast_ref::<T>())
+            .cloned()
+    }
+
+}
+
+
+/// An error from parsing a command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandError {
+    message : String,
+    cursor  : usize
+}
+
+impl CommandError {
+
+    /// Creates a new `CommandError`, pointing at byte `cursor` of the command.
+    pub fn new(message : impl Into<String>, cursor : usize) -> Self {
+        Self { message : message.into(), cursor }
+    }
+
+    /// Returns the error message.
+    #[inline]
+    pub fn message(&self) -> &str { &self.message }
+
+    /// Returns the byte in the command which the error points at.
+    #[inline]
+    pub fn cursor(&self) -> usize { self.cursor }
+
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.cursor)
+    }
+}
+
+impl Error for CommandError { }
+
+
+/// A parse error, and whether it came from a literal which did not match.
+///
+/// Errors further into the command are more useful, and errors from arguments are more
+///  useful than literals which did not match.
+struct Failure {
+    error   : CommandError,
+    literal : bool
+}
+
+impl Failure {
+    fn literal(error : CommandError) -> Self { Self { error, literal : true } }
+    fn argument(error : CommandError) -> Self { Self { error, literal : false } }
+    fn outranks(&self, other : &Self) -> bool {
+        (self.error.cursor, ! self.literal,) > (other.error.cursor, ! other.literal,)
+    }
+}
+
+
+/// An [`ArgumentType`] with its output boxed.
+trait ErasedArgument {
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<Rc<dyn Any>, CommandError>;
+    fn parser(&self) -> &'static str;
+    fn properties(&self, out : &mut Vec<u8>);
+    fn suggestions(&self) -> Vec<String>;
+}
+
+impl<A : ArgumentType> ErasedArgument for A {
+    fn parse(&self, reader : &mut CommandReader<'_>) -> Result<Rc<dyn Any>, CommandError> {
+        Ok(Rc::new(ArgumentType::parse(self, reader)?))
+    }
+    fn parser(&self) -> &'static str { ArgumentType::parser(self) }
+    fn properties(&self, out : &mut Vec<u8>) { ArgumentType::properties(self, out) }
+    fn suggestions(&self) -> Vec<String> { ArgumentType::suggestions(self) }
+}
+
+
+fn encode_str(out : &mut Vec<u8>, value : &str) {
+    out.extend((value.len() as u32).to_le_bytes());
+    out.extend(value.as_bytes());
+}
+
+fn encode_children(out : &mut Vec<u8>, children : &[u32]) {
+    out.extend((children.len() as u32).to_le_bytes());
+    for child in children {
+        out.extend(child.to_le_bytes());
+    }
+}
+
+
+/// Sends the commands in `commands` which `player` can use to their client.
+///
+/// The tree is sent as a `u32` node count, followed by each node. The root node is first. Each node is:
+/// - a `u8` kind: `0` for the root, `1` for a literal, or `2` for an argument,
+/// - a `u8` which is `1` if a command can end at the node,
+/// - a `u32` child count, followed by the `u32` index of each child,
+/// - and, except for the root, the name of the node.
+///
+/// Argument nodes are followed by the ID of their parser, their parser properties prefixed by
+///  their length as a `u32`, and a `u32` count of fixed suggestions, followed by each suggestion.
+///  Strings are prefixed by their length in bytes as a `u32`.
+///
+/// Does nothing if the host does not support [`Capabilities::COMMANDS`](crate::game::Capabilities::COMMANDS).
+pub(crate) fn send(commands : &BTreeMap<String, Rc<Command>>, player : Player) {
+    let Some(set_commands) = flywheel_player_set_commands() else { return; };
+    let mut nodes = vec![ Vec::new() ];
+    let roots = commands.values()
+        .filter(|command| command.can_use(player))
+        .map(|command| command.encode(player, &mut nodes))
+        .collect::<Vec<_>>();
+    nodes[0].push(NODE_ROOT);
+    nodes[0].push(0);
+    encode_children(&mut nodes[0], &roots);
+    let mut tree = Vec::with_capacity(4 + nodes.iter().map(Vec::len).sum::<usize>());
+    tree.extend((nodes.len() as u32).to_le_bytes());
+    for node in nodes {
+        tree.extend(node);
+    }
+    unsafe { set_commands(player.session_id(), tree.as_ptr() as usize, tree.len() as u32); }
+}
+
+/// Parses a command which `player` ran, and spawns its handler.
+///
+/// If the command can not be parsed, the player is told why instead. If the handler returns an
+///  error, it is shown to the player and passed to the error hook.
+pub(crate) fn execute(ctx : &AppContext, commands : &BTreeMap<String, Rc<Command>>, player : Player, input : &str) {
+    let input      = input.strip_prefix('/').unwrap_or(input);
+    let mut reader = CommandReader::new(input);
+    let name       = reader.read_word();
+    let Some(command) = commands.get(name).filter(|command| command.can_use(player)) else {
+        send_error(player, input, &CommandError::new("Unknown command", 0), &[]);
+        return;
+    };
+    let mut args = CommandArgs { input : Rc::from(input), values : Vec::new() };
+    let executes = match (command.parse(player, &mut reader, &mut args)) {
+        Ok(node) => node.executes.clone().expect("command node without a handler was parsed"),
+        Err(failure) => {
+            send_error(player, input, &failure.error, &command.usage(player));
+            return;
+        }
+    };
+    let fut = executes(ctx, (player, args,));
+    task::spawn_in(TaskScope::Player(player), ctx.report(&Rc::from(event::PlayerCommand::ID), Box::pin(async move {
+        let result = fut.await;
+        if let Err(message) = &result {
+            player.send_chat(Text::literal(message).color(Color::Red));
+        }
+        result
+    })));
+}
+
+/// Shows `player` where their command went wrong, and how it is used.
+fn send_error(player : Player, input : &str, error : &CommandError, usage : &[String]) {
+    let cursor  = (0..=error.cursor.min(input.len())).rev().find(|&i| input.is_char_boundary(i)).unwrap_or(0);
+    let context = input[..cursor].char_indices().rev().nth(9).map_or(&input[..cursor], |(i, _,)| &input[i..cursor]);
+    let mut text = Text::empty()
+        .append(Text::literal(format!("{}\n", error.message)).color(Color::Red))
+        .append(Text::literal(if (context.len() < cursor) { format!("...{context}") } else { format!("/{context}") }).color(Color::Gray))
+        .append(Text::literal(&input[cursor..]).color(Color::Red).underlined())
+        .append(Text::literal("<--[HERE]").color(Color::Red).italic());
+    if (! usage.is_empty()) {
+        text = text.append(Text::literal("\nUsage:").color(Color::Gold));
+        for line in usage {
+            text = text.append(Text::literal(format!("\n  {line}")).color(Color::Gray)
+                .click(ClickEvent::SuggestCommand(line.clone())));
+        }
+    }
+    player.send_chat(text);
+}
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use super::{ CommandReader, CommandError, IntegerArg, WordArg, GreedyStringArg, OneOf, send_error };
+    use crate::prelude::*;
+    use crate::testkit::{ self, HostCall };
+    use core::cell::RefCell;
+    use std::rc::Rc;
+
+    async fn ok(_ : Player, _ : CommandArgs) -> Result<(), String> { Ok(()) }
+
+    /// `/give <count> stacks`, `/give <name> <count>`, and a branch which no one can use.
+    fn give() -> Command {
+        Command::new("give")
+            .then(Command::argument("count", IntegerArg::new())
+                .then(Command::literal("stacks").executes(ok))
+            )
+            .then(Command::argument("name", WordArg)
+                .then(Command::argument("count", IntegerArg::range(1..=64)).executes(ok))
+            )
+            .then(Command::literal("secret").requires(|_| false).executes(ok))
+    }
+
+    /// Parses `input` with `command`, returning the name of the node it ends at.
+    fn parse(command : &Command, player : Player, input : &str) -> Result<(String, CommandArgs,), CommandError> {
+        let mut reader = CommandReader::new(input);
+        reader.read_word();
+        let mut args = CommandArgs { input : Rc::from(input), values : Vec::new() };
+        match (command.parse(player, &mut reader, &mut args)) {
+            Ok(node)     => Ok((node.name().to_string(), args,)),
+            Err(failure) => Err(failure.error)
+        }
+    }
+
+    fn parse_error(command : &Command, player : Player, input : &str) -> CommandError {
+        match (parse(command, player, input)) {
+            Ok((node, _,)) => panic!("{input:?} was parsed to {node:?}"),
+            Err(error)     => error
+        }
+    }
+
+    #[test]
+    fn parsing_backtracks_to_sibling_nodes() {
+        let command = give();
+        let player  = testkit::player_joined(Uuid::nil(), "a");
+
+        let (node, args,) = parse(&command, player, "give 5 stacks").unwrap();
+        assert_eq!(node, "stacks");
+        assert_eq!(args.try_get::<i64>("count"), Some(5));
+        assert_eq!(args.try_get::<String>("name"), None);
+
+        // `count` parses, but `stacks` does not match, so `name` is tried and the first `count` is dropped.
+        let (node, args,) = parse(&command, player, "give 5 3").unwrap();
+        assert_eq!(node, "count");
+        assert_eq!(args.try_get::<String>("name"), Some("5".to_string()));
+        assert_eq!(args.get::<i64>("count"), 3);
+        assert_eq!(args.values.len(), 2);
+    }
+
+    #[test]
+    fn parsing_reports_the_deepest_error() {
+        let command = give();
+        let player  = testkit::player_joined(Uuid::nil(), "a");
+        // Both branches fail at the same byte, and the argument error beats the literal which did not match.
+        assert_eq!(parse_error(&command, player, "give 5 x"), CommandError::new("Invalid integer \"x\"", 7));
+        assert_eq!(parse_error(&command, player, "give 5 65"), CommandError::new("Integer must not be more than 64, found 65", 7));
+        // The literal branch gets further than the argument error of the other branch.
+        assert_eq!(parse_error(&command, player, "give 5 stacks 1"), CommandError::new("Unexpected argument", 14));
+        assert_eq!(parse_error(&command, player, "give 5"), CommandError::new("Incomplete command", 6));
+        assert_eq!(parse_error(&command, player, "give"), CommandError::new("Incomplete command", 4));
+        assert_eq!(parse_error(&command, player, "give  5"), CommandError::new("Expected an integer", 5));
+        // Nodes which the player can not use are skipped, so `secret` is read as a `name`.
+        assert_eq!(parse_error(&command, player, "give secret"), CommandError::new("Incomplete command", 11));
+    }
+
+    #[test]
+    fn usage_lists_each_way_to_run_a_command() {
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        assert_eq!(give().usage(player), [ "/give <count> stacks", "/give <name> <count>" ]);
+        assert_eq!(give().executes(ok).usage(player), [ "/give", "/give <count> stacks", "/give <name> <count>" ]);
+        assert!(give().requires(|_| false).usage(player).is_empty());
+    }
+
+    #[test]
+    fn trees_are_sent_when_players_join() {
+        let mut app = App::new();
+        app.command(give());
+        app.command(Command::new("hidden").requires(|_| false).executes(ok));
+        app.command(Command::new("say")
+            .then(Command::argument("mode", OneOf::new([ ("loud", true), ("quiet", false) ]))
+                .then(Command::argument("message", GreedyStringArg).executes(ok))
+            )
+        );
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        app.run();
+        assert!(testkit::take_calls().contains(&HostCall::SetCommands {
+            session_id : player.session_id(),
+            usage      : vec![
+                "/give <count> stacks".to_string(),
+                "/give <name> <count>".to_string(),
+                "/say <mode> <message>".to_string()
+            ]
+        }));
+    }
+
+    #[test]
+    fn errors_point_at_character_boundaries() {
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        testkit::take_calls();
+        // The cursor is inside `é`, so it is moved back to the start of it.
+        send_error(player, "aé b", &CommandError::new("Bad", 2), &[]);
+        send_error(player, "aé b", &CommandError::new("Bad", 99), &[]);
+        // Only the last ten characters before the cursor are shown.
+        send_error(player, "ééééééééééééé x", &CommandError::new("Bad", 26), &[ "/é <x>".to_string() ]);
+        let msgs = testkit::take_calls().into_iter().map(|call| match (call) {
+            HostCall::Chat { msg, .. } => msg,
+            call                       => panic!("unexpected call {call:?}")
+        }).collect::<Vec<_>>();
+        assert_eq!(msgs, [
+            "<red>Bad\n</><gray>/a</><red><u>é b</></><red><i>&lt;--[HERE]</></>",
+            "<red>Bad\n</><gray>/aé b</><red><u></></><red><i>&lt;--[HERE]</></>",
+            concat!(
+                "<red>Bad\n</><gray>...éééééééééé</><red><u> x</></><red><i>&lt;--[HERE]</></>",
+                "<gold>\nUsage:</><gray><click action=\"suggest_command\" value=\"/é &lt;x&gt;\">\n  /é &lt;x&gt;</></>"
+            )
+        ]);
+    }
+
+    #[test]
+    fn handler_errors_reach_the_error_hook() {
+        let errors  = Rc::new(RefCell::new(Vec::new()));
+        let mut app = App::new();
+        app.on_error({
+            let errors = Rc::clone(&errors);
+            move |err| errors.borrow_mut().push((err.event().to_string(), err.message().to_string(),))
+        });
+        app.command(Command::new("fail").executes(|_ : Player, _ : CommandArgs| async { Err::<(), _>("nope") }));
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        app.run();
+        testkit::take_calls();
+        testkit::player_command(player, "/fail");
+        app.run();
+        assert_eq!(*errors.borrow(), [("flywheel_player_command".to_string(), "nope".to_string(),)]);
+        assert_eq!(testkit::take_calls(), [
+            HostCall::Chat { session_id : player.session_id(), msg : "<red>nope</>".to_string() }
+        ]);
+    }
+
+}
@@ -2,7 +2,13 @@
 //!  but are used for certian operations.
 
 
+use super::blocks::{ self, BlockType, BlockError, Property };
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::{ Add, AddAssign, Sub, SubAssign, Neg, Mul, MulAssign, Div, DivAssign };
+use core::str::FromStr;
 use std::collections::HashMap;
+use std::error::Error;
 
 
 /// A chunk position in the world.
@@ -47,6 +53,88 @@ impl ChunkPos {
     #[inline]
     pub const fn min_block(&self) -> BlockPos { BlockPos::new((self.x as i64) * 16, 0, (self.z as i64) * 16) }
 
+    /// Returns the world position of `local`, a position relative to the chunk.
+    ///
+    /// `local.x` and `local.z` are usually from `0` to `15`, and `local.y` is the world height.
+    ///  This is the inverse of [`BlockPos::local_in_chunk`].
+    #[inline]
+    pub const fn block_at(&self, local : BlockPos) -> BlockPos {
+        BlockPos::new((self.x as i64) * 16 + local.x, local.y, (self.z as i64) * 16 + local.z)
+    }
+
+    /// Returns the section of this chunk at section height `y`.
+    #[inline]
+    pub const fn section(&self, y : i32) -> SectionPos { SectionPos::new(self.x, y, self.z) }
+
+    /// Returns the blocks in this chunk between heights `min_y` and `max_y`, including both.
+    #[inline]
+    pub const fn block_box(&self, min_y : i64, max_y : i64) -> BlockBox {
+        BlockBox::new(self.block_at(BlockPos::new(0, min_y, 0)), self.block_at(BlockPos::new(15, max_y, 15)))
+    }
+
+    /// Returns an iterator over every block in this chunk between heights `min_y` and `max_y`,
+    ///  including both, by `y`, then `z`, then `x`.
+    #[inline]
+    pub fn blocks(&self, min_y : i64, max_y : i64) -> impl Iterator<Item = BlockPos> + use<> {
+        self.block_box(min_y, max_y).iter()
+    }
+
+}
+
+
+/// A chunk section position in the world, which is a 16×16×16 part of a chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SectionPos {
+    /// The x position of this `SectionPos`, which is the x position of its chunk.
+    pub x : i32,
+    /// The y position of this `SectionPos`, which covers block heights `y * 16` to `y * 16 + 15`.
+    pub y : i32,
+    /// The z position of this `SectionPos`, which is the z position of its chunk.
+    pub z : i32
+}
+
+impl SectionPos {
+
+    /// The section at the world origin (`<0, 0, 0>`).
+    pub const ZERO : Self = Self::splat(0);
+
+    /// Create a new `SectionPos` with the given `x`, `y`, and `z` values.
+    #[inline]
+    pub const fn new(x : i32, y : i32, z : i32) -> Self { Self { x, y, z } }
+
+    /// Create a new `SectionPos` with the given value as `x`, `y` and `z`.
+    #[inline(always)]
+    pub const fn splat(v : i32) -> Self { Self::new(v, v, v) }
+
+    /// Gets the `ChunkPos` that this `SectionPos` is part of.
+    #[inline]
+    pub const fn chunk(&self) -> ChunkPos { ChunkPos::new(self.x, self.z) }
+
+    /// The minimum (most negative `x`, `y`, and `z`) `BlockPos` in this section.
+    #[inline]
+    pub const fn min_block(&self) -> BlockPos { BlockPos::new((self.x as i64) * 16, (self.y as i64) * 16, (self.z as i64) * 16) }
+
+    /// The maximum (most positive `x`, `y`, and `z`) `BlockPos` in this section.
+    #[inline]
+    pub const fn max_block(&self) -> BlockPos { self.block_at(BlockPos::splat(15)) }
+
+    /// Returns the world position of `local`, a position from `<0, 0, 0>` to `<15, 15, 15>` relative to the section.
+    ///
+    /// This is the inverse of [`BlockPos::local_in_section`].
+    #[inline]
+    pub const fn block_at(&self, local : BlockPos) -> BlockPos {
+        let min = self.min_block();
+        BlockPos::new(min.x + local.x, min.y + local.y, min.z + local.z)
+    }
+
+    /// Returns the blocks in this section.
+    #[inline]
+    pub const fn block_box(&self) -> BlockBox { BlockBox::new(self.min_block(), self.max_block()) }
+
+    /// Returns an iterator over every block in this section, by `y`, then `z`, then `x`.
+    #[inline]
+    pub fn blocks(&self) -> impl Iterator<Item = BlockPos> + use<> { self.block_box().iter() }
+
 }
 
 
@@ -99,11 +187,646 @@ impl BlockPos {
     pub const fn west(&self, offset : i64) -> Self { Self::new(self.x - offset, self.y, self.z) }
 
     /// Gets the `ChunkPos` that this `BlockPos` falls in.
+    ///
+    /// This rounds down, so `x = -1` is in chunk `x = -1`.
+    #[inline]
+    pub const fn chunk(&self) -> ChunkPos { ChunkPos::new(self.x.div_euclid(16) as i32, self.z.div_euclid(16) as i32) }
+
+    /// Gets the `SectionPos` that this `BlockPos` falls in.
+    ///
+    /// This rounds down, so `y = -1` is in section `y = -1`.
+    #[inline]
+    pub const fn section(&self) -> SectionPos {
+        SectionPos::new(self.x.div_euclid(16) as i32, self.y.div_euclid(16) as i32, self.z.div_euclid(16) as i32)
+    }
+
+    /// Returns this position relative to its chunk, with `x` and `z` from `0` to `15`.
+    ///
+    /// `y` is not changed. This is the inverse of [`ChunkPos::block_at`].
+    #[inline]
+    pub const fn local_in_chunk(&self) -> BlockPos { BlockPos::new(self.x.rem_euclid(16), self.y, self.z.rem_euclid(16)) }
+
+    /// Returns this position relative to its section, with `x`, `y`, and `z` from `0` to `15`.
+    ///
+    /// This is the inverse of [`SectionPos::block_at`].
+    #[inline]
+    pub const fn local_in_section(&self) -> BlockPos { BlockPos::new(self.x.rem_euclid(16), self.y.rem_euclid(16), self.z.rem_euclid(16)) }
+
+    /// Returns a new `BlockPos` shifted `offset` blocks in `direction`.
+    #[inline]
+    pub const fn offset(&self, direction : Direction, offset : i64) -> Self {
+        let unit = direction.vector();
+        Self::new(self.x + unit.x * offset, self.y + unit.y * offset, self.z + unit.z * offset)
+    }
+
+    /// Returns the six blocks which share a face with this one, in the order of [`Direction::ALL`].
+    #[inline]
+    pub const fn neighbors(&self) -> [Self; 6] {
+        let [a, b, c, d, e, f] = Direction::ALL;
+        [self.offset(a, 1), self.offset(b, 1), self.offset(c, 1), self.offset(d, 1), self.offset(e, 1), self.offset(f, 1)]
+    }
+
+    /// Returns the number of blocks between this position and `other`, moving only along the axes.
+    ///
+    /// Saturates at `u64::MAX`, which only very distant positions can reach.
+    pub const fn manhattan_distance(&self, other : Self) -> u64 {
+        self.x.abs_diff(other.x).saturating_add(self.y.abs_diff(other.y)).saturating_add(self.z.abs_diff(other.z))
+    }
+
+    /// Returns the number of blocks between this position and `other`, moving along the axes and diagonals.
+    pub const fn chebyshev_distance(&self, other : Self) -> u64 {
+        let (x, y, z,) = (self.x.abs_diff(other.x), self.y.abs_diff(other.y), self.z.abs_diff(other.z),);
+        let xz = if (x > z) { x } else { z };
+        if (xz > y) { xz } else { y }
+    }
+
+    /// Returns the square of the straight-line distance between this position and `other`.
+    ///
+    /// Saturates at `u128::MAX`, which only very distant positions can reach.
+    pub const fn distance_squared(&self, other : Self) -> u128 {
+        let x = self.x.abs_diff(other.x) as u128;
+        let y = self.y.abs_diff(other.y) as u128;
+        let z = self.z.abs_diff(other.z) as u128;
+        x.saturating_mul(x).saturating_add(y.saturating_mul(y)).saturating_add(z.saturating_mul(z))
+    }
+
+    /// Returns this position turned by `rotation` around the y axis through `<0, 0, 0>`.
+    pub const fn rotate(&self, rotation : Rotation) -> Self {
+        match (rotation) {
+            Rotation::None               => *self,
+            Rotation::Clockwise90        => Self::new(- self.z, self.y,    self.x),
+            Rotation::Clockwise180       => Self::new(- self.x, self.y, - self.z),
+            Rotation::CounterClockwise90 => Self::new(   self.z, self.y, - self.x)
+        }
+    }
+
+    /// Returns this position reflected by `mirror` in a plane through `<0, 0, 0>`.
+    pub const fn mirror(&self, mirror : Mirror) -> Self {
+        match (mirror) {
+            Mirror::None      => *self,
+            Mirror::LeftRight => Self::new(self.x, self.y, - self.z),
+            Mirror::FrontBack => Self::new(- self.x, self.y, self.z)
+        }
+    }
+
+    /// Returns the position of the centre of this block.
+    #[inline]
+    pub fn center(&self) -> Vec3 { Vec3::new(self.x as f64 + 0.5, self.y as f64 + 0.5, self.z as f64 + 0.5) }
+
+}
+
+
+macro vector_ops( $ty:ident : $scalar:ty { $( $field:ident ),* } ) {
+    impl Add for $ty {
+        type Output = Self;
+        #[inline]
+        fn add(self, rhs : Self) -> Self { Self { $( $field : self.$field + rhs.$field ),* } }
+    }
+    impl AddAssign for $ty {
+        #[inline]
+        fn add_assign(&mut self, rhs : Self) { *self = *self + rhs; }
+    }
+    impl Sub for $ty {
+        type Output = Self;
+        #[inline]
+        fn sub(self, rhs : Self) -> Self { Self { $( $field : self.$field - rhs.$field ),* } }
+    }
+    impl SubAssign for $ty {
+        #[inline]
+        fn sub_assign(&mut self, rhs : Self) { *self = *self - rhs; }
+    }
+    impl Neg for $ty {
+        type Output = Self;
+        #[inline]
+        fn neg(self) -> Self { Self { $( $field : - self.$field ),* } }
+    }
+    impl Mul<$scalar> for $ty {
+        type Output = Self;
+        #[inline]
+        fn mul(self, rhs : $scalar) -> Self { Self { $( $field : self.$field * rhs ),* } }
+    }
+    impl MulAssign<$scalar> for $ty {
+        #[inline]
+        fn mul_assign(&mut self, rhs : $scalar) { *self = *self * rhs; }
+    }
+}
+
+vector_ops!( ChunkPos : i32 { x, z } );
+vector_ops!( SectionPos : i32 { x, y, z } );
+vector_ops!( BlockPos : i64 { x, y, z } );
+vector_ops!( Vec3 : f64 { x, y, z } );
+
+
+/// One of the six faces of a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Direction {
+    /// Negative y.
+    Down,
+    /// Positive y.
+    Up,
+    /// Negative z.
+    North,
+    /// Positive z.
+    South,
+    /// Negative x.
+    West,
+    /// Positive x.
+    East
+}
+
+impl Direction {
+
+    /// Every direction.
+    pub const ALL        : [Self; 6] = [Self::Down, Self::Up, Self::North, Self::South, Self::West, Self::East];
+    /// The four directions which are not [`Direction::Up`] or [`Direction::Down`], clockwise from [`Direction::North`].
+    pub const HORIZONTAL : [Self; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    /// Returns the direction facing the other way.
+    pub const fn opposite(self) -> Self {
+        match (self) {
+            Self::Down  => Self::Up,
+            Self::Up    => Self::Down,
+            Self::North => Self::South,
+            Self::South => Self::North,
+            Self::West  => Self::East,
+            Self::East  => Self::West
+        }
+    }
+
+    /// Returns this direction rotated a quarter turn clockwise, looking down from above.
+    ///
+    /// [`Direction::Up`] and [`Direction::Down`] are not changed.
+    pub const fn rotate_clockwise(self) -> Self {
+        match (self) {
+            Self::North => Self::East,
+            Self::East  => Self::South,
+            Self::South => Self::West,
+            Self::West  => Self::North,
+            vertical    => vertical
+        }
+    }
+
+    /// Returns this direction rotated a quarter turn counter-clockwise, looking down from above.
+    ///
+    /// [`Direction::Up`] and [`Direction::Down`] are not changed.
+    pub const fn rotate_counter_clockwise(self) -> Self {
+        match (self) {
+            Self::North => Self::West,
+            Self::West  => Self::South,
+            Self::South => Self::East,
+            Self::East  => Self::North,
+            vertical    => vertical
+        }
+    }
+
+    /// Returns the axis this direction is along.
+    pub const fn axis(self) -> Axis {
+        match (self) {
+            Self::Down  | Self::Up    => Axis::Y,
+            Self::North | Self::South => Axis::Z,
+            Self::West  | Self::East  => Axis::X
+        }
+    }
+
+    /// Returns `true` if this direction is not [`Direction::Up`] or [`Direction::Down`].
+    #[inline]
+    pub const fn is_horizontal(self) -> bool { ! matches!(self, Self::Up | Self::Down) }
+
+    /// Returns the offset of the block one step in this direction.
+    pub const fn vector(self) -> BlockPos {
+        match (self) {
+            Self::Down  => BlockPos::new( 0, -1,  0),
+            Self::Up    => BlockPos::new( 0,  1,  0),
+            Self::North => BlockPos::new( 0,  0, -1),
+            Self::South => BlockPos::new( 0,  0,  1),
+            Self::West  => BlockPos::new(-1,  0,  0),
+            Self::East  => BlockPos::new( 1,  0,  0)
+        }
+    }
+
+    /// Returns this direction turned by `rotation`.
+    pub const fn rotate(self, rotation : Rotation) -> Self {
+        match (rotation) {
+            Rotation::None               => self,
+            Rotation::Clockwise90        => self.rotate_clockwise(),
+            Rotation::Clockwise180       => if (self.is_horizontal()) { self.opposite() } else { self },
+            Rotation::CounterClockwise90 => self.rotate_counter_clockwise()
+        }
+    }
+
+    /// Returns this direction reflected by `mirror`.
+    pub const fn mirror(self, mirror : Mirror) -> Self {
+        match (mirror, self,) {
+            (Mirror::LeftRight, Self::North | Self::South,) => self.opposite(),
+            (Mirror::FrontBack, Self::West | Self::East,)   => self.opposite(),
+            _                                               => self
+        }
+    }
+
+    /// Returns the direction named `name`, as it is written in block states.
+    fn from_name(name : &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|direction| direction.as_str() == name)
+    }
+
+    /// Returns the name of this direction as it is written in block states, such as `north`.
+    pub const fn as_str(self) -> &'static str {
+        match (self) {
+            Self::Down  => "down",
+            Self::Up    => "up",
+            Self::North => "north",
+            Self::South => "south",
+            Self::West  => "west",
+            Self::East  => "east"
+        }
+    }
+
+}
+
+
+/// A rotation around the y axis, clockwise looking down from above.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Rotation {
+    /// No rotation.
+    #[default]
+    None,
+    /// A quarter turn clockwise, so north becomes east.
+    Clockwise90,
+    /// A half turn.
+    Clockwise180,
+    /// A quarter turn counter-clockwise, so north becomes west.
+    CounterClockwise90
+}
+
+impl Rotation {
+
+    /// Returns the rotation which undoes this one.
+    pub const fn inverse(self) -> Self {
+        match (self) {
+            Self::Clockwise90        => Self::CounterClockwise90,
+            Self::CounterClockwise90 => Self::Clockwise90,
+            other                    => other
+        }
+    }
+
+    /// Returns the number of quarter turns clockwise.
+    const fn quarter_turns(self) -> i64 {
+        match (self) {
+            Self::None               => 0,
+            Self::Clockwise90        => 1,
+            Self::Clockwise180       => 2,
+            Self::CounterClockwise90 => 3
+        }
+    }
+
+}
+
+
+/// A reflection in a vertical plane, named the same as in vanilla structure blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Mirror {
+    /// No reflection.
+    #[default]
+    None,
+    /// Swaps north and south (negates `z`).
+    LeftRight,
+    /// Swaps east and west (negates `x`).
+    FrontBack
+}
+
+
+/// An axis of the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Axis {
+    /// East and west.
+    X,
+    /// Up and down.
+    Y,
+    /// North and south.
+    Z
+}
+
+
+/// A position in the world which is not snapped to blocks, such as the position of an entity or particle.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Default)]
+pub struct Vec3 {
+    /// The x position of this `Vec3`.
+    pub x : f64,
+    /// The y position of this `Vec3`.
+    pub y : f64,
+    /// The z position of this `Vec3`.
+    pub z : f64
+}
+
+impl Vec3 {
+
+    /// The world origin (`<0, 0, 0>`).
+    pub const ZERO : Self = Self::splat(0.0);
+
+    /// Create a new `Vec3` with the given `x`, `y`, and `z` values.
+    #[inline]
+    pub const fn new(x : f64, y : f64, z : f64) -> Self { Self { x, y, z } }
+
+    /// Create a new `Vec3` with the given value as `x`, `y` and `z`.
+    #[inline(always)]
+    pub const fn splat(v : f64) -> Self { Self::new(v, v, v) }
+
+    /// Returns the dot product of this vector and `other`.
+    #[inline]
+    pub const fn dot(&self, other : Self) -> f64 { self.x * other.x + self.y * other.y + self.z * other.z }
+
+    /// Returns the square of the length of this vector.
+    #[inline]
+    pub const fn length_squared(&self) -> f64 { self.dot(*self) }
+
+    /// Returns the length of this vector.
     #[inline]
-    pub const fn chunk(&self) -> ChunkPos { ChunkPos::new((self.x / 16) as i32, (self.z / 16) as i32) }
+    pub fn length(&self) -> f64 { self.length_squared().sqrt() }
+
+    /// Returns the square of the distance between this position and `other`.
+    #[inline]
+    pub fn distance_squared(&self, other : Self) -> f64 { (*self - other).length_squared() }
+
+    /// Returns the distance between this position and `other`.
+    #[inline]
+    pub fn distance(&self, other : Self) -> f64 { (*self - other).length() }
+
+    /// Returns this vector scaled to a length of `1`, or `None` if its length is `0`.
+    pub fn normalize(&self) -> Option<Self> {
+        let length = self.length();
+        (length > 0.0 && length.is_finite()).then(|| *self / length)
+    }
+
+    /// Returns the block this position is in, rounding down.
+    #[inline]
+    pub fn block(&self) -> BlockPos { BlockPos::new(self.x.floor() as i64, self.y.floor() as i64, self.z.floor() as i64) }
 
 }
 
+impl Div<f64> for Vec3 {
+    type Output = Self;
+    #[inline]
+    fn div(self, rhs : f64) -> Self { Self::new(self.x / rhs, self.y / rhs, self.z / rhs) }
+}
+
+impl DivAssign<f64> for Vec3 {
+    #[inline]
+    fn div_assign(&mut self, rhs : f64) { *self = *self / rhs; }
+}
+
+/// The position of the minimum corner of the block.
+impl From<BlockPos> for Vec3 {
+    fn from(pos : BlockPos) -> Self { Self::new(pos.x as f64, pos.y as f64, pos.z as f64) }
+}
+
+
+/// A box of blocks, including both its minimum and maximum corners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockBox {
+    min : BlockPos,
+    max : BlockPos
+}
+
+impl BlockBox {
+
+    /// Creates a new `BlockBox` between two opposite corners, in any order.
+    pub const fn new(a : BlockPos, b : BlockPos) -> Self {
+        Self {
+            min : BlockPos::new(min(a.x, b.x), min(a.y, b.y), min(a.z, b.z)),
+            max : BlockPos::new(max(a.x, b.x), max(a.y, b.y), max(a.z, b.z))
+        }
+    }
+
+    /// Returns the corner with the lowest `x`, `y`, and `z`.
+    #[inline]
+    pub const fn min(&self) -> BlockPos { self.min }
+
+    /// Returns the corner with the highest `x`, `y`, and `z`.
+    #[inline]
+    pub const fn max(&self) -> BlockPos { self.max }
+
+    /// Returns `true` if `pos` is in this box.
+    pub const fn contains(&self, pos : BlockPos) -> bool {
+        self.min.x <= pos.x && pos.x <= self.max.x
+        && self.min.y <= pos.y && pos.y <= self.max.y
+        && self.min.z <= pos.z && pos.z <= self.max.z
+    }
+
+    /// Returns `true` if `pos` is on one of the faces of this box.
+    pub const fn is_on_surface(&self, pos : BlockPos) -> bool {
+        self.contains(pos) && (
+            pos.x == self.min.x || pos.x == self.max.x
+            || pos.y == self.min.y || pos.y == self.max.y
+            || pos.z == self.min.z || pos.z == self.max.z
+        )
+    }
+
+    /// Returns the number of blocks in this box.
+    ///
+    /// Saturates at `u128::MAX`, which only very large boxes can reach.
+    pub const fn volume(&self) -> u128 {
+        let x = self.max.x.abs_diff(self.min.x) as u128 + 1;
+        let y = self.max.y.abs_diff(self.min.y) as u128 + 1;
+        let z = self.max.z.abs_diff(self.min.z) as u128 + 1;
+        x.saturating_mul(y).saturating_mul(z)
+    }
+
+    /// Returns the box which is in both this box and `other`, if they overlap.
+    pub const fn intersect(&self, other : &Self) -> Option<Self> {
+        let lo = BlockPos::new(max(self.min.x, other.min.x), max(self.min.y, other.min.y), max(self.min.z, other.min.z));
+        let hi = BlockPos::new(min(self.max.x, other.max.x), min(self.max.y, other.max.y), min(self.max.z, other.max.z));
+        if (lo.x > hi.x || lo.y > hi.y || lo.z > hi.z) { return None; }
+        Some(Self { min : lo, max : hi })
+    }
+
+    /// Returns the smallest box which contains both this box and `other`.
+    pub const fn union(&self, other : &Self) -> Self {
+        Self {
+            min : BlockPos::new(min(self.min.x, other.min.x), min(self.min.y, other.min.y), min(self.min.z, other.min.z)),
+            max : BlockPos::new(max(self.max.x, other.max.x), max(self.max.y, other.max.y), max(self.max.z, other.max.z))
+        }
+    }
+
+    /// Returns this box grown by `amount` blocks on every side.
+    ///
+    /// Saturates at the limits of `i64`.
+    pub const fn expand(&self, amount : u64) -> Self {
+        Self {
+            min : BlockPos::new(self.min.x.saturating_sub_unsigned(amount), self.min.y.saturating_sub_unsigned(amount), self.min.z.saturating_sub_unsigned(amount)),
+            max : BlockPos::new(self.max.x.saturating_add_unsigned(amount), self.max.y.saturating_add_unsigned(amount), self.max.z.saturating_add_unsigned(amount))
+        }
+    }
+
+    /// Returns this box shrunk by `amount` blocks on every side, or `None` if nothing would be left.
+    pub const fn contract(&self, amount : u64) -> Option<Self> {
+        let lo = BlockPos::new(self.min.x.saturating_add_unsigned(amount), self.min.y.saturating_add_unsigned(amount), self.min.z.saturating_add_unsigned(amount));
+        let hi = BlockPos::new(self.max.x.saturating_sub_unsigned(amount), self.max.y.saturating_sub_unsigned(amount), self.max.z.saturating_sub_unsigned(amount));
+        if (lo.x > hi.x || lo.y > hi.y || lo.z > hi.z) { return None; }
+        Some(Self { min : lo, max : hi })
+    }
+
+    /// Returns this box moved by `offset`.
+    #[inline]
+    pub const fn translate(&self, offset : BlockPos) -> Self {
+        Self {
+            min : BlockPos::new(self.min.x + offset.x, self.min.y + offset.y, self.min.z + offset.z),
+            max : BlockPos::new(self.max.x + offset.x, self.max.y + offset.y, self.max.z + offset.z)
+        }
+    }
+
+    /// Returns an iterator over every block in this box, by `y`, then `z`, then `x`.
+    #[inline]
+    pub const fn iter(&self) -> BlockBoxIter { BlockBoxIter { region : *self, next : Some(self.min) } }
+
+    /// Returns the part of this box in `chunk`, if any.
+    pub const fn in_chunk(&self, chunk : ChunkPos) -> Option<Self> {
+        self.intersect(&chunk.block_box(self.min.y, self.max.y))
+    }
+
+    /// Returns an iterator over every chunk this box is in, and the part of this box in that chunk.
+    ///
+    /// Chunks are ordered by `z`, then `x`.
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkPos, BlockBox,)> + use<> {
+        let region      = *self;
+        let (min, max,) = (self.min.chunk(), self.max.chunk(),);
+        (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| {
+            let chunk = ChunkPos::new(x, z);
+            (chunk, region.in_chunk(chunk).expect("chunk should overlap region"),)
+        }))
+    }
+
+}
+
+impl IntoIterator for BlockBox {
+    type Item     = BlockPos;
+    type IntoIter = BlockBoxIter;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+impl IntoIterator for &BlockBox {
+    type Item     = BlockPos;
+    type IntoIter = BlockBoxIter;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.iter() }
+}
+
+
+/// An iterator over every block in a [`BlockBox`], by `y`, then `z`, then `x`.
+///
+/// Created by [`BlockBox::iter`].
+#[derive(Clone, Debug)]
+pub struct BlockBoxIter {
+    region : BlockBox,
+    next   : Option<BlockPos>
+}
+
+impl Iterator for BlockBoxIter {
+    type Item = BlockPos;
+
+    fn next(&mut self) -> Option<BlockPos> {
+        let pos = self.next?;
+        let (min, max,) = (self.region.min, self.region.max,);
+        self.next = if (pos.x < max.x) {
+            Some(BlockPos::new(pos.x + 1, pos.y, pos.z))
+        } else if (pos.z < max.z) {
+            Some(BlockPos::new(min.x, pos.y, pos.z + 1))
+        } else if (pos.y < max.y) {
+            Some(BlockPos::new(min.x, pos.y + 1, min.z))
+        } else {
+            None
+        };
+        Some(pos)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let Some(pos) = self.next else { return (0, Some(0),) };
+        let (min, max,) = (self.region.min, self.region.max,);
+        let width       = max.x.abs_diff(min.x) as u128 + 1;
+        let depth       = max.z.abs_diff(min.z) as u128 + 1;
+        // Blocks left in this row, then in this layer, then in the layers above.
+        let remaining = (max.x.abs_diff(pos.x) as u128 + 1)
+            + (max.z.abs_diff(pos.z) as u128) * width
+            + (max.y.abs_diff(pos.y) as u128) * width * depth;
+        match (usize::try_from(remaining)) {
+            Ok(remaining) => (remaining, Some(remaining),),
+            Err(_)        => (usize::MAX, None,)
+        }
+    }
+
+}
+
+impl FusedIterator for BlockBoxIter { }
+
+const fn min(a : i64, b : i64) -> i64 { if (a < b) { a } else { b } }
+const fn max(a : i64, b : i64) -> i64 { if (a > b) { a } else { b } }
+
+
+/// Which blocks [`RegionOp::Replace`] replaces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockFilter {
+    /// Blocks with this material ID, whatever their states.
+    Id(String),
+    /// Blocks with exactly this material ID and these states.
+    Exact(Block)
+}
+
+impl BlockFilter {
+
+    /// Returns `true` if `block` passes this filter.
+    pub fn matches(&self, block : &Block) -> bool {
+        match (self) {
+            Self::Id(id)       => block.id() == id,
+            Self::Exact(exact) => block == exact
+        }
+    }
+
+}
+
+
+/// An operation on a whole region of a world, which the host carries out without each block being sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegionOp {
+    /// Sets every block in `region` to `block`.
+    Fill {
+        /// The blocks to set.
+        region : BlockBox,
+        /// The block to set them to.
+        block  : Block
+    },
+    /// Sets every block in `region` which passes `filter` to `block`.
+    Replace {
+        /// The blocks to check.
+        region : BlockBox,
+        /// Which blocks to replace.
+        filter : BlockFilter,
+        /// The block to replace them with.
+        block  : Block
+    },
+    /// Sets the faces of `region` to `block`, and everything inside it to air.
+    Hollow {
+        /// The blocks to set.
+        region : BlockBox,
+        /// The block to set the faces to.
+        block  : Block
+    },
+    /// Sets the faces of `region` to `block`, leaving everything inside it as it is.
+    Outline {
+        /// The blocks to set.
+        region : BlockBox,
+        /// The block to set the faces to.
+        block  : Block
+    },
+    /// Copies every block in `src` so that its minimum corner is at `dst`.
+    ///
+    /// The regions may overlap.
+    Clone {
+        /// The blocks to copy.
+        src : BlockBox,
+        /// Where to copy the minimum corner of `src` to.
+        dst : BlockPos
+    }
+}
+
 
 /// The category that a played sound falls into.
 ///
@@ -137,6 +860,7 @@ pub enum SoundCategory {
 
 
 /// A world block, including material and states.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Block {
     id     : String,
     states : HashMap<String, String>
@@ -171,6 +895,116 @@ impl Block {
         self
     }
 
+    /// Sets a property of this block, if it is valid for the block according to [`blocks`].
+    ///
+    /// For a variant of this function which returns `self` see [`Self::try_with`].
+    pub fn try_set(&mut self, state : &str, value : &str) -> Result<&mut Self, BlockError> {
+        blocks::check(&self.id, state, value)?;
+        Ok(self.set(state, value))
+    }
+
+    /// Sets a property of this block, if it is valid for the block according to [`blocks`].
+    ///
+    /// ```ignore
+    /// let stairs = Block::new("minecraft:oak_stairs").try_with("facing", "north")?;
+    /// ```
+    ///
+    /// For a variant of this function which mutates `self` see [`Self::try_set`].
+    #[inline]
+    pub fn try_with(mut self, state : &str, value : &str) -> Result<Self, BlockError> {
+        self.try_set(state, value)?;
+        Ok(self)
+    }
+
+    /// Sets a typed property of this block, such as [`blocks::Facing`].
+    ///
+    /// Like [`Self::set`], this does not check that the block has the property.
+    #[inline]
+    pub fn set_property<P : Property>(&mut self, value : P) -> &mut Self {
+        self.set(P::NAME, value.as_str())
+    }
+
+    /// Sets a typed property of this block, such as [`blocks::Facing`].
+    ///
+    /// Like [`Self::with`], this does not check that the block has the property.
+    #[inline]
+    pub fn with_property<P : Property>(mut self, value : P) -> Self {
+        self.set_property(value);
+        self
+    }
+
+    /// Returns a typed property of this block if set.
+    pub fn property<P : Property>(&self) -> Option<P> {
+        self.get(P::NAME).and_then(P::parse)
+    }
+
+    /// Returns the type of this block, if it is in [`blocks`].
+    #[inline]
+    pub fn block_type(&self) -> Option<&'static BlockType> { blocks::get(&self.id) }
+
+    /// Checks that this block is in [`blocks`], and that every property set on it is valid.
+    pub fn validate(&self) -> Result<(), BlockError> {
+        if (self.block_type().is_none()) {
+            return Err(BlockError::UnknownId { id : self.id.clone() });
+        }
+        self.states().try_for_each(|(state, value,)| blocks::check(&self.id, state, value))
+    }
+
+    /// Returns this block turned by `rotation`, such as when the structure it is in is rotated.
+    ///
+    /// This changes the `facing`, `axis`, and `rotation` properties, rail shapes, and which
+    ///  sides connecting blocks like fences and walls connect on.
+    pub fn rotate(&self, rotation : Rotation) -> Self {
+        let turns = rotation.quarter_turns();
+        self.transform(
+            |direction| direction.rotate(rotation),
+            |value| (value + 4 * turns).rem_euclid(16),
+            turns % 2 == 1,
+            false
+        )
+    }
+
+    /// Returns this block reflected by `mirror`, such as when the structure it is in is mirrored.
+    ///
+    /// As well as the properties changed by [`Block::rotate`], this swaps left and right in
+    ///  stair shapes, door hinges, and double chests.
+    pub fn mirror(&self, mirror : Mirror) -> Self {
+        self.transform(
+            |direction| direction.mirror(mirror),
+            |value| match (mirror) {
+                Mirror::None      => value,
+                Mirror::LeftRight => (8 - value).rem_euclid(16),
+                Mirror::FrontBack => (16 - value).rem_euclid(16)
+            },
+            false,
+            mirror != Mirror::None
+        )
+    }
+
+    fn transform(&self, direction : impl Fn(Direction) -> Direction, rotation : impl Fn(i64) -> i64, swap_axes : bool, swap_hands : bool) -> Self {
+        let mut block = Block::new(&self.id);
+        for (state, value,) in self.states() {
+            let state = match (Direction::from_name(state)) {
+                Some(side) if (side.is_horizontal()) => direction(side).as_str(),
+                _                                    => state
+            };
+            let changed = match (state) {
+                "facing"              => Direction::from_name(value).map(|facing| direction(facing).as_str().to_string()),
+                "axis" if (swap_axes) => match (value) {
+                    "x" => Some("z".to_string()),
+                    "z" => Some("x".to_string()),
+                    _   => None
+                },
+                "rotation"                       => value.parse::<i64>().ok().filter(|value| (0..16).contains(value)).map(|value| rotation(value).to_string()),
+                "shape"                          => rail_shape(value, &direction).or_else(|| if (swap_hands) { swap_hand(value) } else { None }),
+                "hinge" | "type" if (swap_hands) => swap_hand(value),
+                _                                => None
+            };
+            block.set(state, changed.as_deref().unwrap_or(value));
+        }
+        block
+    }
+
     /// Returns a property of this block if set.
     pub fn get(&self, state : &str) -> Option<&str> {
         self.states.get(state).map(|s| s.as_str())
@@ -191,3 +1025,383 @@ impl Block {
 impl AsRef<Block> for Block {
     fn as_ref(&self) -> &Block { self }
 }
+
+/// Returns the rail shape `shape` with each of its directions changed by `direction`,
+///  or `None` if it is not a rail shape.
+fn rail_shape(shape : &str, direction : impl Fn(Direction) -> Direction) -> Option<String> {
+    if let Some(rest) = shape.strip_prefix("ascending_") {
+        let side = Direction::from_name(rest).filter(|side| side.is_horizontal())?;
+        return Some(format!("ascending_{}", direction(side).as_str()));
+    }
+    let (a, b,) = shape.split_once('_')?;
+    let a       = direction(Direction::from_name(a).filter(|side| side.is_horizontal())?);
+    let b       = direction(Direction::from_name(b).filter(|side| side.is_horizontal())?);
+    // Straight rails are written north then east, and curved rails are written north or south then east or west.
+    let (a, b,) = if (matches!(a, Direction::West | Direction::East) && (a.axis() != b.axis() || a == Direction::West)) { (b, a,) } else { (a, b,) };
+    let (a, b,) = if (a == Direction::South && b == Direction::North) { (b, a,) } else { (a, b,) };
+    Some(format!("{}_{}", a.as_str(), b.as_str()))
+}
+
+/// Swaps `left` and `right` in a property value, or returns `None` if it has neither.
+fn swap_hand(value : &str) -> Option<String> {
+    if let Some(rest) = value.strip_suffix("left") {
+        Some(format!("{rest}right"))
+    } else {
+        value.strip_suffix("right").map(|rest| format!("{rest}left"))
+    }
+}
+
+/// Formats this block as its ID followed by its states, such as `minecraft:oak_log[axis=y]`.
+///
+/// States are sorted by name, and the brackets are left out if there are no states.
+impl fmt::Display for Block {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)?;
+        if (! self.states.is_empty()) {
+            let mut states = self.states().collect::<Vec<_>>();
+            states.sort_unstable();
+            f.write_str("[")?;
+            for (i, (state, value,),) in states.into_iter().enumerate() {
+                if (i > 0) { f.write_str(",")?; }
+                write!(f, "{state}={value}")?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a block from its ID followed by its states, such as `minecraft:oak_log[axis=y]`.
+///
+/// Spaces are allowed around state names and values. The block is not checked against
+///  [`blocks`]; use [`Block::validate`] for that.
+impl FromStr for Block {
+    type Err = BlockParseError;
+    fn from_str(s : &str) -> Result<Self, Self::Err> {
+        let mut reader = BlockReader { s, pos : 0 };
+        let id         = reader.take_while(|ch| matches!(ch, 'a'..='z' | '0'..='9' | '_' | '-' | '.' | '/' | ':'));
+        if (id.is_empty() || id.matches(':').count() > 1 || id.starts_with(':') || id.ends_with(':')) {
+            return Err(BlockParseError::Expected { offset : 0, what : "a block ID" });
+        }
+        let mut block = Block::new(id);
+        if (reader.is_empty()) {
+            return Ok(block);
+        }
+        reader.expect('[', "`[` or the end of the block")?;
+        reader.skip_spaces();
+        if (! reader.eat(']')) {
+            loop {
+                reader.skip_spaces();
+                let offset = reader.pos;
+                let state  = reader.take_while(is_state_char);
+                if (state.is_empty()) {
+                    return Err(BlockParseError::Expected { offset, what : "a state name" });
+                }
+                reader.skip_spaces();
+                reader.expect('=', "`=`")?;
+                reader.skip_spaces();
+                let value = reader.take_while(is_state_char);
+                if (value.is_empty()) {
+                    return Err(BlockParseError::Expected { offset : reader.pos, what : "a state value" });
+                }
+                if (block.get(state).is_some()) {
+                    return Err(BlockParseError::DuplicateState { offset, state : state.to_string() });
+                }
+                if (block.states_len() >= 15) {
+                    return Err(BlockParseError::TooManyStates { offset });
+                }
+                block.set(state, value);
+                reader.skip_spaces();
+                if (reader.eat(']')) { break; }
+                reader.expect(',', "`,` or `]`")?;
+            }
+        }
+        if (! reader.is_empty()) {
+            return Err(BlockParseError::Expected { offset : reader.pos, what : "the end of the block" });
+        }
+        Ok(block)
+    }
+}
+
+fn is_state_char(ch : char) -> bool { matches!(ch, 'a'..='z' | '0'..='9' | '_') }
+
+/// A cursor over a block which is being parsed.
+struct BlockReader<'l> {
+    s   : &'l str,
+    pos : usize
+}
+
+impl<'l> BlockReader<'l> {
+
+    fn is_empty(&self) -> bool { self.pos == self.s.len() }
+
+    fn take_while(&mut self, f : impl Fn(char) -> bool) -> &'l str {
+        let rest = &self.s[self.pos..];
+        let len  = rest.find(|ch| ! f(ch)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn skip_spaces(&mut self) { self.take_while(|ch| ch == ' '); }
+
+    fn eat(&mut self, ch : char) -> bool {
+        let found = self.s[self.pos..].starts_with(ch);
+        if (found) { self.pos += ch.len_utf8(); }
+        found
+    }
+
+    fn expect(&mut self, ch : char, what : &'static str) -> Result<(), BlockParseError> {
+        if (self.eat(ch)) { Ok(()) } else { Err(BlockParseError::Expected { offset : self.pos, what }) }
+    }
+
+}
+
+
+/// An error returned when a [`Block`] could not be parsed from a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockParseError {
+    /// Something else was found where a part of the block was expected.
+    Expected {
+        /// The byte offset in the string.
+        offset : usize,
+        /// What was expected.
+        what   : &'static str
+    },
+    /// A state was given more than once.
+    DuplicateState {
+        /// The byte offset of the second state in the string.
+        offset : usize,
+        /// The name of the state.
+        state  : String
+    },
+    /// More than 15 states were given.
+    TooManyStates {
+        /// The byte offset of the 16th state in the string.
+        offset : usize
+    }
+}
+
+impl fmt::Display for BlockParseError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::Expected { offset, what }        => write!(f, "expected {what} at offset {offset}"),
+            Self::DuplicateState { offset, state } => write!(f, "duplicate state {state:?} at offset {offset}"),
+            Self::TooManyStates { offset }         => write!(f, "too many states at offset {offset}")
+        }
+    }
+}
+
+impl Error for BlockParseError { }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negative_positions_round_down() {
+        for (x, chunk, local,) in [(-1, -1, 15,), (-16, -1, 0,), (-17, -2, 15,)] {
+            let pos = BlockPos::new(x, -1, x);
+            assert_eq!(pos.chunk(), ChunkPos::new(chunk, chunk));
+            assert_eq!(pos.section(), SectionPos::new(chunk, -1, chunk));
+            assert_eq!(pos.local_in_chunk(), BlockPos::new(local, -1, local));
+            assert_eq!(pos.local_in_section(), BlockPos::new(local, 15, local));
+            assert_eq!(pos.chunk().block_at(pos.local_in_chunk()), pos);
+            assert_eq!(pos.section().block_at(pos.local_in_section()), pos);
+        }
+    }
+
+    #[test]
+    fn boxes_are_split_into_negative_chunks() {
+        let chunks = BlockBox::new(BlockPos::new(-17, 0, 0), BlockPos::new(-1, 0, 0)).chunks().collect::<Vec<_>>();
+        assert_eq!(chunks, [
+            (ChunkPos::new(-2, 0), BlockBox::new(BlockPos::new(-17, 0, 0), BlockPos::new(-17, 0, 0)),),
+            (ChunkPos::new(-1, 0), BlockBox::new(BlockPos::new(-16, 0, 0), BlockPos::new(-1, 0, 0)),)
+        ]);
+    }
+
+    const ROTATIONS : [Rotation; 4] = [Rotation::None, Rotation::Clockwise90, Rotation::Clockwise180, Rotation::CounterClockwise90];
+
+    #[test]
+    fn rails_are_rotated_and_mirrored() {
+        let rail = |shape : &str| Block::new("minecraft:rail").with("shape", shape);
+        for (shape, rotated,) in [
+            ("north_south",     ["north_south", "east_west", "north_south", "east_west"],),
+            ("ascending_north", ["ascending_north", "ascending_east", "ascending_south", "ascending_west"],),
+            ("north_east",      ["north_east", "south_east", "south_west", "north_west"],),
+            ("south_west",      ["south_west", "north_west", "north_east", "south_east"],)
+        ] {
+            for (rotation, expected,) in ROTATIONS.into_iter().zip(rotated) {
+                assert_eq!(rail(shape).rotate(rotation), rail(expected), "{shape} {rotation:?}");
+            }
+        }
+        for (shape, left_right, front_back,) in [
+            ("east_west",       "east_west",       "east_west",),
+            ("ascending_north", "ascending_south", "ascending_north",),
+            ("ascending_east",  "ascending_east",  "ascending_west",),
+            ("north_east",      "south_east",      "north_west",),
+            ("south_west",      "north_west",      "south_east",)
+        ] {
+            assert_eq!(rail(shape).mirror(Mirror::None), rail(shape));
+            assert_eq!(rail(shape).mirror(Mirror::LeftRight), rail(left_right), "{shape}");
+            assert_eq!(rail(shape).mirror(Mirror::FrontBack), rail(front_back), "{shape}");
+        }
+    }
+
+    #[test]
+    fn stairs_are_rotated_and_mirrored() {
+        let stairs = |facing : &str, shape : &str| Block::new("minecraft:oak_stairs").with("facing", facing).with("half", "bottom").with("shape", shape);
+        for (rotation, facing,) in ROTATIONS.into_iter().zip(["north", "east", "south", "west"]) {
+            assert_eq!(stairs("north", "outer_left").rotate(rotation), stairs(facing, "outer_left"), "{rotation:?}");
+        }
+        assert_eq!(stairs("north", "outer_left").mirror(Mirror::None),       stairs("north", "outer_left"));
+        assert_eq!(stairs("north", "outer_left").mirror(Mirror::LeftRight),  stairs("south", "outer_right"));
+        assert_eq!(stairs("north", "inner_right").mirror(Mirror::FrontBack), stairs("north", "inner_left"));
+        assert_eq!(stairs("east", "straight").mirror(Mirror::FrontBack),     stairs("west", "straight"));
+    }
+
+    #[test]
+    fn doors_are_rotated_and_mirrored() {
+        let door = |facing : &str, hinge : &str| Block::new("minecraft:oak_door").with("facing", facing).with("half", "lower").with("hinge", hinge);
+        for (rotation, facing,) in ROTATIONS.into_iter().zip(["east", "south", "west", "north"]) {
+            assert_eq!(door("east", "left").rotate(rotation), door(facing, "left"), "{rotation:?}");
+        }
+        assert_eq!(door("east", "left").mirror(Mirror::None),      door("east", "left"));
+        assert_eq!(door("east", "left").mirror(Mirror::LeftRight), door("east", "right"));
+        assert_eq!(door("east", "left").mirror(Mirror::FrontBack), door("west", "right"));
+    }
+
+    #[test]
+    fn blocks_round_trip_through_strings() {
+        let block = "minecraft:oak_log[axis=y]".parse::<Block>().unwrap();
+        assert_eq!(block.id(), "minecraft:oak_log");
+        assert_eq!(block.get("axis"), Some("y"));
+        assert_eq!(block.to_string(), "minecraft:oak_log[axis=y]");
+        assert_eq!(block.to_string().parse::<Block>(), Ok(block));
+
+        let block = "minecraft:oak_stairs[ half = top , facing=east ]".parse::<Block>().unwrap();
+        assert_eq!(block.to_string(), "minecraft:oak_stairs[facing=east,half=top]");
+        assert_eq!("minecraft:oak_log[]".parse::<Block>().unwrap().to_string(), "minecraft:oak_log");
+    }
+
+    #[test]
+    fn block_parse_errors_have_offsets() {
+        fn err(s : &str) -> BlockParseError { s.parse::<Block>().unwrap_err() }
+        assert_eq!(err(""),                                 BlockParseError::Expected { offset : 0, what : "a block ID" });
+        assert_eq!(err("minecraft:oak_log(axis=y)"),        BlockParseError::Expected { offset : 17, what : "`[` or the end of the block" });
+        assert_eq!(err("minecraft:oak_log[axis]"),          BlockParseError::Expected { offset : 22, what : "`=`" });
+        assert_eq!(err("minecraft:oak_log[axis=]"),         BlockParseError::Expected { offset : 23, what : "a state value" });
+        assert_eq!(err("minecraft:oak_log[axis=y"),         BlockParseError::Expected { offset : 24, what : "`,` or `]`" });
+        assert_eq!(err("minecraft:oak_log[axis=y]x"),       BlockParseError::Expected { offset : 25, what : "the end of the block" });
+        assert_eq!(err("minecraft:oak_log[axis=y,axis=x]"), BlockParseError::DuplicateState { offset : 25, state : "axis".to_string() });
+    }
+
+
+    #[test]
+    fn vectors_add_subtract_negate_and_scale() {
+        let (a, b,) = (BlockPos::new(1, -2, 3), BlockPos::new(-4, 5, -6),);
+        assert_eq!(a + b, BlockPos::new(-3, 3, -3));
+        assert_eq!(a - b, BlockPos::new(5, -7, 9));
+        assert_eq!(- a, BlockPos::new(-1, 2, -3));
+        assert_eq!(a * -2, BlockPos::new(-2, 4, -6));
+        let mut c = a;
+        c += b;
+        c -= a;
+        assert_eq!(c, b);
+        c *= 0;
+        assert_eq!(c, BlockPos::splat(0));
+
+        assert_eq!(ChunkPos::new(1, -2) + ChunkPos::new(-3, 4), ChunkPos::new(-2, 2));
+        assert_eq!(ChunkPos::new(1, -2) - ChunkPos::new(-3, 4), ChunkPos::new(4, -6));
+        assert_eq!(- ChunkPos::new(1, -2) * 3, ChunkPos::new(-3, 6));
+        assert_eq!(SectionPos::new(1, -2, 3) + SectionPos::splat(-1), SectionPos::new(0, -3, 2));
+        assert_eq!(SectionPos::new(1, -2, 3) * -1, - SectionPos::new(1, -2, 3));
+
+        let mut v = Vec3::new(0.5, -1.0, 2.0);
+        v += Vec3::splat(1.0);
+        v -= Vec3::new(0.5, 0.0, 0.0);
+        v *= 2.0;
+        assert_eq!(v, Vec3::new(2.0, 0.0, 6.0));
+        v /= 4.0;
+        assert_eq!(v, Vec3::new(0.5, 0.0, 1.5));
+        assert_eq!(- v, Vec3::new(-0.5, -0.0, -1.5));
+    }
+
+    #[test]
+    fn directions_turn_and_have_axes() {
+        for direction in Direction::ALL {
+            assert_ne!(direction.opposite(), direction);
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_eq!(direction.opposite().vector(), - direction.vector());
+            assert_eq!(direction.opposite().axis(), direction.axis());
+            for rotation in ROTATIONS {
+                assert_eq!(direction.rotate(rotation).rotate(rotation.inverse()), direction, "{direction:?} {rotation:?}");
+                assert_eq!(direction.rotate(rotation).vector(), direction.vector().rotate(rotation), "{direction:?} {rotation:?}");
+                assert_eq!(direction.rotate(rotation).is_horizontal(), direction.is_horizontal());
+            }
+        }
+        for (i, direction,) in Direction::HORIZONTAL.into_iter().enumerate() {
+            for (turns, rotation,) in ROTATIONS.into_iter().enumerate() {
+                assert_eq!(direction.rotate(rotation), Direction::HORIZONTAL[(i + turns) % 4], "{direction:?} {rotation:?}");
+            }
+            assert_eq!(direction.rotate_clockwise().rotate_counter_clockwise(), direction);
+        }
+        for rotation in ROTATIONS {
+            assert_eq!(Direction::Up.rotate(rotation), Direction::Up);
+            assert_eq!(Direction::Down.rotate(rotation), Direction::Down);
+        }
+        assert_eq!(Direction::ALL.map(Direction::axis), [Axis::Y, Axis::Y, Axis::Z, Axis::Z, Axis::X, Axis::X]);
+    }
+
+    #[test]
+    fn positions_are_offset_and_rotated() {
+        let pos = BlockPos::new(-3, -64, 7);
+        assert_eq!(pos.offset(Direction::West, 5), pos.west(5));
+        assert_eq!(pos.offset(Direction::Down, -2), pos.up(2));
+        assert_eq!(pos.offset(Direction::North, 0), pos);
+        assert_eq!(pos.neighbors(), [pos.down(1), pos.up(1), pos.north(1), pos.south(1), pos.west(1), pos.east(1)]);
+        for neighbor in pos.neighbors() {
+            assert_eq!(pos.manhattan_distance(neighbor), 1);
+        }
+        let rotated = ROTATIONS.map(|rotation| pos.rotate(rotation));
+        assert_eq!(rotated, [pos, BlockPos::new(-7, -64, -3), BlockPos::new(3, -64, -7), BlockPos::new(7, -64, 3)]);
+        for rotation in ROTATIONS {
+            assert_eq!(pos.rotate(rotation).rotate(rotation.inverse()), pos, "{rotation:?}");
+        }
+    }
+
+    #[test]
+    fn distances_between_positions() {
+        let (a, b,) = (BlockPos::new(-3, 10, 4), BlockPos::new(2, -2, 1),);
+        assert_eq!(a.manhattan_distance(b), 5 + 12 + 3);
+        assert_eq!(a.chebyshev_distance(b), 12);
+        assert_eq!(a.distance_squared(b), 25 + 144 + 9);
+        assert_eq!(b.manhattan_distance(a), a.manhattan_distance(b));
+        assert_eq!(a.chebyshev_distance(a), 0);
+        assert_eq!(BlockPos::new(-9, 0, 0).chebyshev_distance(BlockPos::new(0, 3, -4)), 9);
+        assert_eq!(BlockPos::new(0, 0, -9).chebyshev_distance(BlockPos::new(4, 3, 0)), 9);
+
+        let (min, max,) = (BlockPos::splat(i64::MIN), BlockPos::splat(i64::MAX),);
+        assert_eq!(min.manhattan_distance(max), u64::MAX);
+        assert_eq!(min.chebyshev_distance(max), u64::MAX);
+        assert_eq!(min.distance_squared(max), u128::MAX);
+    }
+
+    #[test]
+    fn vec3_lengths_and_blocks() {
+        let v = Vec3::new(3.0, -4.0, 12.0);
+        assert_eq!(v.dot(Vec3::new(1.0, 1.0, 1.0)), 11.0);
+        assert_eq!(v.length_squared(), 169.0);
+        assert_eq!(v.length(), 13.0);
+        assert_eq!(Vec3::ZERO.distance(v), 13.0);
+        assert_eq!(v.distance_squared(v + Vec3::new(0.0, 3.0, 4.0)), 25.0);
+        assert_eq!(v.normalize(), Some(v / 13.0));
+        assert_eq!(Vec3::ZERO.normalize(), None);
+        assert_eq!(Vec3::splat(f64::INFINITY).normalize(), None);
+        assert_eq!(Vec3::new(-0.5, -1.0, 1.999).block(), BlockPos::new(-1, -1, 1));
+        assert_eq!(BlockPos::new(-1, 2, -3).center(), Vec3::new(-0.5, 2.5, -2.5));
+        assert_eq!(Vec3::from(BlockPos::new(-1, 2, -3)), Vec3::new(-1.0, 2.0, -3.0));
+        assert_eq!(Vec3::from(BlockPos::new(-1, 2, -3)).block(), BlockPos::new(-1, 2, -3));
+    }
+
+}
@@ -2,17 +2,34 @@
 
 
 mod server;
-pub use server::Server;
+pub use server::{ Server, Capabilities };
+pub(crate) use server::optional_imports;
 mod player;
-pub use player::Player;
+pub use player::{ Player, BatchSet, ChatMessage };
 
 pub mod data;
 
+pub mod blocks;
+
+pub mod nbt;
+
+#[cfg(feature = "schematic")]
+#[doc(cfg(feature = "schematic"))]
+pub mod schematic;
+
+pub mod text;
+
+pub mod command;
+
 
 /// Commonly used items.
 pub mod prelude {
     pub use super::server::Server;
-    pub use super::player::{ Player, World };
+    pub use super::player::{ Player, World, BatchSet, ChatMessage, ChunkSnapshot, ChunkSection };
+
+    pub use super::data::{ ChunkPos, SectionPos, BlockPos, BlockBox, Direction, Vec3, SoundCategory, Block };
+
+    pub use super::text::{ Text, Color, ClickEvent };
 
-    pub use super::data::{ ChunkPos, BlockPos, SoundCategory, Block };
+    pub use super::command::{ Command, CommandArgs };
 }
@@ -0,0 +1,465 @@
+//! Reading and writing Minecraft's Named Binary Tag format.
+//!
+//! NBT is used by schematics, structure files, and many other Minecraft data files.
+//!  Only uncompressed NBT is read and written here. Files are usually gzip-compressed, which
+//!  `game::schematic` handles when the `schematic` feature is enabled.
+
+
+use core::fmt;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+
+/// The deepest lists and compounds can be nested, the same as the vanilla limit.
+const MAX_DEPTH : usize = 512;
+
+
+/// An NBT value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Nbt {
+    /// A signed 8-bit integer.
+    Byte(i8),
+    /// A signed 16-bit integer.
+    Short(i16),
+    /// A signed 32-bit integer.
+    Int(i32),
+    /// A signed 64-bit integer.
+    Long(i64),
+    /// A 32-bit floating point number.
+    Float(f32),
+    /// A 64-bit floating point number.
+    Double(f64),
+    /// An array of signed 8-bit integers.
+    ByteArray(Vec<i8>),
+    /// A string.
+    String(String),
+    /// A list of unnamed values, which all have the same type.
+    List(Vec<Nbt>),
+    /// Named values.
+    Compound(BTreeMap<String, Nbt>),
+    /// An array of signed 32-bit integers.
+    IntArray(Vec<i32>),
+    /// An array of signed 64-bit integers.
+    LongArray(Vec<i64>)
+}
+
+impl Nbt {
+
+    /// Reads an uncompressed NBT file, returning the name and value of its root tag.
+    ///
+    /// The whole of `data` must be used.
+    pub fn read(data : &[u8]) -> Result<(String, Nbt,), NbtError> {
+        let mut reader = NbtReader { data, offset : 0 };
+        let offset     = reader.offset;
+        let tag        = reader.tag()?;
+        if (tag == 0) {
+            return Err(NbtError::InvalidTag { offset, tag });
+        }
+        let name  = reader.string()?;
+        let value = reader.value(tag, 0)?;
+        if (reader.offset != data.len()) {
+            return Err(NbtError::TrailingData { offset : reader.offset });
+        }
+        Ok((name, value,))
+    }
+
+    /// Writes this value as the root tag of an uncompressed NBT file, named `name`.
+    ///
+    /// ### Panics
+    /// Panics if a list has values of different types, or if a string is longer than 65535 bytes
+    ///  once encoded.
+    #[track_caller]
+    pub fn write(&self, name : &str) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.push(self.tag());
+        write_string(&mut out, name);
+        self.write_value(&mut out);
+        out
+    }
+
+    /// Returns the tag type of this value.
+    fn tag(&self) -> u8 {
+        match (self) {
+            Self::Byte(_)      => 1,
+            Self::Short(_)     => 2,
+            Self::Int(_)       => 3,
+            Self::Long(_)      => 4,
+            Self::Float(_)     => 5,
+            Self::Double(_)    => 6,
+            Self::ByteArray(_) => 7,
+            Self::String(_)    => 8,
+            Self::List(_)      => 9,
+            Self::Compound(_)  => 10,
+            Self::IntArray(_)  => 11,
+            Self::LongArray(_) => 12
+        }
+    }
+
+    #[track_caller]
+    fn write_value(&self, out : &mut Vec<u8>) {
+        match (self) {
+            Self::Byte(value)       => out.extend(value.to_be_bytes()),
+            Self::Short(value)      => out.extend(value.to_be_bytes()),
+            Self::Int(value)        => out.extend(value.to_be_bytes()),
+            Self::Long(value)       => out.extend(value.to_be_bytes()),
+            Self::Float(value)      => out.extend(value.to_be_bytes()),
+            Self::Double(value)     => out.extend(value.to_be_bytes()),
+            Self::ByteArray(values) => {
+                write_len(out, values.len());
+                out.extend(values.iter().map(|&value| value as u8));
+            },
+            Self::String(value)     => write_string(out, value),
+            Self::List(values)      => {
+                let tag = values.first().map_or(0, Nbt::tag);
+                assert!(values.iter().all(|value| value.tag() == tag), "list has values of different types");
+                out.push(tag);
+                write_len(out, values.len());
+                for value in values {
+                    value.write_value(out);
+                }
+            },
+            Self::Compound(values)  => {
+                for (name, value,) in values {
+                    out.push(value.tag());
+                    write_string(out, name);
+                    value.write_value(out);
+                }
+                out.push(0);
+            },
+            Self::IntArray(values)  => {
+                write_len(out, values.len());
+                for value in values { out.extend(value.to_be_bytes()); }
+            },
+            Self::LongArray(values) => {
+                write_len(out, values.len());
+                for value in values { out.extend(value.to_be_bytes()); }
+            }
+        }
+    }
+
+    /// Returns the value named `key`, if this is a compound which has one.
+    pub fn get(&self, key : &str) -> Option<&Nbt> {
+        self.as_compound()?.get(key)
+    }
+
+    /// Returns this value as an `i64`, if it is a [`Nbt::Byte`], [`Nbt::Short`], [`Nbt::Int`], or [`Nbt::Long`].
+    pub fn as_i64(&self) -> Option<i64> {
+        match (self) {
+            Self::Byte(value)  => Some(*value as i64),
+            Self::Short(value) => Some(*value as i64),
+            Self::Int(value)   => Some(*value as i64),
+            Self::Long(value)  => Some(*value),
+            _                  => None
+        }
+    }
+
+    /// Returns this value as an `f64`, if it is a [`Nbt::Float`] or [`Nbt::Double`].
+    pub fn as_f64(&self) -> Option<f64> {
+        match (self) {
+            Self::Float(value)  => Some(*value as f64),
+            Self::Double(value) => Some(*value),
+            _                   => None
+        }
+    }
+
+    /// Returns this value as a string, if it is a [`Nbt::String`].
+    pub fn as_str(&self) -> Option<&str> {
+        match (self) { Self::String(value) => Some(value), _ => None }
+    }
+
+    /// Returns this value as a list, if it is a [`Nbt::List`].
+    pub fn as_list(&self) -> Option<&[Nbt]> {
+        match (self) { Self::List(values) => Some(values), _ => None }
+    }
+
+    /// Returns this value as a compound, if it is a [`Nbt::Compound`].
+    pub fn as_compound(&self) -> Option<&BTreeMap<String, Nbt>> {
+        match (self) { Self::Compound(values) => Some(values), _ => None }
+    }
+
+    /// Returns this value as a byte array, if it is a [`Nbt::ByteArray`].
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match (self) { Self::ByteArray(values) => Some(values), _ => None }
+    }
+
+    /// Returns this value as an int array, if it is a [`Nbt::IntArray`].
+    pub fn as_int_array(&self) -> Option<&[i32]> {
+        match (self) { Self::IntArray(values) => Some(values), _ => None }
+    }
+
+    /// Returns this value as a long array, if it is a [`Nbt::LongArray`].
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match (self) { Self::LongArray(values) => Some(values), _ => None }
+    }
+
+}
+
+
+/// A cursor over NBT data which is being read.
+struct NbtReader<'l> {
+    data   : &'l [u8],
+    offset : usize
+}
+
+impl<'l> NbtReader<'l> {
+
+    fn bytes(&mut self, len : usize) -> Result<&'l [u8], NbtError> {
+        let end = self.offset.checked_add(len).filter(|&end| end <= self.data.len())
+            .ok_or(NbtError::UnexpectedEnd { offset : self.offset })?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn array<const N : usize>(&mut self) -> Result<[u8; N], NbtError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    /// Reads a tag type, which is `0` for the end of a compound, or the type of a value.
+    fn tag(&mut self) -> Result<u8, NbtError> {
+        let offset = self.offset;
+        let tag    = self.array::<1>()?[0];
+        if (tag > 12) {
+            return Err(NbtError::InvalidTag { offset, tag });
+        }
+        Ok(tag)
+    }
+
+    fn len(&mut self) -> Result<usize, NbtError> {
+        let offset = self.offset;
+        let len    = i32::from_be_bytes(self.array()?);
+        usize::try_from(len).map_err(|_| NbtError::NegativeLength { offset })
+    }
+
+    fn string(&mut self) -> Result<String, NbtError> {
+        let len    = u16::from_be_bytes(self.array()?) as usize;
+        let offset = self.offset;
+        decode_mutf8(self.bytes(len)?).ok_or(NbtError::InvalidString { offset })
+    }
+
+    fn value(&mut self, tag : u8, depth : usize) -> Result<Nbt, NbtError> {
+        Ok(match (tag) {
+            1  => Nbt::Byte(i8::from_be_bytes(self.array()?)),
+            2  => Nbt::Short(i16::from_be_bytes(self.array()?)),
+            3  => Nbt::Int(i32::from_be_bytes(self.array()?)),
+            4  => Nbt::Long(i64::from_be_bytes(self.array()?)),
+            5  => Nbt::Float(f32::from_be_bytes(self.array()?)),
+            6  => Nbt::Double(f64::from_be_bytes(self.array()?)),
+            7  => {
+                let len = self.len()?;
+                Nbt::ByteArray(self.bytes(len)?.iter().map(|&byte| byte as i8).collect())
+            },
+            8  => Nbt::String(self.string()?),
+            9  => {
+                let offset = self.offset;
+                if (depth >= MAX_DEPTH) { return Err(NbtError::TooDeep { offset }); }
+                let tag = self.tag()?;
+                let len = self.len()?;
+                if (tag == 0 && len > 0) {
+                    return Err(NbtError::InvalidTag { offset, tag });
+                }
+                let mut values = Vec::with_capacity(len.min(self.data.len() - self.offset));
+                for _ in 0..len {
+                    values.push(self.value(tag, depth + 1)?);
+                }
+                Nbt::List(values)
+            },
+            10 => {
+                if (depth >= MAX_DEPTH) { return Err(NbtError::TooDeep { offset : self.offset }); }
+                let mut values = BTreeMap::new();
+                loop {
+                    let tag = self.tag()?;
+                    if (tag == 0) { break; }
+                    let name = self.string()?;
+                    values.insert(name, self.value(tag, depth + 1)?);
+                }
+                Nbt::Compound(values)
+            },
+            11 => {
+                let len = self.len()?;
+                Nbt::IntArray(self.bytes(len.saturating_mul(4))?.chunks_exact(4).map(|chunk| i32::from_be_bytes(chunk.try_into().unwrap())).collect())
+            },
+            12 => {
+                let len = self.len()?;
+                Nbt::LongArray(self.bytes(len.saturating_mul(8))?.chunks_exact(8).map(|chunk| i64::from_be_bytes(chunk.try_into().unwrap())).collect())
+            },
+            _  => unreachable!("tag types are checked when they are read")
+        })
+    }
+
+}
+
+/// Decodes Java's modified UTF-8, which NBT strings use.
+///
+/// This differs from UTF-8 in that `\0` is two bytes, and characters outside of the basic
+///  multilingual plane are two three-byte surrogates.
+fn decode_mutf8(bytes : &[u8]) -> Option<String> {
+    if let Ok(string) = str::from_utf8(bytes) {
+        return Some(string.to_string());
+    }
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i     = 0;
+    while (i < bytes.len()) {
+        let byte = bytes[i];
+        let (unit, len,) = match (byte) {
+            0x01..=0x7F => (byte as u16, 1,),
+            0xC0..=0xDF => {
+                let b1 = *bytes.get(i + 1)?;
+                ((((byte & 0x1F) as u16) << 6) | ((b1 & 0x3F) as u16), 2,)
+            },
+            0xE0..=0xEF => {
+                let (b1, b2,) = (*bytes.get(i + 1)?, *bytes.get(i + 2)?,);
+                ((((byte & 0x0F) as u16) << 12) | (((b1 & 0x3F) as u16) << 6) | ((b2 & 0x3F) as u16), 3,)
+            },
+            _ => { return None; }
+        };
+        units.push(unit);
+        i += len;
+    }
+    String::from_utf16(&units).ok()
+}
+
+/// Writes the length of an array or list.
+#[track_caller]
+fn write_len(out : &mut Vec<u8>, len : usize) {
+    let len = i32::try_from(len).expect("array or list is too long");
+    out.extend(len.to_be_bytes());
+}
+
+/// Writes a string as Java's modified UTF-8, prefixed by its length in bytes.
+#[track_caller]
+fn write_string(out : &mut Vec<u8>, string : &str) {
+    let start = out.len();
+    out.extend([0, 0]);
+    if (! string.chars().any(|char| char == '\0' || (char as u32) > 0xFFFF)) {
+        out.extend(string.as_bytes());
+    } else {
+        for unit in string.encode_utf16() {
+            match (unit) {
+                0x0001..=0x007F => out.push(unit as u8),
+                0x0000..=0x07FF => out.extend([0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8]),
+                _               => out.extend([0xE0 | (unit >> 12) as u8, 0x80 | ((unit >> 6) & 0x3F) as u8, 0x80 | (unit & 0x3F) as u8])
+            }
+        }
+    }
+    let len = u16::try_from(out.len() - start - 2).expect("string is too long");
+    out[start..(start + 2)].copy_from_slice(&len.to_be_bytes());
+}
+
+
+/// An error returned by [`Nbt::read`] if the data is malformed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NbtError {
+    /// The data ended before a value could be read.
+    UnexpectedEnd {
+        /// The offset of the value in the data.
+        offset : usize
+    },
+    /// A tag type was not valid.
+    InvalidTag {
+        /// The offset of the tag type in the data.
+        offset : usize,
+        /// The tag type.
+        tag    : u8
+    },
+    /// A length was negative.
+    NegativeLength {
+        /// The offset of the length in the data.
+        offset : usize
+    },
+    /// A string was not valid modified UTF-8.
+    InvalidString {
+        /// The offset of the string in the data, after the length prefix.
+        offset : usize
+    },
+    /// Lists and compounds were nested too deeply.
+    TooDeep {
+        /// The offset of the list or compound which was too deep.
+        offset : usize
+    },
+    /// There was more data after the root tag.
+    TrailingData {
+        /// The offset of the end of the root tag.
+        offset : usize
+    }
+}
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::UnexpectedEnd { offset }   => write!(f, "unexpected end of data at offset {offset}"),
+            Self::InvalidTag { offset, tag } => write!(f, "invalid tag type {tag} at offset {offset}"),
+            Self::NegativeLength { offset }  => write!(f, "negative length at offset {offset}"),
+            Self::InvalidString { offset }   => write!(f, "invalid string at offset {offset}"),
+            Self::TooDeep { offset }         => write!(f, "too deeply nested at offset {offset}"),
+            Self::TrailingData { offset }    => write!(f, "trailing data at offset {offset}")
+        }
+    }
+}
+
+impl Error for NbtError { }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip() {
+        let value = Nbt::Compound(BTreeMap::from([
+            ("byte".to_string(),   Nbt::Byte(-1),),
+            ("short".to_string(),  Nbt::Short(-300),),
+            ("int".to_string(),    Nbt::Int(1 << 20),),
+            ("long".to_string(),   Nbt::Long(-1 << 40),),
+            ("float".to_string(),  Nbt::Float(0.5),),
+            ("double".to_string(), Nbt::Double(-0.25),),
+            ("bytes".to_string(),  Nbt::ByteArray(vec![ -1, 0, 1 ]),),
+            ("string".to_string(), Nbt::String("stone".to_string()),),
+            ("list".to_string(),   Nbt::List(vec![ Nbt::Int(1), Nbt::Int(2) ]),),
+            ("empty".to_string(),  Nbt::List(Vec::new()),),
+            ("ints".to_string(),   Nbt::IntArray(vec![ i32::MIN, 0, i32::MAX ]),),
+            ("longs".to_string(),  Nbt::LongArray(vec![ i64::MIN, i64::MAX ]),)
+        ]));
+        assert_eq!(Nbt::read(&value.write("root")), Ok(("root".to_string(), value,)));
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        assert_eq!(Nbt::read(&[]), Err(NbtError::UnexpectedEnd { offset : 0 }));
+        assert_eq!(Nbt::read(&[3, 0, 1, b'a', 0, 0]), Err(NbtError::UnexpectedEnd { offset : 4 }));
+        let data = Nbt::Compound(BTreeMap::from([("a".to_string(), Nbt::String("b".to_string()),)])).write("");
+        for len in 0..data.len() {
+            assert!(matches!(Nbt::read(&data[..len]), Err(NbtError::UnexpectedEnd { .. })), "{len}");
+        }
+        assert_eq!(Nbt::read(&[1, 0, 0, 5, 0]), Err(NbtError::TrailingData { offset : 4 }));
+    }
+
+    #[test]
+    fn negative_lengths_are_an_error() {
+        assert_eq!(Nbt::read(&[7, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]), Err(NbtError::NegativeLength { offset : 3 }));
+        assert_eq!(Nbt::read(&[9, 0, 0, 1, 0x80, 0, 0, 0]), Err(NbtError::NegativeLength { offset : 4 }));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth : usize| {
+            let mut data = vec![ 9, 0, 0 ];
+            data.extend([9, 0, 0, 0, 1].repeat(depth));
+            data.extend([0, 0, 0, 0, 0]);
+            data
+        };
+        assert!(Nbt::read(&nested(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(Nbt::read(&nested(MAX_DEPTH)), Err(NbtError::TooDeep { offset : 3 + MAX_DEPTH * 5 }));
+    }
+
+    #[test]
+    fn strings_are_modified_utf8() {
+        let value = Nbt::String("a\0b\u{1F600}".to_string());
+        let data  = value.write("");
+        assert_eq!(&data[3..], [0, 10, b'a', 0xC0, 0x80, b'b', 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(Nbt::read(&data), Ok((String::new(), value,)));
+        assert_eq!(Nbt::read(&[8, 0, 0, 0, 2, 0xC0, 0x80]), Ok((String::new(), Nbt::String("\0".to_string()),)));
+        assert_eq!(Nbt::read(&[8, 0, 0, 0, 1, 0xFF]), Err(NbtError::InvalidString { offset : 5 }));
+    }
+
+}
@@ -0,0 +1,189 @@
+use crate::game::{ Player, optional_imports };
+use crate::game::text::Text;
+use core::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{ Rc, Weak };
+
+
+optional_imports! {
+    fn flywheel_player_chat_resolve(
+        session_id      : u64,
+        message_id      : u64,
+        flags           : u32,
+        in_msg          : usize,
+        msg_len         : u32,
+        in_recipients   : usize,
+        recipient_count : u32
+    );
+}
+
+
+/// The message was cancelled, and is not sent to anyone.
+const FLAG_CANCELLED  : u32 = 1 << 0;
+/// The message was rewritten, and the markup passed to the host replaces it.
+const FLAG_REWRITTEN  : u32 = 1 << 1;
+/// The message is only sent to the session IDs passed to the host.
+const FLAG_RESTRICTED : u32 = 1 << 2;
+
+
+thread_local! {
+    /// Chat messages which the host is waiting on, by sender session ID and message ID.
+    static PENDING : RefCell<HashMap<(u64, u64,), Weak<Pending>>> = RefCell::new(HashMap::new());
+}
+
+
+/// A chat message sent by a player, which the host holds back until the plot decides what to do with it.
+///
+/// Every `on_player_chat` callback gets a handle to the same message. Once every handle has
+///  been dropped (usually when the callbacks finish), the message is sent on with any changes made
+///  to it. Callbacks should not hold on to the message across long `.await`s, as the chat is delayed
+///  until then.
+///
+/// If several callbacks change the message, cancelling wins over everything else, the last
+///  rewrite is used, and restricting the recipients more than once only sends the message to
+///  players in every subset.
+///
+/// Changes are ignored if the host does not support [`Capabilities::CHAT_RESOLVE`](crate::game::Capabilities::CHAT_RESOLVE),
+///  as it sends chat messages on by itself.
+#[derive(Clone)]
+pub struct ChatMessage {
+    inner : Rc<Pending>
+}
+
+struct Pending {
+    sender : Player,
+    id     : u64,
+    text   : String,
+    state  : RefCell<ChatState>
+}
+
+#[derive(Default)]
+struct ChatState {
+    cancelled  : bool,
+    rewrite    : Option<Text>,
+    recipients : Option<Vec<Player>>
+}
+
+impl ChatMessage {
+
+    /// Returns the handle to the message `id` sent by `sender`, creating it if no callback has one yet.
+    pub(crate) fn pending(sender : Player, id : u64, text : &str) -> Self {
+        let key = (sender.session_id(), id,);
+        if let Some(inner) = PENDING.with_borrow(|pending| pending.get(&key).and_then(Weak::upgrade)) {
+            return Self { inner };
+        }
+        let inner = Rc::new(Pending { sender, id, text : text.to_string(), state : RefCell::default() });
+        PENDING.with_borrow_mut(|pending| pending.insert(key, Rc::downgrade(&inner)));
+        Self { inner }
+    }
+
+    /// Returns the player who sent this message.
+    #[inline]
+    pub fn sender(&self) -> Player { self.inner.sender }
+
+    /// Returns the ID the host assigned to this message.
+    #[inline]
+    pub fn id(&self) -> u64 { self.inner.id }
+
+    /// Returns the message exactly as the player typed it.
+    ///
+    /// This is not changed by [`ChatMessage::rewrite`].
+    #[inline]
+    pub fn text(&self) -> &str { &self.inner.text }
+
+    /// Cancels this message, so that it is not sent to anyone.
+    pub fn cancel(&self) {
+        self.inner.state.borrow_mut().cancelled = true;
+    }
+
+    /// Returns `true` if this message was cancelled.
+    pub fn is_cancelled(&self) -> bool { self.inner.state.borrow().cancelled }
+
+    /// Replaces the contents of this message.
+    ///
+    /// The host still shows who sent the message. Strings are escaped, like [`Text::literal`],
+    ///  so text from [`ChatMessage::text`] can not inject formatting into other players' chat.
+    ///  Use [`Text::markup`] to rewrite the message to markup.
+    pub fn rewrite(&self, text : impl Into<Text>) {
+        self.inner.state.borrow_mut().rewrite = Some(text.into());
+    }
+
+    /// Returns the contents this message was rewritten to, if it was.
+    pub fn rewritten(&self) -> Option<Text> { self.inner.state.borrow().rewrite.clone() }
+
+    /// Only sends this message to players in `recipients`.
+    ///
+    /// If the recipients were already restricted, only players in both are kept.
+    ///  The sender is not added automatically.
+    pub fn restrict_recipients(&self, recipients : impl IntoIterator<Item = Player>) {
+        let recipients = recipients.into_iter().collect::<Vec<_>>();
+        let mut state  = self.inner.state.borrow_mut();
+        state.recipients = Some(match (state.recipients.take()) {
+            Some(current) => current.into_iter()
+                .filter(|player| recipients.iter().any(|recipient| recipient.session_id() == player.session_id()))
+                .collect(),
+            None          => recipients
+        });
+    }
+
+    /// Returns the players this message is restricted to, or `None` if it is sent to everyone.
+    pub fn recipients(&self) -> Option<Vec<Player>> { self.inner.state.borrow().recipients.clone() }
+
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        let session_id = self.sender.session_id();
+        PENDING.with_borrow_mut(|pending| pending.remove(&(session_id, self.id,)));
+        let Some(resolve) = flywheel_player_chat_resolve() else { return; };
+        let state = self.state.get_mut();
+        if (state.cancelled) {
+            unsafe { resolve(session_id, self.id, FLAG_CANCELLED, 0, 0, 0, 0); }
+            return;
+        }
+        let mut flags = 0;
+        let msg       = state.rewrite.as_ref().map(Text::to_markup);
+        if (msg.is_some()) {
+            flags |= FLAG_REWRITTEN;
+        }
+        let recipients = state.recipients.as_ref().map(|recipients| recipients.iter()
+            .flat_map(|player| player.session_id().to_le_bytes())
+            .collect::<Vec<_>>()
+        );
+        if (recipients.is_some()) {
+            flags |= FLAG_RESTRICTED;
+        }
+        let msg        = msg.unwrap_or_default();
+        let recipients = recipients.unwrap_or_default();
+        unsafe { resolve(
+            session_id, self.id, flags,
+            msg.as_ptr() as usize, msg.len() as u32,
+            recipients.as_ptr() as usize, (recipients.len() / 8) as u32
+        ); }
+    }
+}
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use crate::prelude::*;
+    use crate::testkit::{ self, HostCall };
+
+    #[test]
+    fn rewritten_strings_are_escaped() {
+        let mut app = App::new();
+        app.on_player_chat(|_ : Player, message : ChatMessage| async move {
+            message.rewrite(message.text().replace("heck", "****"));
+        });
+        let player     = testkit::player_joined(Uuid::nil(), "a");
+        let message_id = testkit::player_chat(player, "<red>heck</>");
+        app.run();
+        assert_eq!(testkit::take_calls(), [HostCall::ChatResolved {
+            session_id : player.session_id(), message_id,
+            cancelled  : false,
+            rewrite    : Some("&lt;red&gt;****&lt;/&gt;".to_string()),
+            recipients : None
+        }]);
+    }
+
+}
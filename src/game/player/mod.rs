@@ -1,22 +1,31 @@
 use crate::prelude::*;
+use crate::game::optional_imports;
+use crate::internal::alloc::{ HostBuf, Out };
+use crate::task::{ TaskHandle, TaskScope };
 
 
 mod profile;
 pub use profile::PlayerProfile;
 
 mod world;
-pub use world::World;
+pub use world::{ World, BatchSet };
+
+mod snapshot;
+pub use snapshot::{ ChunkSnapshot, ChunkSection };
+
+mod chat;
+pub use chat::ChatMessage;
 
 
 unsafe extern "C" {
     safe fn flywheel_player_exists(session_id : u64) -> u32;
-    unsafe fn flywheel_player_send_chat(session_id : u64, in_msg : u32, msg_len : u32);
-    unsafe fn flywheel_player_send_actionbar(session_id : u64, in_msg : u32, msg_len : u32);
+    unsafe fn flywheel_player_send_chat(session_id : u64, in_msg : usize, msg_len : u32);
+    unsafe fn flywheel_player_send_actionbar(session_id : u64, in_msg : usize, msg_len : u32);
     unsafe fn flywheel_player_send_title(
         session_id   : u64,
-        in_title     : u32,
+        in_title     : usize,
         title_len    : u32,
-        in_subtitle  : u32,
+        in_subtitle  : usize,
         subtitle_len : u32,
         fade_in      : u32,
         stay         : u32,
@@ -24,7 +33,7 @@ unsafe extern "C" {
     );
     unsafe fn flywheel_player_send_sound(
         session_id : u64,
-        in_id      : u32,
+        in_id      : usize,
         id_len     : u32,
         category   : u32,
         volume     : f32,
@@ -33,6 +42,10 @@ unsafe extern "C" {
     );
 }
 
+optional_imports! {
+    fn flywheel_player_from_name(in_name : usize, name_len : u32, out_session_id : usize) -> u32;
+}
+
 
 /// A [`Player`] on the server.
 ///
@@ -53,6 +66,21 @@ impl Player {
     #[inline]
     pub unsafe fn from_session_id(session_id : u64) -> Self { Self { session_id } }
 
+    /// Finds the player on the server with the username `name`, ignoring case.
+    ///
+    /// If no player with that name is on the server, or the host does not support
+    ///  [`Capabilities::PLAYER_LOOKUP`](crate::game::Capabilities::PLAYER_LOOKUP), `None` is returned.
+    pub fn from_name(name : &str) -> Option<Self> {
+        let from_name      = flywheel_player_from_name()?;
+        let mut session_id = Out::<u64>::new();
+        if (unsafe { from_name(
+            name.as_ptr() as usize, name.len() as u32,
+            session_id.ptr()
+        ) } == 0) { None } else {
+            Some(Self { session_id : session_id.get() })
+        }
+    }
+
     /// Gets this player's session ID.
     #[inline]
     pub fn session_id(&self) -> u64 { self.session_id }
@@ -66,18 +94,20 @@ impl Player {
     ///
     /// If this player is no longer on the server, `None` is returned.
     pub fn fetch_profile(&self) -> Option<PlayerProfile> {
-        let mut name_ptr = 0u32;
-        let mut name_len = 0u32;
-        let mut uuid     = 0u128;
+        let mut name_ptr = Out::<usize>::new();
+        let mut name_len = Out::<u32>::new();
+        let mut uuid     = Out::<u128>::new();
         if (unsafe { profile::flywheel_profile_from_session(
             self.session_id,
-            (&mut uuid) as (*mut _) as u32,
-            (&mut name_ptr) as (*mut _) as u32,
-            (&mut name_len) as (*mut _) as u32
+            uuid.ptr(),
+            name_ptr.ptr(),
+            name_len.ptr()
         ) } == 0) { None } else {
-            let name_len = name_len as usize;
-            let name = unsafe { String::from_raw_parts(name_ptr as (*mut u8), name_len, name_len) };
-            Some(PlayerProfile { uuid : Uuid::from_u128_le(uuid), name })
+            let name = unsafe { HostBuf::from_raw(name_ptr.get(), name_len.get()) };
+            Some(PlayerProfile {
+                uuid : Uuid::from_u128_le(uuid.get()),
+                name : name.into_string().unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+            })
         }
     }
 
@@ -86,44 +116,56 @@ impl Player {
         World { session_id : self.session_id }
     }
 
+    /// Spawns a new task which is cancelled when this player leaves the server.
+    ///
+    /// See [`task::spawn_in`](crate::task::spawn_in).
+    pub fn spawn<F, T>(&self, fut : F) -> TaskHandle<T>
+    where
+        F : Future<Output = T> + 'static,
+        T : 'static
+    { task::spawn_in(TaskScope::Player(*self), fut) }
+
 }
 
 impl Player {
 
     /// Adds a chat message to the player's chat stream.
-    ///  `msg` is in the XML text format.
     ///
     /// The chat appears on the left side of the player's screen.
-    pub fn send_chat(&self, msg : &str) {
-        unsafe { flywheel_player_send_chat(self.session_id, msg.as_ptr() as u32, msg.len() as u32); }
+    pub fn send_chat(&self, msg : impl Into<Text>) {
+        let msg = msg.into().to_markup();
+        unsafe { flywheel_player_send_chat(self.session_id, msg.as_ptr() as usize, msg.len() as u32); }
     }
 
     /// Show an actionbar message to the player.
-    ///  `msg` is in the XML text format.
     ///
     /// The actionbar appears above the player's hotbar.
-    pub fn send_actionbar(&self, msg : &str) {
-        unsafe { flywheel_player_send_actionbar(self.session_id, msg.as_ptr() as u32, msg.len() as u32); }
+    pub fn send_actionbar(&self, msg : impl Into<Text>) {
+        let msg = msg.into().to_markup();
+        unsafe { flywheel_player_send_actionbar(self.session_id, msg.as_ptr() as usize, msg.len() as u32); }
     }
 
     /// Show a title message to the player.
-    ///  `title` and `subtitle` are in the XML text format.
     ///
     /// The title appears in the middle of the player's screen.
     pub fn send_title(&self,
-        title    : &str,
-        subtitle : &str,
+        title    : impl Into<Text>,
+        subtitle : impl Into<Text>,
         fade_in  : Duration,
         stay     : Duration,
         fade_out : Duration
-    ) { unsafe { flywheel_player_send_title(
-        self.session_id,
-        title.as_ptr() as u32, title.len() as u32,
-        subtitle.as_ptr() as u32, subtitle.len() as u32,
-        fade_in.as_ticks(),
-        stay.as_ticks(),
-        fade_out.as_ticks()
-    ); } }
+    ) {
+        let title    = title.into().to_markup();
+        let subtitle = subtitle.into().to_markup();
+        unsafe { flywheel_player_send_title(
+            self.session_id,
+            title.as_ptr() as usize, title.len() as u32,
+            subtitle.as_ptr() as usize, subtitle.len() as u32,
+            fade_in.as_ticks(),
+            stay.as_ticks(),
+            fade_out.as_ticks()
+        ); }
+    }
 
     /// Play a sound to the player.
     pub fn send_sound(&self,
@@ -134,7 +176,7 @@ impl Player {
         seed     : u64
     ) { unsafe { flywheel_player_send_sound(
         self.session_id,
-        id.as_ptr() as u32, id.len() as u32,
+        id.as_ptr() as usize, id.len() as u32,
         category as u32,
         volume, pitch, seed
     ); } }
@@ -0,0 +1,280 @@
+use crate::event::{ EventArgs, EventArgsError };
+use crate::game::data::{ BlockPos, ChunkPos, Block };
+
+
+/// The number of blocks along each edge of a chunk section.
+const SECTION_SIZE   : usize = 16;
+/// The number of blocks in a chunk section.
+const SECTION_BLOCKS : usize = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+
+
+/// A copy of the blocks in a chunk, taken with [`World::snapshot`](super::World::snapshot).
+///
+/// The snapshot does not change when the world does.
+#[derive(Clone, Debug)]
+pub struct ChunkSnapshot {
+    pos      : ChunkPos,
+    sections : Vec<ChunkSection>
+}
+
+impl ChunkSnapshot {
+
+    /// Decodes a snapshot of the chunk at `pos`.
+    ///
+    /// The snapshot is a `u32` section count, followed by each [`ChunkSection`].
+    pub(super) fn decode(pos : ChunkPos, args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        let count        = args.u32()?;
+        let mut sections = Vec::new();
+        for _ in 0..count {
+            sections.push(ChunkSection::decode(args)?);
+        }
+        sections.sort_by_key(|section| section.y);
+        Ok(Self { pos, sections })
+    }
+
+    /// Returns the position of the chunk.
+    #[inline]
+    pub fn pos(&self) -> ChunkPos { self.pos }
+
+    /// Returns the sections of the chunk, from the bottom up.
+    ///
+    /// Sections which the host did not send, such as those above or below the world, are not included.
+    #[inline]
+    pub fn sections(&self) -> &[ChunkSection] { &self.sections }
+
+    /// Returns the section at section height `y`, which covers block heights `y * 16` to `y * 16 + 15`.
+    pub fn section(&self, y : i32) -> Option<&ChunkSection> {
+        self.sections.binary_search_by_key(&y, |section| section.y).ok().map(|index| &self.sections[index])
+    }
+
+    /// Returns the block at `pos`, in world coordinates.
+    ///
+    /// Returns `None` if `pos` is not in this chunk, or if its section is not included.
+    pub fn get(&self, pos : BlockPos) -> Option<&Block> {
+        if (pos.chunk() != self.pos) {
+            return None;
+        }
+        let section = self.section(i32::try_from(pos.y.div_euclid(16)).ok()?)?;
+        let local   = pos.local_in_section();
+        Some(section.get(local.x as usize, local.y as usize, local.z as usize))
+    }
+
+}
+
+
+/// A 16×16×16 section of a [`ChunkSnapshot`], stored as a palette of blocks and an index into it for each block.
+#[derive(Clone, Debug)]
+pub struct ChunkSection {
+    y       : i32,
+    palette : Vec<Block>,
+    bits    : u32,
+    data    : Vec<u64>
+}
+
+impl ChunkSection {
+
+    /// Decodes a section.
+    ///
+    /// A section is its `i32` section height, a `u32` palette length followed by each block in the
+    ///  palette, a `u8` number of bits per index, and a `u32` word count followed by each `u64` word.
+    ///  Indices are packed from the lowest bit of each word, and do not span words. They are ordered
+    ///  by `y`, then `z`, then `x`. If the palette has one block, the bits per index may be `0`.
+    fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> {
+        let y           = args.i32()?;
+        let offset      = args.offset();
+        let len         = args.u32()?;
+        let mut palette = Vec::new();
+        for _ in 0..len {
+            palette.push(args.block()?);
+        }
+        if (palette.is_empty()) {
+            return Err(EventArgsError::InvalidValue { offset, what : "palette length" });
+        }
+        let offset = args.offset();
+        let bits   = args.u8()? as u32;
+        if (bits > 32 || (bits == 0 && palette.len() > 1)) {
+            return Err(EventArgsError::InvalidValue { offset, what : "bits per index" });
+        }
+        let offset   = args.offset();
+        let words    = args.u32()?;
+        let needed   = 64u32.checked_div(bits).map_or(0, |per_word| SECTION_BLOCKS.div_ceil(per_word as usize));
+        if ((words as usize) < needed) {
+            return Err(EventArgsError::InvalidValue { offset, what : "section data length" });
+        }
+        let mut data = Vec::with_capacity(needed);
+        for _ in 0..words {
+            data.push(args.u64()?);
+        }
+        let section = Self { y, palette, bits, data };
+        if ((0..SECTION_BLOCKS).any(|index| section.index(index) >= section.palette.len())) {
+            return Err(EventArgsError::InvalidValue { offset, what : "palette index" });
+        }
+        Ok(section)
+    }
+
+    /// Returns the section height of this section.
+    #[inline]
+    pub fn y(&self) -> i32 { self.y }
+
+    /// Returns the height of the lowest blocks in this section.
+    #[inline]
+    pub fn min_y(&self) -> i64 { (self.y as i64) * 16 }
+
+    /// Returns every distinct block in this section.
+    #[inline]
+    pub fn palette(&self) -> &[Block] { &self.palette }
+
+    /// Returns `true` if every block in this section is the same.
+    #[inline]
+    pub fn is_uniform(&self) -> bool { self.palette.len() == 1 }
+
+    /// Returns the index into [`ChunkSection::palette`] of the block at `x`, `y`, `z` in this section.
+    ///
+    /// ### Panics
+    /// Panics if `x`, `y`, or `z` is not less than 16.
+    #[track_caller]
+    pub fn palette_index(&self, x : usize, y : usize, z : usize) -> usize {
+        assert!(x < SECTION_SIZE && y < SECTION_SIZE && z < SECTION_SIZE, "position <{x}, {y}, {z}> is outside of the section");
+        self.index((y * SECTION_SIZE + z) * SECTION_SIZE + x)
+    }
+
+    /// Returns the block at `x`, `y`, `z` in this section.
+    ///
+    /// ### Panics
+    /// Panics if `x`, `y`, or `z` is not less than 16.
+    #[track_caller]
+    pub fn get(&self, x : usize, y : usize, z : usize) -> &Block {
+        &self.palette[self.palette_index(x, y, z)]
+    }
+
+    fn index(&self, index : usize) -> usize {
+        if (self.bits == 0) { return 0; }
+        let per_word = (64 / self.bits) as usize;
+        let word     = self.data[index / per_word];
+        let shift    = ((index % per_word) as u32) * self.bits;
+        ((word >> shift) & ((1 << self.bits) - 1)) as usize
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a section whose palette has blocks without states.
+    fn encode(y : i32, palette : &[&str], bits : u8, words : &[u64]) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend(y.to_le_bytes());
+        out.extend((palette.len() as u32).to_le_bytes());
+        for id in palette {
+            out.extend((id.len() as u32).to_le_bytes());
+            out.extend(id.as_bytes());
+            out.push(0);
+        }
+        out.push(bits);
+        out.extend((words.len() as u32).to_le_bytes());
+        for word in words {
+            out.extend(word.to_le_bytes());
+        }
+        out
+    }
+
+    /// Packs `index(i)` for each block `i` of a section, with the unused high bits of each word set.
+    fn pack(bits : u32, index : impl Fn(usize) -> u64) -> Vec<u64> {
+        let per_word = (64 / bits) as usize;
+        let mut data = vec![ u64::MAX.checked_shl(per_word as u32 * bits).unwrap_or(0); SECTION_BLOCKS.div_ceil(per_word) ];
+        for i in 0..SECTION_BLOCKS {
+            data[i / per_word] |= index(i) << ((i % per_word) as u32 * bits);
+        }
+        data
+    }
+
+    fn decode(data : &[u8]) -> Result<ChunkSection, EventArgsError> {
+        ChunkSection::decode(&mut EventArgs::new(data))
+    }
+
+    /// The index of the block at `x`, `y`, `z` in sections made by the tests.
+    fn pattern(i : usize, len : u64) -> u64 {
+        let (x, y, z,) = (i % 16, i / 256, (i / 16) % 16,);
+        ((x + 2 * y + 3 * z) as u64) % len
+    }
+
+    #[test]
+    fn uniform_sections_have_no_data() {
+        let section = decode(&encode(-2, &["minecraft:stone"], 0, &[])).unwrap();
+        assert_eq!(section.y(), -2);
+        assert_eq!(section.min_y(), -32);
+        assert!(section.is_uniform());
+        for (x, y, z,) in [(0, 0, 0,), (15, 15, 15,), (3, 9, 14,)] {
+            assert_eq!(section.palette_index(x, y, z), 0);
+            assert_eq!(section.get(x, y, z), &Block::new("minecraft:stone"));
+        }
+    }
+
+    #[test]
+    fn indices_do_not_span_words() {
+        let palette = ["minecraft:air", "minecraft:stone", "minecraft:dirt"];
+        for bits in [5, 32] {
+            let data = pack(bits, |i| pattern(i, 3));
+            // 12 indices fit in each word with 5 bits, leaving 4 bits unused.
+            assert_eq!(data.len(), if (bits == 5) { 342 } else { 2048 });
+            let section = decode(&encode(0, &palette, bits as u8, &data)).unwrap();
+            assert!(! section.is_uniform());
+            for x in 0..16 { for y in 0..16 { for z in 0..16 {
+                let index = (x + 2 * y + 3 * z) % 3;
+                assert_eq!(section.palette_index(x, y, z), index, "<{x}, {y}, {z}> with {bits} bits");
+                assert_eq!(section.get(x, y, z), &Block::new(palette[index]));
+            } } }
+        }
+    }
+
+    #[test]
+    fn malformed_sections_are_errors() {
+        let palette = ["minecraft:air", "minecraft:stone"];
+        let header  = encode(0, &palette, 5, &[]).len() - 4;
+        let bits    = header - 1;
+        assert_eq!(decode(&encode(0, &[], 0, &[])).unwrap_err(), EventArgsError::InvalidValue { offset : 4, what : "palette length" });
+        assert_eq!(decode(&encode(0, &palette, 0, &[])).unwrap_err(), EventArgsError::InvalidValue { offset : bits, what : "bits per index" });
+        assert_eq!(decode(&encode(0, &palette, 33, &[])).unwrap_err(), EventArgsError::InvalidValue { offset : bits, what : "bits per index" });
+
+        let mut data = pack(5, |i| pattern(i, 2));
+        data.pop();
+        assert_eq!(decode(&encode(0, &palette, 5, &data)).unwrap_err(), EventArgsError::InvalidValue { offset : header, what : "section data length" });
+
+        // The word count is right, but the words are missing.
+        let mut short = encode(0, &palette, 5, &pack(5, |i| pattern(i, 2)));
+        short.truncate(short.len() - 4);
+        assert_eq!(decode(&short).unwrap_err(), EventArgsError::UnexpectedEnd { offset : short.len() - 4, len : 8 });
+
+        let data = pack(5, |i| if (i == 4095) { 2 } else { pattern(i, 2) });
+        assert_eq!(decode(&encode(0, &palette, 5, &data)).unwrap_err(), EventArgsError::InvalidValue { offset : header, what : "palette index" });
+        let data = pack(1, |i| (i == 0) as u64);
+        let data = encode(0, &palette[..1], 1, &data);
+        assert_eq!(decode(&data).unwrap_err(), EventArgsError::InvalidValue { offset : data.len() - 64 * 8 - 4, what : "palette index" });
+    }
+
+    #[test]
+    fn snapshots_find_blocks_in_sections() {
+        let palette = ["minecraft:air", "minecraft:stone", "minecraft:dirt"];
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend(encode(0, &palette[..1], 0, &[]));
+        data.extend(encode(-1, &palette, 5, &pack(5, |i| pattern(i, 3))));
+        let snapshot = ChunkSnapshot::decode(ChunkPos::new(-1, 2), &mut EventArgs::new(&data)).unwrap();
+        assert_eq!(snapshot.pos(), ChunkPos::new(-1, 2));
+        assert_eq!(snapshot.sections().iter().map(ChunkSection::y).collect::<Vec<_>>(), [-1, 0]);
+        assert!(snapshot.section(1).is_none());
+
+        // <-1, -1, 37> is <15, 15, 5> in section -1.
+        assert_eq!(snapshot.get(BlockPos::new(-1, -1, 37)), Some(&Block::new(palette[(15 + 2 * 15 + 3 * 5) % 3])));
+        assert_eq!(snapshot.get(BlockPos::new(-16, -16, 32)), Some(&Block::new("minecraft:air")));
+        assert_eq!(snapshot.get(BlockPos::new(-16, 15, 47)), Some(&Block::new("minecraft:air")));
+        assert_eq!(snapshot.get(BlockPos::new(-16, 16, 32)), None);
+        assert_eq!(snapshot.get(BlockPos::new(-16, -17, 32)), None);
+        assert_eq!(snapshot.get(BlockPos::new(0, 0, 32)), None);
+        assert_eq!(snapshot.get(BlockPos::new(-17, 0, 32)), None);
+        assert_eq!(snapshot.get(BlockPos::new(-1, 0, 31)), None);
+        assert_eq!(snapshot.get(BlockPos::new(-1, i64::MIN, 32)), None);
+    }
+
+}
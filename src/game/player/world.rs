@@ -1,11 +1,24 @@
-use crate::game::data::{ BlockPos, ChunkPos, Block };
+use super::snapshot::ChunkSnapshot;
+use crate::event::EventArgs;
+use crate::game::data::{ BlockPos, ChunkPos, Block, BlockBox, BlockFilter, RegionOp };
+use crate::internal::alloc::{ HostBuf, Out };
+use crate::internal::{ AppContext, HandlerError };
+use crate::game::{ Capabilities, optional_imports };
 use core::mem;
-use std::collections::BTreeMap;
+use std::collections::{ BTreeMap, HashMap };
+use std::rc::Rc;
 
 
 unsafe extern "C" {
     safe fn flywheel_world_mark_ready(session_id : u64, x : i32, z : i32);
-    unsafe fn flywheel_world_set_blocks(session_id : u64, in_data : u32);
+    unsafe fn flywheel_world_set_blocks(session_id : u64, in_data : usize);
+}
+
+optional_imports! {
+    fn flywheel_world_set_blocks_palette(session_id : u64, in_data : usize, data_len : u32);
+    fn flywheel_world_region_ops(session_id : u64, in_data : usize, data_len : u32);
+    fn flywheel_world_get_block(session_id : u64, x : i64, y : i64, z : i64, out_ptr : usize, out_len : usize) -> u32;
+    fn flywheel_world_snapshot(session_id : u64, x : i32, z : i32, out_ptr : usize, out_len : usize) -> u32;
 }
 
 
@@ -33,60 +46,638 @@ impl World {
             .submit();
     }
 
+    /// Returns the block at `pos`.
+    ///
+    /// Returns `None` if the chunk containing `pos` is not loaded, if the player left, or if the
+    ///  host does not support [`Capabilities::WORLD_READ`](crate::game::Capabilities::WORLD_READ).
+    pub fn get(&self, pos : BlockPos) -> Option<Block> {
+        let get_block = flywheel_world_get_block()?;
+        let mut ptr   = Out::<usize>::new();
+        let mut len   = Out::<u32>::new();
+        if (unsafe { get_block(
+            self.session_id,
+            pos.x, pos.y, pos.z,
+            ptr.ptr(), len.ptr()
+        ) } == 0) { return None; }
+        let buf = unsafe { HostBuf::from_raw(ptr.get(), len.get()) };
+        EventArgs::new(buf.as_slice()).block().ok()
+    }
+
+    /// Copies every block in a chunk.
+    ///
+    /// This is much faster than calling [`World::get`] for each block. Returns `None` if the chunk
+    ///  is not loaded, if the player left, or if the host does not support
+    ///  [`Capabilities::WORLD_READ`](crate::game::Capabilities::WORLD_READ).
+    pub fn snapshot(&self, chunk : ChunkPos) -> Option<ChunkSnapshot> {
+        let snapshot = flywheel_world_snapshot()?;
+        let mut ptr  = Out::<usize>::new();
+        let mut len  = Out::<u32>::new();
+        if (unsafe { snapshot(
+            self.session_id,
+            chunk.x, chunk.z,
+            ptr.ptr(), len.ptr()
+        ) } == 0) { return None; }
+        let buf = unsafe { HostBuf::from_raw(ptr.get(), len.get()) };
+        ChunkSnapshot::decode(chunk, &mut EventArgs::new(buf.as_slice())).ok()
+    }
+
+    /// Sets every block in `region` to `block`.
+    ///
+    /// See [`RegionOp::Fill`].
+    pub fn fill(&self, region : BlockBox, block : &Block) {
+        self.region(RegionOp::Fill { region, block : block.clone() });
+    }
+
+    /// Sets every block in `region` which passes `filter` to `block`.
+    ///
+    /// If the host supports neither [`Capabilities::REGION_OPS`] nor [`Capabilities::WORLD_READ`],
+    ///  the blocks can not be checked, so nothing is replaced and an error is passed to the error hook.
+    ///
+    /// See [`RegionOp::Replace`].
+    pub fn replace(&self, region : BlockBox, filter : BlockFilter, block : &Block) {
+        self.region(RegionOp::Replace { region, filter, block : block.clone() });
+    }
+
+    /// Sets the faces of `region` to `block`, and everything inside it to air.
+    ///
+    /// See [`RegionOp::Hollow`].
+    pub fn hollow(&self, region : BlockBox, block : &Block) {
+        self.region(RegionOp::Hollow { region, block : block.clone() });
+    }
+
+    /// Sets the faces of `region` to `block`, leaving everything inside it as it is.
+    ///
+    /// See [`RegionOp::Outline`].
+    pub fn outline(&self, region : BlockBox, block : &Block) {
+        self.region(RegionOp::Outline { region, block : block.clone() });
+    }
+
+    /// Copies every block in `src` so that its minimum corner is at `dst`.
+    ///
+    /// If the host supports neither [`Capabilities::REGION_OPS`] nor [`Capabilities::WORLD_READ`],
+    ///  the blocks can not be read, so nothing is copied and an error is passed to the error hook.
+    ///
+    /// See [`RegionOp::Clone`].
+    pub fn clone_region(&self, src : BlockBox, dst : BlockPos) {
+        self.region(RegionOp::Clone { src, dst });
+    }
+
+    /// Carries out an operation on a whole region.
+    ///
+    /// To carry out several operations at once, use [`World::batch_set`].
+    pub fn region(&self, op : RegionOp) {
+        let mut batch = self.batch_set();
+        batch.region(op);
+        batch.submit();
+    }
 
     /// Sets multiple blocks in the world.
     ///
     /// This is an expensive operation.
     #[inline]
     pub fn batch_set(&self) -> BatchSet {
-        BatchSet { session_id : self.session_id, blocks : BTreeMap::new() }
+        BatchSet { session_id : self.session_id, steps : Vec::new() }
     }
 
 }
 
 
+/// Changes to a [`World`] which are sent to the host together when [`BatchSet::submit`] is called.
+///
+/// Created by [`World::batch_set`].
 pub struct BatchSet {
     session_id : u64,
-    blocks     : BTreeMap<BlockPos, Block>
+    steps      : Vec<Step>
+}
+
+/// Changes in a [`BatchSet`], which are sent to the host in order.
+enum Step {
+    Blocks(BTreeMap<BlockPos, Block>),
+    Regions(Vec<RegionOp>)
 }
 
 impl BatchSet {
 
+    /// Sets the block at `pos` to `block`.
+    ///
+    /// For a variant of this function which returns `self` see [`Self::with`].
     pub fn put(&mut self, pos : BlockPos, block : Block) {
-        self.blocks.insert(pos, block);
+        if let Some(Step::Blocks(blocks)) = self.steps.last_mut() {
+            blocks.insert(pos, block);
+        } else {
+            self.steps.push(Step::Blocks(BTreeMap::from([(pos, block,)])));
+        }
     }
 
+    /// Sets the block at `pos` to `block`.
+    ///
+    /// For a variant of this function which mutates `self` see [`Self::put`].
     pub fn with(mut self, pos : BlockPos, block : Block) -> Self {
         self.put(pos, block);
         self
     }
 
+    /// Adds an operation on a whole region.
+    ///
+    /// Region operations and single blocks are applied in the order they were added.
+    pub fn region(&mut self, op : RegionOp) {
+        if let Some(Step::Regions(ops)) = self.steps.last_mut() {
+            ops.push(op);
+        } else {
+            self.steps.push(Step::Regions(vec![ op ]));
+        }
+    }
+
+    /// Sets every block in `region` to `block`.
+    pub fn fill(&mut self, region : BlockBox, block : &Block) {
+        self.region(RegionOp::Fill { region, block : block.clone() });
+    }
+
+    /// Sets every block in `region` which passes `filter` to `block`.
+    ///
+    /// See [`World::replace`] for hosts which can not read blocks.
+    pub fn replace(&mut self, region : BlockBox, filter : BlockFilter, block : &Block) {
+        self.region(RegionOp::Replace { region, filter, block : block.clone() });
+    }
+
+    /// Sets the faces of `region` to `block`, and everything inside it to air.
+    pub fn hollow(&mut self, region : BlockBox, block : &Block) {
+        self.region(RegionOp::Hollow { region, block : block.clone() });
+    }
+
+    /// Sets the faces of `region` to `block`, leaving everything inside it as it is.
+    pub fn outline(&mut self, region : BlockBox, block : &Block) {
+        self.region(RegionOp::Outline { region, block : block.clone() });
+    }
+
+    /// Copies every block in `src` so that its minimum corner is at `dst`.
+    ///
+    /// See [`World::clone_region`] for hosts which can not read blocks.
+    pub fn clone_region(&mut self, src : BlockBox, dst : BlockPos) {
+        self.region(RegionOp::Clone { src, dst });
+    }
+
+    /// Returns every block this batch set changes, and the block it is changed to, as if it
+    ///  were submitted now.
+    ///
+    /// Region operations are expanded into single blocks. Those which depend on the world, such
+    ///  as [`RegionOp::Replace`] and [`RegionOp::Clone`], read the blocks in the world as they
+    ///  are now, with the earlier changes in this batch set applied. Blocks in chunks which are
+    ///  not loaded are left out, as are all of their blocks if the host does not support
+    ///  [`Capabilities::WORLD_READ`].
+    pub fn blocks(&self) -> BTreeMap<BlockPos, Block> {
+        let world         = World { session_id : self.session_id };
+        let mut expansion = Expansion::new(&world, None);
+        for step in &self.steps {
+            match (step) {
+                Step::Blocks(blocks) => expansion.blocks.extend(blocks.iter().map(|(pos, block,)| (*pos, block.clone(),))),
+                Step::Regions(ops)   => for op in ops {
+                    expansion.apply(op);
+                }
+            }
+        }
+        expansion.blocks
+    }
 
+    /// Sends the changes to the host.
+    ///
+    /// If the host supports [`Capabilities::PALETTE_BLOCKS`](crate::game::Capabilities::PALETTE_BLOCKS),
+    ///  blocks are sent palette-encoded. If the host supports [`Capabilities::REGION_OPS`](crate::game::Capabilities::REGION_OPS),
+    ///  region operations are sent as they are. Otherwise, the operations of each step are expanded
+    ///  into single blocks here, and sent in batches of a bounded size. This is much slower for
+    ///  large regions.
+    ///
+    /// Expanding [`RegionOp::Replace`] and [`RegionOp::Clone`] reads the world, so if the host does
+    ///  not support [`Capabilities::WORLD_READ`] either, they are skipped. Each skipped operation is
+    ///  passed to the [`App::on_error`](crate::App::on_error) hook as an error, if an `App` is running.
     pub fn submit(self) {
-        let mut data = vec![0u8; mem::size_of::<u32>()];
-        let mut count = 0u32;
-        for (pos, block,) in self.blocks {
-            let block = block.as_ref();
-            count += 1;
-            data.extend(pos.x.to_le_bytes());
-            data.extend(pos.y.to_le_bytes());
-            data.extend(pos.z.to_le_bytes());
-            data.extend((block.id().len() as u32).to_le_bytes());
-            data.extend(block.id().as_bytes());
-            data.extend((block.states_len() as u8).to_le_bytes());
-            for (state, value,) in block.states() {
-                data.extend((state.len() as u32).to_le_bytes());
-                data.extend(state.as_bytes());
-                data.extend((value.len() as u32).to_le_bytes());
-                data.extend(value.as_bytes());
+        let world = World { session_id : self.session_id };
+        for step in self.steps {
+            match (step) {
+                Step::Blocks(blocks) => world.submit_blocks(&blocks),
+                Step::Regions(ops)   => if let Some(region_ops) = flywheel_world_region_ops() {
+                    let data = encode_regions(&ops);
+                    unsafe { region_ops(
+                        self.session_id,
+                        data.as_ptr() as usize,
+                        data.len() as u32
+                    ); }
+                } else {
+                    let can_read      = flywheel_world_snapshot().is_some();
+                    let mut expansion = Expansion::new(&world, Some(MAX_EXPANDED_BLOCKS));
+                    for op in &ops {
+                        if let Some(kind) = world_reader(op).filter(|_| ! can_read) {
+                            report_unreadable(kind);
+                            continue;
+                        }
+                        expansion.apply(op);
+                    }
+                    expansion.flush();
+                }
             }
         }
-        if (count == 0) { return; }
-        data[0..(mem::size_of::<u32>())].copy_from_slice(&count.to_le_bytes());
-        unsafe { flywheel_world_set_blocks(
-            self.session_id,
-            data.as_ptr() as u32
-        ); }
+    }
+
+}
+
+impl World {
+
+    /// Sends single blocks to the host.
+    fn submit_blocks(&self, blocks : &BTreeMap<BlockPos, Block>) {
+        if (blocks.is_empty()) { return; }
+        if let Some(set_blocks_palette) = flywheel_world_set_blocks_palette() {
+            let data = encode_palette(blocks);
+            unsafe { set_blocks_palette(
+                self.session_id,
+                data.as_ptr() as usize,
+                data.len() as u32
+            ); }
+        } else {
+            let data = encode_legacy(blocks);
+            unsafe { flywheel_world_set_blocks(
+                self.session_id,
+                data.as_ptr() as usize
+            ); }
+        }
+    }
+
+    /// Returns the block at `pos`, reading it from a snapshot of its chunk which is kept in `snapshots`.
+    ///
+    /// Returns `None` if the chunk is not loaded.
+    fn snapshot_get(&self, snapshots : &mut HashMap<ChunkPos, Option<ChunkSnapshot>>, pos : BlockPos) -> Option<Block> {
+        let chunk = pos.chunk();
+        snapshots.entry(chunk).or_insert_with(|| self.snapshot(chunk)).as_ref()?.get(pos).cloned()
+    }
+
+}
+
+
+/// The most blocks which are sent to the host at once when region operations are expanded into
+///  single blocks, for hosts which do not support [`Capabilities::REGION_OPS`](crate::game::Capabilities::REGION_OPS).
+const MAX_EXPANDED_BLOCKS : usize = 1 << 16;
+
+/// Region operations being expanded into single blocks, for hosts which do not support them and
+///  for [`BatchSet::blocks`].
+struct Expansion<'l> {
+    world     : &'l World,
+    /// The blocks changed since the last flush.
+    blocks    : BTreeMap<BlockPos, Block>,
+    /// Snapshots of the chunks which were read since the last flush.
+    snapshots : HashMap<ChunkPos, Option<ChunkSnapshot>>,
+    /// The number of changed blocks at which they are sent to the host, if they are sent at all.
+    limit     : Option<usize>
+}
+
+impl<'l> Expansion<'l> {
+
+    fn new(world : &'l World, limit : Option<usize>) -> Self {
+        Self { world, blocks : BTreeMap::new(), snapshots : HashMap::new(), limit }
+    }
+
+    /// Returns the block at `pos`, with the changes made so far.
+    ///
+    /// Returns `None` if the chunk is not loaded.
+    fn get(&mut self, pos : BlockPos) -> Option<Block> {
+        match (self.blocks.get(&pos)) {
+            Some(block) => Some(block.clone()),
+            None        => self.world.snapshot_get(&mut self.snapshots, pos)
+        }
+    }
+
+    fn set(&mut self, pos : BlockPos, block : Block) {
+        self.blocks.insert(pos, block);
+        if (self.limit.is_some_and(|limit| self.blocks.len() >= limit)) {
+            self.flush();
+        }
+    }
+
+    /// Sends the blocks changed so far to the host.
+    fn flush(&mut self) {
+        self.world.submit_blocks(&mem::take(&mut self.blocks));
+        // The snapshots are out of date now.
+        self.snapshots.clear();
+    }
+
+    /// Carries out a region operation.
+    ///
+    /// Blocks which [`RegionOp::Replace`] or [`RegionOp::Clone`] read in chunks which are not
+    ///  loaded are left as they are.
+    fn apply(&mut self, op : &RegionOp) {
+        match (op) {
+            RegionOp::Fill { region, block } => for pos in region {
+                self.set(pos, block.clone());
+            },
+            RegionOp::Replace { region, filter, block } => for pos in region {
+                if (self.get(pos).is_some_and(|old| filter.matches(&old))) {
+                    self.set(pos, block.clone());
+                }
+            },
+            RegionOp::Hollow { region, block } => {
+                let air = Block::new("minecraft:air");
+                for pos in region {
+                    self.set(pos, if (region.is_on_surface(pos)) { block.clone() } else { air.clone() });
+                }
+            },
+            RegionOp::Outline { region, block } => for pos in region {
+                if (region.is_on_surface(pos)) {
+                    self.set(pos, block.clone());
+                }
+            },
+            RegionOp::Clone { src, dst } => {
+                let offset = *dst - src.min();
+                for pos in clone_order(src, offset) {
+                    if let Some(block) = self.get(pos) {
+                        self.set(pos + offset, block);
+                    }
+                }
+            }
+        }
+    }
+
+}
+
+/// Returns the kind of `op` if expanding it into single blocks reads the blocks in the world.
+fn world_reader(op : &RegionOp) -> Option<&'static str> {
+    match (op) {
+        RegionOp::Replace { .. } => Some("replace"),
+        RegionOp::Clone { .. }   => Some("clone"),
+        _                        => None
+    }
+}
+
+/// Passes an error to the error hook of the running `App`, for a region operation which was
+///  skipped as the host can neither carry it out nor let the plot read the world.
+fn report_unreadable(kind : &str) {
+    let Some(ctx) = AppContext::current() else { return; };
+    ctx.handle_error(HandlerError::new(
+        Rc::from(Capabilities::WORLD_READ),
+        format!("{kind} operation skipped, as the host supports neither `{}` nor `{}`", Capabilities::REGION_OPS, Capabilities::WORLD_READ)
+    ));
+}
+
+/// Returns every block in `src`, ordered so that copying them by `offset` one at a time never
+///  overwrites a block in `src` before it is copied.
+fn clone_order(src : &BlockBox, offset : BlockPos) -> impl Iterator<Item = BlockPos> + use<> {
+    // Each axis is walked away from the direction the blocks are copied in.
+    fn along(lo : i64, hi : i64, reverse : bool) -> impl Iterator<Item = i64> + Clone {
+        (lo..=hi).map(move |i| if (reverse) { hi - (i - lo) } else { i })
+    }
+    let (min, max,) = (src.min(), src.max(),);
+    along(min.y, max.y, offset.y > 0).flat_map(move |y|
+        along(min.z, max.z, offset.z > 0).flat_map(move |z|
+            along(min.x, max.x, offset.x > 0).map(move |x| BlockPos::new(x, y, z))
+        )
+    )
+}
+
+
+/// Encodes single blocks, each with its full position and block.
+fn encode_legacy(blocks : &BTreeMap<BlockPos, Block>) -> Vec<u8> {
+    let mut data = Vec::with_capacity(mem::size_of::<u32>());
+    data.extend((blocks.len() as u32).to_le_bytes());
+    for (pos, block,) in blocks {
+        data.extend(pos.x.to_le_bytes());
+        data.extend(pos.y.to_le_bytes());
+        data.extend(pos.z.to_le_bytes());
+        data.extend((block.id().len() as u32).to_le_bytes());
+        data.extend(block.id().as_bytes());
+        data.extend((block.states_len() as u8).to_le_bytes());
+        for (state, value,) in block.states() {
+            data.extend((state.len() as u32).to_le_bytes());
+            data.extend(state.as_bytes());
+            data.extend((value.len() as u32).to_le_bytes());
+            data.extend(value.as_bytes());
+        }
+    }
+    data
+}
+
+/// Encodes single blocks with a palette, grouped by chunk section.
+///
+/// The data is a varint palette length followed by each distinct block, then a varint section
+///  count followed by each section. A block is its ID, a `u8` count of its states, and the name
+///  and value of each state. A section is its `x`, `y`, and `z` section coordinates as zigzag
+///  varints, then a varint run count followed by each run. A run is the `u16` index of its first
+///  block in the section (`y * 256 + z * 16 + x`), then the varint number of blocks in the run,
+///  then the varint palette index of the block which fills it.
+///
+/// Varints are unsigned LEB128. Strings are prefixed by their length in bytes as a varint.
+fn encode_palette(blocks : &BTreeMap<BlockPos, Block>) -> Vec<u8> {
+    let mut palette  = Vec::<&Block>::new();
+    let mut by_id    = HashMap::<&str, Vec<u32>>::new();
+    let mut sections = BTreeMap::<(i64, i64, i64,), Vec<(u16, u32,)>>::new();
+    for (pos, block,) in blocks {
+        let candidates = by_id.entry(block.id()).or_default();
+        let index      = match (candidates.iter().find(|&&index| palette[index as usize] == block)) {
+            Some(&index) => index,
+            None         => {
+                let index = palette.len() as u32;
+                palette.push(block);
+                candidates.push(index);
+                index
+            }
+        };
+        let section = (pos.x.div_euclid(16), pos.y.div_euclid(16), pos.z.div_euclid(16),);
+        let local   = (pos.y.rem_euclid(16) * 256 + pos.z.rem_euclid(16) * 16 + pos.x.rem_euclid(16)) as u16;
+        sections.entry(section).or_default().push((local, index,));
+    }
+
+    let mut data = Vec::new();
+    write_varint(&mut data, palette.len() as u64);
+    for block in palette {
+        write_block(&mut data, block);
+    }
+    write_varint(&mut data, sections.len() as u64);
+    for ((x, y, z,), mut blocks,) in sections {
+        write_zigzag(&mut data, x);
+        write_zigzag(&mut data, y);
+        write_zigzag(&mut data, z);
+        blocks.sort_unstable_by_key(|(local, _,)| *local);
+        let mut runs = Vec::<(u16, u32, u32,)>::new();
+        for (local, index,) in blocks {
+            match (runs.last_mut()) {
+                Some((start, len, run_index,)) if (*run_index == index && (*start as u32) + *len == (local as u32)) => { *len += 1; },
+                _ => runs.push((local, 1, index,))
+            }
+        }
+        write_varint(&mut data, runs.len() as u64);
+        for (start, len, index,) in runs {
+            data.extend(start.to_le_bytes());
+            write_varint(&mut data, len as u64);
+            write_varint(&mut data, index as u64);
+        }
+    }
+    data
+}
+
+/// Encodes region operations.
+///
+/// The data is a varint operation count, followed by each operation. An operation is a `u8` kind
+///  (`0` fill, `1` replace, `2` hollow, `3` outline, or `4` clone), then the minimum and maximum
+///  corners of its region as zigzag varints, then:
+/// - for fill, hollow, and outline, the block,
+/// - for replace, the filter as a `u8` (`0` for an ID or `1` for an exact block) followed by the
+///   ID or block, then the block,
+/// - for clone, the destination as zigzag varints.
+///
+/// IDs and blocks are encoded in the same way as [`encode_palette`].
+fn encode_regions(ops : &[RegionOp]) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_varint(&mut data, ops.len() as u64);
+    for op in ops {
+        let (kind, region,) = match (op) {
+            RegionOp::Fill    { region, .. } => (0, region,),
+            RegionOp::Replace { region, .. } => (1, region,),
+            RegionOp::Hollow  { region, .. } => (2, region,),
+            RegionOp::Outline { region, .. } => (3, region,),
+            RegionOp::Clone   { src, .. }    => (4, src,)
+        };
+        data.push(kind);
+        for pos in [region.min(), region.max()] {
+            write_zigzag(&mut data, pos.x);
+            write_zigzag(&mut data, pos.y);
+            write_zigzag(&mut data, pos.z);
+        }
+        match (op) {
+            RegionOp::Fill { block, .. }
+            | RegionOp::Hollow { block, .. }
+            | RegionOp::Outline { block, .. } => write_block(&mut data, block),
+            RegionOp::Replace { filter, block, .. } => {
+                match (filter) {
+                    BlockFilter::Id(id) => {
+                        data.push(0);
+                        write_str(&mut data, id);
+                    },
+                    BlockFilter::Exact(exact) => {
+                        data.push(1);
+                        write_block(&mut data, exact);
+                    }
+                }
+                write_block(&mut data, block);
+            },
+            RegionOp::Clone { dst, .. } => {
+                write_zigzag(&mut data, dst.x);
+                write_zigzag(&mut data, dst.y);
+                write_zigzag(&mut data, dst.z);
+            }
+        }
+    }
+    data
+}
+
+
+fn write_block(out : &mut Vec<u8>, block : &Block) {
+    write_str(out, block.id());
+    out.push(block.states_len() as u8);
+    for (state, value,) in block.states() {
+        write_str(out, state);
+        write_str(out, value);
+    }
+}
+
+fn write_varint(out : &mut Vec<u8>, mut value : u64) {
+    while (value >= 0x80) {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_zigzag(out : &mut Vec<u8>, value : i64) {
+    write_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn write_str(out : &mut Vec<u8>, value : &str) {
+    write_varint(out, value.len() as u64);
+    out.extend(value.as_bytes());
+}
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use super::MAX_EXPANDED_BLOCKS;
+    use crate::prelude::*;
+    use crate::game::Capabilities;
+    use crate::game::data::BlockFilter;
+    use crate::testkit::{ self, HostCall };
+    use core::cell::RefCell;
+    use std::rc::Rc;
+
+    fn without_region_ops() {
+        testkit::set_capabilities([Capabilities::PALETTE_BLOCKS, Capabilities::WORLD_READ]);
+    }
+
+    /// Returns the number of blocks in each recorded `SetBlocks` call.
+    fn set_blocks() -> Vec<usize> {
+        testkit::take_calls().into_iter().filter_map(|call| match (call) {
+            HostCall::SetBlocks { blocks, .. } => Some(blocks.len()),
+            _                                  => None
+        }).collect()
+    }
+
+    #[test]
+    fn expanded_ops_are_merged() {
+        without_region_ops();
+        let world = testkit::player_joined(Uuid::nil(), "a").world();
+        let mut batch = world.batch_set();
+        batch.fill(BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(3, 0, 3)), &Block::new("minecraft:stone"));
+        batch.fill(BlockBox::new(BlockPos::new(2, 0, 2), BlockPos::new(5, 0, 5)), &Block::new("minecraft:dirt"));
+        batch.replace(BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(5, 0, 5)), BlockFilter::Id("minecraft:stone".to_string()), &Block::new("minecraft:glass"));
+        batch.submit();
+        assert_eq!(set_blocks(), [28]);
+        assert_eq!(world.get(BlockPos::new(0, 0, 0)), Some(Block::new("minecraft:glass")));
+        assert_eq!(world.get(BlockPos::new(2, 0, 2)), Some(Block::new("minecraft:dirt")));
+    }
+
+    #[test]
+    fn large_ops_are_sent_in_batches() {
+        without_region_ops();
+        let world = testkit::player_joined(Uuid::nil(), "a").world();
+        world.fill(BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(299, 0, 299)), &Block::new("minecraft:stone"));
+        assert_eq!(set_blocks(), [MAX_EXPANDED_BLOCKS, 300 * 300 - MAX_EXPANDED_BLOCKS]);
+    }
+
+    #[test]
+    fn overlapping_clones_match_the_host() {
+        let src = BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(3, 1, 0));
+        let run = |world : World| {
+            for pos in src {
+                testkit::set_block(unsafe { Player::from_session_id(world.session_id) }, pos, Block::new(&format!("minecraft:b{}_{}", pos.x, pos.y)));
+            }
+            world.clone_region(src, BlockPos::new(1, 1, 0));
+            BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(4, 2, 0)).iter().map(|pos| world.get(pos)).collect::<Vec<_>>()
+        };
+        let expected = run(testkit::player_joined(Uuid::nil(), "a").world());
+        without_region_ops();
+        assert_eq!(run(testkit::player_joined(Uuid::nil(), "b").world()), expected);
+    }
+
+
+    #[test]
+    fn unreadable_ops_reach_the_error_hook() {
+        testkit::set_capabilities([Capabilities::PALETTE_BLOCKS]);
+        let errors  = Rc::new(RefCell::new(Vec::new()));
+        let mut app = App::new();
+        app.on_error({
+            let errors = Rc::clone(&errors);
+            move |err| errors.borrow_mut().push((err.event().to_string(), err.message().to_string(),))
+        });
+        app.on_player_joined(|player : Player| async move {
+            let region = BlockBox::new(BlockPos::new(0, 0, 0), BlockPos::new(1, 0, 1));
+            let mut batch = player.world().batch_set();
+            batch.replace(region, BlockFilter::Id("minecraft:air".to_string()), &Block::new("minecraft:stone"));
+            batch.fill(region, &Block::new("minecraft:dirt"));
+            batch.clone_region(region, BlockPos::new(5, 0, 0));
+            batch.submit();
+        });
+        testkit::player_joined(Uuid::nil(), "a");
+        app.run();
+        assert_eq!(set_blocks(), [4]);
+        let message = |kind| format!("{kind} operation skipped, as the host supports neither `flywheel_world_region_ops` nor `flywheel_world_read`");
+        assert_eq!(*errors.borrow(), [
+            ("flywheel_world_read".to_string(), message("replace"),),
+            ("flywheel_world_read".to_string(), message("clone"),)
+        ]);
     }
 
 }
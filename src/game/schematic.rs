@@ -0,0 +1,751 @@
+//! Loading structures built in Minecraft, pasting them into worlds, and saving them.
+//!
+//! [`Schematic`] reads Sponge schematics (`.schem`, versions 1 to 3) and vanilla structure files
+//!  (`.nbt`), compressed or not. Only blocks are loaded. Block entity data, such as the contents
+//!  of chests and the text on signs, and entities are not.
+//!
+//! Schematics can also be made from the blocks in a [`BatchSet`] or a region of a world, and
+//!  saved as Sponge schematics with [`Schematic::to_bytes`].
+//!
+//! ```ignore
+//! static ARENA : &[u8] = include_bytes!("arena.schem");
+//!
+//! let arena = Schematic::from_bytes(ARENA)?;
+//! app.on_world_chunk_loading(move |player : Player, chunk : ChunkPos| {
+//!     let mut batch_set = player.world().batch_set();
+//!     arena.paste_in_chunk(&mut batch_set, Placement::at(BlockPos::new(0, 64, 0)), chunk);
+//!     batch_set.submit();
+//!     player.world().mark_ready(chunk);
+//! });
+//!
+//! let generated = Schematic::from_blocks(batch_set.blocks())?;
+//! let copied    = Schematic::from_world(&player.world(), BlockBox::new(a, b))?;
+//! std::fs::write("generated.schem", generated.to_bytes())?;
+//! ```
+
+
+use super::BatchSet;
+use super::player::World;
+use super::data::{ BlockPos, ChunkPos, BlockBox, Block, BlockParseError, Rotation, Mirror };
+use super::nbt::{ Nbt, NbtError };
+use miniz_oxide::inflate::TINFLStatus;
+use core::fmt;
+use std::collections::{ BTreeMap, HashMap };
+use std::error::Error;
+
+
+/// The most blocks a schematic can have.
+const MAX_VOLUME : u64 = 1 << 26;
+
+/// The most bytes a compressed file can decompress to. This is enough for the block data of a
+///  schematic with `MAX_VOLUME` blocks, and 16 MiB for its palette and other fields.
+const MAX_DECOMPRESSED : usize = (MAX_VOLUME as usize) * 4 + (1 << 24);
+
+/// Marks a position in `Schematic::blocks` which has no block, and is left as it is when pasted.
+const NO_BLOCK : u32 = u32::MAX;
+
+/// The block which Sponge schematics use for positions which are left as they are.
+const STRUCTURE_VOID : &str = "minecraft:structure_void";
+
+/// The data version written to schematics which do not have one, which is Minecraft 1.21.
+const DEFAULT_DATA_VERSION : i32 = 3953;
+
+
+/// A structure of blocks, loaded from a Sponge schematic or a vanilla structure file.
+#[derive(Clone, Debug)]
+pub struct Schematic {
+    size         : BlockPos,
+    offset       : BlockPos,
+    data_version : Option<i32>,
+    palette      : Vec<Block>,
+    /// An index into `palette` for each position, by `y`, then `z`, then `x`.
+    blocks       : Vec<u32>
+}
+
+impl Schematic {
+
+    /// Reads a schematic from the contents of a `.schem` or `.nbt` file.
+    ///
+    /// The file may be gzip-compressed or not.
+    pub fn from_bytes(data : &[u8]) -> Result<Self, SchematicError> {
+        let (_, root,) = if (data.starts_with(&[0x1F, 0x8B])) {
+            Nbt::read(&gunzip(data, MAX_DECOMPRESSED)?)?
+        } else {
+            Nbt::read(data)?
+        };
+        Self::from_nbt(&root)
+    }
+
+    /// Reads a schematic from the root tag of a `.schem` or `.nbt` file.
+    pub fn from_nbt(root : &Nbt) -> Result<Self, SchematicError> {
+        if let Some(schematic) = root.get("Schematic") {
+            Self::from_sponge(schematic)
+        } else if (root.get("Version").is_some() || root.get("BlockData").is_some()) {
+            Self::from_sponge(root)
+        } else if (root.get("size").is_some() && root.get("blocks").is_some()) {
+            Self::from_structure(root)
+        } else {
+            Err(SchematicError::UnknownFormat)
+        }
+    }
+
+    fn empty(size : BlockPos) -> Result<Self, SchematicError> {
+        if (size.x < 0 || size.y < 0 || size.z < 0) {
+            return Err(SchematicError::InvalidField { field : "size" });
+        }
+        // Sponge schematics store sizes as unsigned shorts.
+        if (size.x > (u16::MAX as i64) || size.y > (u16::MAX as i64) || size.z > (u16::MAX as i64)) {
+            return Err(SchematicError::TooLarge);
+        }
+        let volume = (size.x as u64).saturating_mul(size.y as u64).saturating_mul(size.z as u64);
+        if (volume > MAX_VOLUME) {
+            return Err(SchematicError::TooLarge);
+        }
+        Ok(Self {
+            size,
+            offset       : BlockPos::ZERO,
+            data_version : None,
+            palette      : Vec::new(),
+            blocks       : vec![NO_BLOCK; volume as usize]
+        })
+    }
+
+    /// Reads a Sponge schematic, from the compound which has its `Version`.
+    fn from_sponge(schematic : &Nbt) -> Result<Self, SchematicError> {
+        let version = int_field(schematic, "Version")?;
+        // Sizes are unsigned shorts, which NBT stores as signed shorts.
+        let size = |field| int_field(schematic, field).map(|value| (value as u16) as i64);
+        let mut out = Self::empty(BlockPos::new(size("Width")?, size("Height")?, size("Length")?))?;
+        out.data_version = schematic.get("DataVersion").and_then(Nbt::as_i64).map(|version| version as i32);
+        if let Some(offset) = schematic.get("Offset") {
+            let &[x, y, z] = offset.as_int_array().ok_or(SchematicError::InvalidField { field : "Offset" })? else {
+                return Err(SchematicError::InvalidField { field : "Offset" });
+            };
+            out.offset = BlockPos::new(x as i64, y as i64, z as i64);
+        }
+        let (palette, data,) = if (version >= 3) {
+            let Some(blocks) = schematic.get("Blocks") else { return Ok(out); };
+            (blocks.get("Palette"), blocks.get("Data"),)
+        } else {
+            (schematic.get("Palette"), schematic.get("BlockData"),)
+        };
+        let palette = palette.and_then(Nbt::as_compound).ok_or(SchematicError::InvalidField { field : "Palette" })?;
+        let data    = data.and_then(Nbt::as_byte_array).ok_or(SchematicError::InvalidField { field : "BlockData" })?;
+
+        let mut blocks = vec![None; palette.len()];
+        for (state, index,) in palette {
+            let index = index.as_i64().and_then(|index| usize::try_from(index).ok())
+                .filter(|&index| index < blocks.len())
+                .ok_or(SchematicError::InvalidField { field : "Palette" })?;
+            let block = state.parse::<Block>().map_err(|error| SchematicError::InvalidBlock { state : state.clone(), error })?;
+            blocks[index] = Some(block);
+        }
+        let blocks = blocks.into_iter().collect::<Option<Vec<_>>>().ok_or(SchematicError::InvalidField { field : "Palette" })?;
+        // Structure voids are left out of the palette, and leave blocks as they are when pasted.
+        let void    = Block::new(STRUCTURE_VOID);
+        let indices = blocks.into_iter().map(|block| {
+            if (block == void) { return NO_BLOCK; }
+            out.palette.push(block);
+            (out.palette.len() - 1) as u32
+        }).collect::<Vec<_>>();
+
+        let mut data = data.iter().map(|&byte| byte as u8);
+        for slot in &mut out.blocks {
+            let index = read_varint(&mut data).and_then(|index| indices.get(index as usize))
+                .ok_or(SchematicError::InvalidField { field : "BlockData" })?;
+            *slot = *index;
+        }
+        if (data.next().is_some()) {
+            return Err(SchematicError::InvalidField { field : "BlockData" });
+        }
+        Ok(out)
+    }
+
+    /// Reads a vanilla structure file.
+    fn from_structure(structure : &Nbt) -> Result<Self, SchematicError> {
+        let size     = vec3_field(structure, "size")?;
+        let mut out  = Self::empty(size)?;
+        out.data_version = structure.get("DataVersion").and_then(Nbt::as_i64).map(|version| version as i32);
+        // Structures with several palettes, such as shipwrecks, pick one at random. The first is used here.
+        let palette = structure.get("palette")
+            .or_else(|| structure.get("palettes").and_then(Nbt::as_list).and_then(|palettes| palettes.first()))
+            .and_then(Nbt::as_list)
+            .ok_or(SchematicError::InvalidField { field : "palette" })?;
+        for entry in palette {
+            let name      = entry.get("Name").and_then(Nbt::as_str).ok_or(SchematicError::InvalidField { field : "Name" })?;
+            let mut block = Block::new(name);
+            if let Some(properties) = entry.get("Properties") {
+                let properties = properties.as_compound().ok_or(SchematicError::InvalidField { field : "Properties" })?;
+                if (properties.len() >= 16) {
+                    return Err(SchematicError::InvalidField { field : "Properties" });
+                }
+                for (state, value,) in properties {
+                    block.set(state, value.as_str().ok_or(SchematicError::InvalidField { field : "Properties" })?);
+                }
+            }
+            out.palette.push(block);
+        }
+        let blocks = structure.get("blocks").and_then(Nbt::as_list).ok_or(SchematicError::InvalidField { field : "blocks" })?;
+        for entry in blocks {
+            let pos   = vec3_field(entry, "pos")?;
+            let state = entry.get("state").and_then(Nbt::as_i64)
+                .filter(|&state| 0 <= state && (state as usize) < out.palette.len())
+                .ok_or(SchematicError::InvalidField { field : "state" })?;
+            let index = out.index(pos).ok_or(SchematicError::InvalidField { field : "pos" })?;
+            out.blocks[index] = state as u32;
+        }
+        Ok(out)
+    }
+
+    /// Makes a schematic from blocks and their positions, such as those returned by [`BatchSet::blocks`].
+    ///
+    /// The schematic covers the smallest box which contains every position, and its minimum
+    ///  corner is the minimum corner of that box. Positions in the box which are not given are
+    ///  left as they are when the schematic is pasted.
+    pub fn from_blocks(blocks : impl IntoIterator<Item = (BlockPos, Block,)>) -> Result<Self, SchematicError> {
+        let blocks = blocks.into_iter().collect::<Vec<_>>();
+        let Some(region) = blocks.iter().map(|(pos, _,)| BlockBox::new(*pos, *pos)).reduce(|a, b| a.union(&b)) else {
+            return Self::empty(BlockPos::ZERO);
+        };
+        let mut out   = Self::empty(box_size(region)?)?;
+        let mut by_id = HashMap::new();
+        for (pos, block,) in blocks {
+            let index = out.index(pos - region.min()).unwrap();
+            out.blocks[index] = out.palette_index(&mut by_id, block);
+        }
+        Ok(out)
+    }
+
+    /// Copies the blocks in `region` of `world`.
+    ///
+    /// The minimum corner of the schematic is the minimum corner of `region`. Blocks in chunks
+    ///  which are not loaded are left as they are when the schematic is pasted.
+    pub fn from_world(world : &World, region : BlockBox) -> Result<Self, SchematicError> {
+        let mut out   = Self::empty(box_size(region)?)?;
+        let mut by_id = HashMap::new();
+        for (chunk, part,) in region.chunks() {
+            let Some(snapshot) = world.snapshot(chunk) else { continue; };
+            for pos in part {
+                let Some(block) = snapshot.get(pos) else { continue; };
+                let index = out.index(pos - region.min()).unwrap();
+                out.blocks[index] = out.palette_index(&mut by_id, block.clone());
+            }
+        }
+        Ok(out)
+    }
+
+    /// Returns the index of `block` in `palette`, adding it if it is not there.
+    ///
+    /// `by_id` keeps the indices of the blocks in the palette with each ID.
+    fn palette_index(&mut self, by_id : &mut HashMap<String, Vec<u32>>, block : Block) -> u32 {
+        let candidates = by_id.entry(block.id().to_string()).or_default();
+        if let Some(&index) = candidates.iter().find(|&&index| self.palette[index as usize] == block) {
+            return index;
+        }
+        let index = self.palette.len() as u32;
+        self.palette.push(block);
+        candidates.push(index);
+        index
+    }
+
+    /// Returns the size of this schematic along each axis.
+    #[inline]
+    pub fn size(&self) -> BlockPos { self.size }
+
+    /// Returns the positions in this schematic, from `<0, 0, 0>` to one less than [`Schematic::size`],
+    ///  or `None` if it is empty.
+    pub fn local_box(&self) -> Option<BlockBox> {
+        if (self.blocks.is_empty()) { return None; }
+        Some(BlockBox::new(BlockPos::ZERO, self.size - BlockPos::splat(1)))
+    }
+
+    /// Returns the offset stored in a Sponge schematic, which is where it was copied from
+    ///  relative to the player who copied it. This is `<0, 0, 0>` for other schematics.
+    #[inline]
+    pub fn offset(&self) -> BlockPos { self.offset }
+
+    /// Sets the offset written by [`Schematic::to_bytes`], which is where the schematic is pasted
+    ///  relative to the player by tools such as WorldEdit.
+    #[inline]
+    pub fn set_offset(&mut self, offset : BlockPos) { self.offset = offset; }
+
+    /// Returns the data version of Minecraft the schematic was saved in, if it has one.
+    #[inline]
+    pub fn data_version(&self) -> Option<i32> { self.data_version }
+
+    /// Sets the data version of Minecraft written by [`Schematic::to_bytes`].
+    ///
+    /// If it is not set, the data version of Minecraft 1.21 is written.
+    #[inline]
+    pub fn set_data_version(&mut self, data_version : i32) { self.data_version = Some(data_version); }
+
+    /// Returns every distinct block in this schematic.
+    #[inline]
+    pub fn palette(&self) -> &[Block] { &self.palette }
+
+    /// Returns the block at `local`, relative to the minimum corner of this schematic.
+    ///
+    /// Returns `None` if `local` is outside of this schematic, or if the schematic leaves it
+    ///  as it is, such as where a vanilla structure had a structure void.
+    pub fn get(&self, local : BlockPos) -> Option<&Block> {
+        let index = self.blocks[self.index(local)?];
+        self.palette.get(index as usize)
+    }
+
+    /// Returns an iterator over every block in this schematic and its position, relative to
+    ///  the minimum corner of this schematic.
+    pub fn iter(&self) -> impl Iterator<Item = (BlockPos, &Block,)> {
+        self.local_box().into_iter().flat_map(|region| region.iter())
+            .zip(&self.blocks)
+            .filter_map(|(local, &index,)| Some((local, self.palette.get(index as usize)?,)))
+    }
+
+    /// Returns the blocks this schematic covers when pasted with `placement`, or `None` if it is empty.
+    pub fn placed_box(&self, placement : Placement) -> Option<BlockBox> {
+        let local = self.local_box()?;
+        Some(BlockBox::new(placement.apply(local.min()), placement.apply(local.max())))
+    }
+
+    /// Adds every block in this schematic to `batch_set`, placed with `placement`.
+    pub fn paste(&self, batch_set : &mut BatchSet, placement : Placement) {
+        let palette = placement.apply_palette(&self.palette);
+        for (local, &index,) in self.local_box().into_iter().flat_map(|region| region.iter()).zip(&self.blocks) {
+            if let Some(Some(block)) = palette.get(index as usize) {
+                batch_set.put(placement.apply(local), block.clone());
+            }
+        }
+    }
+
+    /// Adds the blocks in this schematic which are in `chunk` to `batch_set`, placed with `placement`.
+    ///
+    /// This only visits the part of the schematic in the chunk, so it can be used when each
+    ///  chunk is loaded.
+    pub fn paste_in_chunk(&self, batch_set : &mut BatchSet, placement : Placement, chunk : ChunkPos) {
+        let Some(region) = self.placed_box(placement).and_then(|region| region.in_chunk(chunk)) else { return; };
+        let palette = placement.apply_palette(&self.palette);
+        for pos in region {
+            let Some(index) = self.index(placement.unapply(pos)) else { continue; };
+            if let Some(Some(block)) = palette.get(self.blocks[index] as usize) {
+                batch_set.put(pos, block.clone());
+            }
+        }
+    }
+
+    /// Writes this schematic as the root tag of a version 3 Sponge schematic.
+    ///
+    /// Positions which this schematic leaves as they are are written as structure voids.
+    pub fn to_nbt(&self) -> Nbt {
+        // Only blocks which are used are written, in the order they are first used.
+        let mut indices = vec![None; self.palette.len()];
+        let mut void    = None;
+        let mut palette = BTreeMap::new();
+        let mut data    = Vec::with_capacity(self.blocks.len());
+        let mut add     = |state : String| {
+            let written = palette.len() as u32;
+            palette.insert(state, Nbt::Int(written as i32));
+            written
+        };
+        for &index in &self.blocks {
+            let written = match (indices.get_mut(index as usize)) {
+                Some(slot) => *slot.get_or_insert_with(|| add(self.palette[index as usize].to_string())),
+                None       => *void.get_or_insert_with(|| add(STRUCTURE_VOID.to_string()))
+            };
+            write_varint(&mut data, written);
+        }
+        let schematic = BTreeMap::from([
+            ("Version".to_string(),     Nbt::Int(3),),
+            ("DataVersion".to_string(), Nbt::Int(self.data_version.unwrap_or(DEFAULT_DATA_VERSION)),),
+            ("Width".to_string(),       Nbt::Short(self.size.x as u16 as i16),),
+            ("Height".to_string(),      Nbt::Short(self.size.y as u16 as i16),),
+            ("Length".to_string(),      Nbt::Short(self.size.z as u16 as i16),),
+            ("Offset".to_string(),      Nbt::IntArray(vec![ self.offset.x as i32, self.offset.y as i32, self.offset.z as i32 ]),),
+            ("Blocks".to_string(),      Nbt::Compound(BTreeMap::from([
+                ("Palette".to_string(), Nbt::Compound(palette),),
+                ("Data".to_string(),    Nbt::ByteArray(data.into_iter().map(|byte| byte as i8).collect()),)
+            ])),)
+        ]);
+        Nbt::Compound(BTreeMap::from([("Schematic".to_string(), Nbt::Compound(schematic),)]))
+    }
+
+    /// Writes this schematic as the contents of a gzip-compressed `.schem` file, in version 3 of
+    ///  the Sponge schematic format.
+    ///
+    /// See [`Schematic::to_nbt`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        gzip(&self.to_nbt().write(""))
+    }
+
+    /// Returns the index into `blocks` of `local`, if it is in this schematic.
+    fn index(&self, local : BlockPos) -> Option<usize> {
+        if (local.x < 0 || local.y < 0 || local.z < 0 || local.x >= self.size.x || local.y >= self.size.y || local.z >= self.size.z) {
+            return None;
+        }
+        Some((((local.y * self.size.z) + local.z) * self.size.x + local.x) as usize)
+    }
+
+}
+
+fn int_field(nbt : &Nbt, field : &'static str) -> Result<i64, SchematicError> {
+    nbt.get(field).and_then(Nbt::as_i64).ok_or(SchematicError::InvalidField { field })
+}
+
+/// Reads a list of three ints, as vanilla structures store positions.
+fn vec3_field(nbt : &Nbt, field : &'static str) -> Result<BlockPos, SchematicError> {
+    match (nbt.get(field).and_then(Nbt::as_list)) {
+        Some([x, y, z]) => match (x.as_i64(), y.as_i64(), z.as_i64(),) {
+            (Some(x), Some(y), Some(z),) => Ok(BlockPos::new(x, y, z)),
+            _                            => Err(SchematicError::InvalidField { field })
+        },
+        _ => Err(SchematicError::InvalidField { field })
+    }
+}
+
+/// Returns the number of blocks along each axis of `region`.
+fn box_size(region : BlockBox) -> Result<BlockPos, SchematicError> {
+    let size = |min : i64, max : i64| max.checked_sub(min).and_then(|size| size.checked_add(1)).ok_or(SchematicError::TooLarge);
+    Ok(BlockPos::new(
+        size(region.min().x, region.max().x)?,
+        size(region.min().y, region.max().y)?,
+        size(region.min().z, region.max().z)?
+    ))
+}
+
+fn write_varint(out : &mut Vec<u8>, mut value : u32) {
+    while (value >= 0x80) {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes : &mut impl Iterator<Item = u8>) -> Option<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7F) as u32).checked_shl(shift)?;
+        if (byte & 0x80 == 0) { return Some(value); }
+    }
+    None
+}
+
+/// Decompresses a gzip file which decompresses to at most `limit` bytes.
+fn gunzip(data : &[u8], limit : usize) -> Result<Vec<u8>, SchematicError> {
+    let deflated = gzip_body(data).ok_or(SchematicError::Compression)?;
+    miniz_oxide::inflate::decompress_to_vec_with_limit(deflated, limit).map_err(|error| match (error.status) {
+        TINFLStatus::HasMoreOutput => SchematicError::TooLarge,
+        _                          => SchematicError::Compression
+    })
+}
+
+/// Returns the compressed data in a gzip file, after its header, or `None` if the header is malformed.
+fn gzip_body(data : &[u8]) -> Option<&[u8]> {
+    const FHCRC    : u8 = 1 << 1;
+    const FEXTRA   : u8 = 1 << 2;
+    const FNAME    : u8 = 1 << 3;
+    const FCOMMENT : u8 = 1 << 4;
+    if (data.get(2) != Some(&8)) { return None; }
+    let flags   = *data.get(3)?;
+    let mut pos = 10;
+    if (flags & FEXTRA != 0) {
+        pos += 2 + u16::from_le_bytes([*data.get(pos)?, *data.get(pos + 1)?]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if (flags & flag != 0) {
+            pos += data.get(pos..)?.iter().position(|&byte| byte == 0)? + 1;
+        }
+    }
+    if (flags & FHCRC != 0) { pos += 2; }
+    data.get(pos..)
+}
+
+
+/// Compresses `data` as a gzip file.
+fn gzip(data : &[u8]) -> Vec<u8> {
+    // No flags, no modification time, and an unknown OS, so the output only depends on `data`.
+    let mut out = vec![ 0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 0xFF ];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, 6));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// Returns the CRC-32 of `data`, as used by gzip.
+fn crc32(data : &[u8]) -> u32 {
+    const TABLE : [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i     = 0;
+        while (i < 256) {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while (bit < 8) {
+                crc  = if (crc & 1 != 0) { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+    ! data.iter().fold(! 0u32, |crc, &byte| TABLE[((crc as u8) ^ byte) as usize] ^ (crc >> 8))
+}
+
+
+/// Where and how a [`Schematic`] is pasted.
+///
+/// The schematic is first mirrored, then rotated around its minimum corner, then moved so
+///  that the block which was at its minimum corner is at the origin. This is the same as
+///  vanilla structure blocks. Rotating or mirroring a schematic can make it extend into
+///  negative `x` or `z` from the origin, such as [`Rotation::Clockwise90`] and
+///  [`Mirror::FrontBack`] do in `x`. Use [`Schematic::placed_box`] to find the blocks it covers.
+///
+/// ```ignore
+/// let placement = Placement::at(BlockPos::new(0, 64, 0)).rotate(Rotation::Clockwise90).skip_air(true);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Placement {
+    origin   : BlockPos,
+    rotation : Rotation,
+    mirror   : Mirror,
+    skip_air : bool
+}
+
+impl Placement {
+
+    /// Places the minimum corner of a schematic at `origin`, without rotating or mirroring it.
+    #[inline]
+    pub const fn at(origin : BlockPos) -> Self {
+        Self { origin, rotation : Rotation::None, mirror : Mirror::None, skip_air : false }
+    }
+
+    /// Rotates the schematic around its minimum corner.
+    #[inline]
+    pub const fn rotate(mut self, rotation : Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Mirrors the schematic before it is rotated.
+    #[inline]
+    pub const fn mirror(mut self, mirror : Mirror) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// If `skip_air` is `true`, air in the schematic leaves the blocks in the world as they are,
+    ///  instead of replacing them.
+    #[inline]
+    pub const fn skip_air(mut self, skip_air : bool) -> Self {
+        self.skip_air = skip_air;
+        self
+    }
+
+    /// Returns where the block at `local` in a schematic is placed in the world.
+    #[inline]
+    pub const fn apply(&self, local : BlockPos) -> BlockPos {
+        let pos = local.mirror(self.mirror).rotate(self.rotation);
+        BlockPos::new(pos.x + self.origin.x, pos.y + self.origin.y, pos.z + self.origin.z)
+    }
+
+    /// Returns which position in a schematic is placed at `pos` in the world.
+    ///
+    /// This is the inverse of [`Placement::apply`].
+    #[inline]
+    pub const fn unapply(&self, pos : BlockPos) -> BlockPos {
+        BlockPos::new(pos.x - self.origin.x, pos.y - self.origin.y, pos.z - self.origin.z)
+            .rotate(self.rotation.inverse())
+            .mirror(self.mirror)
+    }
+
+    /// Returns a block from a schematic as it is placed in the world.
+    pub fn apply_block(&self, block : &Block) -> Block {
+        block.mirror(self.mirror).rotate(self.rotation)
+    }
+
+    /// Returns each block in `palette` as it is placed in the world, or `None` if it is skipped.
+    fn apply_palette(&self, palette : &[Block]) -> Vec<Option<Block>> {
+        palette.iter().map(|block| {
+            let air = matches!(block.id(), "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air" | "air" | "cave_air" | "void_air");
+            (! (self.skip_air && air)).then(|| self.apply_block(block))
+        }).collect()
+    }
+
+}
+
+
+/// An error returned when a [`Schematic`] could not be read or made.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchematicError {
+    /// The file looked gzip-compressed, but could not be decompressed.
+    Compression,
+    /// The file was not valid NBT.
+    Nbt(NbtError),
+    /// The file was not a Sponge schematic or a vanilla structure.
+    UnknownFormat,
+    /// A field was missing, or had the wrong type or an invalid value.
+    InvalidField {
+        /// The name of the field.
+        field : &'static str
+    },
+    /// A block state in the palette could not be parsed.
+    InvalidBlock {
+        /// The block state.
+        state : String,
+        /// Why it could not be parsed.
+        error : BlockParseError
+    },
+    /// The schematic has more blocks than can be loaded, is too large to save, or its file
+    ///  decompresses to too many bytes.
+    TooLarge
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self) {
+            Self::Compression                   => write!(f, "invalid gzip data"),
+            Self::Nbt(error)                    => write!(f, "invalid NBT: {error}"),
+            Self::UnknownFormat                 => write!(f, "not a Sponge schematic or vanilla structure"),
+            Self::InvalidField { field }        => write!(f, "missing or invalid field {field:?}"),
+            Self::InvalidBlock { state, error } => write!(f, "invalid block state {state:?}: {error}"),
+            Self::TooLarge                      => write!(f, "schematic has more than {MAX_VOLUME} blocks, is more than {} blocks along an axis, or decompresses to more than {MAX_DECOMPRESSED} bytes", u16::MAX)
+        }
+    }
+}
+
+impl Error for SchematicError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match (self) {
+            Self::Nbt(error)                 => Some(error),
+            Self::InvalidBlock { error, .. } => Some(error),
+            _                                => None
+        }
+    }
+}
+
+impl From<NbtError> for SchematicError {
+    fn from(error : NbtError) -> Self { Self::Nbt(error) }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static V2        : &[u8] = include_bytes!("../../data/fixtures/v2.schem");
+    static V3        : &[u8] = include_bytes!("../../data/fixtures/v3.schem");
+    static STRUCTURE : &[u8] = include_bytes!("../../data/fixtures/structure.nbt");
+
+    const ROTATIONS : [Rotation; 4] = [Rotation::None, Rotation::Clockwise90, Rotation::Clockwise180, Rotation::CounterClockwise90];
+    const MIRRORS   : [Mirror; 3]   = [Mirror::None, Mirror::LeftRight, Mirror::FrontBack];
+
+    /// Checks the blocks of `v2.schem` and `v3.schem`, which are the same.
+    fn check_sponge(schematic : &Schematic) {
+        assert_eq!(schematic.size(), BlockPos::new(3, 2, 2));
+        assert_eq!(schematic.get(BlockPos::new(0, 0, 0)), Some(&"minecraft:oak_stairs[facing=north,half=bottom,shape=outer_left]".parse().unwrap()));
+        for pos in [BlockPos::new(1, 0, 0), BlockPos::new(2, 0, 0), BlockPos::new(0, 0, 1), BlockPos::new(2, 0, 1)] {
+            assert_eq!(schematic.get(pos), Some(&Block::new("minecraft:stone")), "{pos:?}");
+        }
+        assert_eq!(schematic.get(BlockPos::new(0, 1, 0)), Some(&Block::new("minecraft:air")));
+        // Its palette has 200 entries, so this index is a two byte varint.
+        assert_eq!(schematic.get(BlockPos::new(1, 1, 1)), Some(&Block::new("minecraft:crimson_stairs")));
+        assert_eq!(schematic.get(BlockPos::new(2, 1, 1)), None);
+        assert_eq!(schematic.get(BlockPos::new(3, 0, 0)), None);
+        assert_eq!(schematic.iter().count(), 11);
+    }
+
+    #[test]
+    fn sponge_schematics_are_read() {
+        let v3 = Schematic::from_bytes(V3).unwrap();
+        check_sponge(&v3);
+        assert_eq!(v3.offset(), BlockPos::new(1, 2, 3));
+        assert_eq!(v3.data_version(), Some(3700));
+        let v2 = Schematic::from_bytes(V2).unwrap();
+        check_sponge(&v2);
+        assert_eq!(v2.offset(), BlockPos::ZERO);
+        assert_eq!(v2.data_version(), Some(2586));
+    }
+
+    #[test]
+    fn structures_are_read() {
+        let structure = Schematic::from_bytes(STRUCTURE).unwrap();
+        assert_eq!(structure.size(), BlockPos::new(2, 1, 3));
+        assert_eq!(structure.data_version(), Some(3700));
+        assert_eq!(structure.get(BlockPos::new(0, 0, 0)), Some(&Block::new("minecraft:stone")));
+        assert_eq!(structure.get(BlockPos::new(1, 0, 2)), Some(&"minecraft:chest[facing=east,type=left,waterlogged=false]".parse().unwrap()));
+        assert_eq!(structure.iter().count(), 2);
+    }
+
+    #[test]
+    fn malformed_files_are_an_error() {
+        assert_eq!(Schematic::from_bytes(&V3[..(V3.len() / 2)]).unwrap_err(), SchematicError::Compression);
+        assert!(matches!(Schematic::from_bytes(&V2[..(V2.len() - 1)]), Err(SchematicError::Nbt(NbtError::UnexpectedEnd { .. }))));
+        assert_eq!(Schematic::from_nbt(&Nbt::Compound(BTreeMap::new())).unwrap_err(), SchematicError::UnknownFormat);
+    }
+
+    #[test]
+    fn schematics_round_trip() {
+        let blocks = |schematic : &Schematic| schematic.iter().map(|(pos, block,)| (pos, block.clone(),)).collect::<Vec<_>>();
+        // `v3.schem` has a structure void, and the structure only has two blocks.
+        for mut schematic in [Schematic::from_bytes(V3).unwrap(), Schematic::from_bytes(STRUCTURE).unwrap()] {
+            schematic.set_offset(BlockPos::new(-4, 5, -6));
+            let read = Schematic::from_bytes(&schematic.to_bytes()).unwrap();
+            assert_eq!(read.size(), schematic.size());
+            assert_eq!(read.offset(), BlockPos::new(-4, 5, -6));
+            assert_eq!(read.data_version(), Some(3700));
+            assert_eq!(blocks(&read), blocks(&schematic));
+        }
+
+        let sparse = Schematic::from_blocks([
+            (BlockPos::new(-1, 60, 4), Block::new("minecraft:stone"),),
+            (BlockPos::new(1, 62, 4),  Block::new("minecraft:oak_log").with("axis", "x"),)
+        ]).unwrap();
+        let read = Schematic::from_bytes(&sparse.to_bytes()).unwrap();
+        assert_eq!(read.size(), BlockPos::new(3, 3, 1));
+        assert_eq!(read.data_version(), Some(DEFAULT_DATA_VERSION));
+        // Positions which were not given are written as structure voids, and are still left out.
+        assert_eq!(blocks(&read), [
+            (BlockPos::new(0, 0, 0), Block::new("minecraft:stone"),),
+            (BlockPos::new(2, 2, 0), Block::new("minecraft:oak_log").with("axis", "x"),)
+        ]);
+    }
+
+    #[test]
+    fn decompression_is_limited() {
+        let data = gzip(&[0; 100]);
+        assert_eq!(gunzip(&data, 100), Ok(vec![ 0; 100 ]));
+        assert_eq!(gunzip(&data, 99), Err(SchematicError::TooLarge));
+    }
+
+    #[test]
+    fn placements_round_trip() {
+        let origin = BlockPos::new(-20, 64, 7);
+        for rotation in ROTATIONS {
+            for mirror in MIRRORS {
+                let placement = Placement::at(origin).rotate(rotation).mirror(mirror);
+                assert_eq!(placement.apply(BlockPos::ZERO), origin);
+                for local in BlockBox::new(BlockPos::new(-2, 0, -2), BlockPos::new(2, 1, 2)) {
+                    assert_eq!(placement.unapply(placement.apply(local)), local, "{rotation:?} {mirror:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn placed_boxes_can_extend_past_the_origin() {
+        let schematic = Schematic::from_bytes(V3).unwrap();
+        let placed    = |placement : Placement| schematic.placed_box(placement).unwrap();
+        let origin    = BlockPos::new(0, 64, 0);
+        assert_eq!(placed(Placement::at(origin)), BlockBox::new(origin, BlockPos::new(2, 65, 1)));
+        assert_eq!(placed(Placement::at(origin).rotate(Rotation::Clockwise90)), BlockBox::new(BlockPos::new(-1, 64, 0), BlockPos::new(0, 65, 2)));
+        assert_eq!(placed(Placement::at(origin).mirror(Mirror::FrontBack)), BlockBox::new(BlockPos::new(-2, 64, 0), BlockPos::new(0, 65, 1)));
+        for rotation in ROTATIONS {
+            for mirror in MIRRORS {
+                let placement = Placement::at(origin).rotate(rotation).mirror(mirror);
+                let region    = placed(placement);
+                for (local, _,) in schematic.iter() {
+                    assert!(region.contains(placement.apply(local)), "{rotation:?} {mirror:?}");
+                }
+            }
+        }
+    }
+
+}
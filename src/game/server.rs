@@ -1,3 +1,221 @@
+use crate::event::EventArgs;
+use crate::internal::alloc::{ HostBuf, Out };
+use core::cell::RefCell;
+use std::collections::{ BTreeSet, BTreeMap };
+use std::rc::Rc;
+
+
+unsafe extern "C" {
+    unsafe fn flywheel_server_capabilities(out_ptr : usize, out_len : usize);
+}
+
+
+thread_local! {
+    /// The capabilities of the host, once they have been requested.
+    static CAPABILITIES : RefCell<Option<Rc<Capabilities>>> = const { RefCell::new(None) };
+}
+
+
+/// Tells the host which version of the API this plot was compiled against.
+#[unsafe(no_mangle)]
+extern "C" fn flywheel_abi_version() -> u32 { Server::ABI_VERSION }
+
+
 /// This server.
 #[derive(Clone, Copy)]
 pub struct Server;
+
+impl Server {
+
+    /// The version of the host API which this crate was compiled against.
+    ///
+    /// This is exported to the host as `flywheel_abi_version`. It is increased whenever a
+    ///  required import or the function of a capability is added or changed, including the wire
+    ///  formats of existing imports.
+    pub const ABI_VERSION : u32 = 1;
+
+    /// Returns the optional features which the host supports.
+    ///
+    /// The host is only asked once. See [`Capabilities`].
+    pub fn capabilities() -> Rc<Capabilities> {
+        if let Some(capabilities) = CAPABILITIES.with_borrow(|capabilities| capabilities.clone()) {
+            return capabilities;
+        }
+        let mut ptr = Out::<usize>::new();
+        let mut len = Out::<u32>::new();
+        unsafe { flywheel_server_capabilities(ptr.ptr(), len.ptr()); }
+        let buf          = unsafe { HostBuf::from_raw(ptr.get(), len.get()) };
+        let capabilities = Rc::new(Capabilities::decode(buf.as_slice()));
+        CAPABILITIES.set(Some(Rc::clone(&capabilities)));
+        capabilities
+    }
+
+    /// Returns `true` if the host supports the capability `name`.
+    ///
+    /// See [`Server::capabilities`].
+    pub fn has_capability(name : &str) -> bool {
+        Self::capabilities().contains(name)
+    }
+
+    /// Forgets the capabilities of the host, so that they are requested again.
+    #[cfg(feature = "testkit")]
+    pub(crate) fn forget_capabilities() {
+        CAPABILITIES.set(None);
+    }
+
+}
+
+
+/// The optional features which a host supports.
+///
+/// Hosts only have to provide the imports which every Flywheel server supports, and
+///  `flywheel_server_capabilities`. Everything else is optional: each capability the host
+///  supports comes with the functions it provides, which the plot calls through function
+///  pointers instead of importing them. Plots can check for features here to degrade
+///  gracefully on servers which do not support them.
+///
+/// On WASM, the function pointers are indices into the plot's function table, so plots must
+///  be linked with `-C link-arg=--export-table`.
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    names     : BTreeSet<String>,
+    functions : BTreeMap<String, usize>
+}
+
+impl Capabilities {
+
+    /// The host is a selfhosted server, which supports [`Server::set_motd`] and console logging.
+    ///
+    /// Provides `flywheel_system_set_motd`, and `flywheel_trace`, `flywheel_debug`, `flywheel_info`,
+    ///  `flywheel_pass`, `flywheel_warn`, `flywheel_error`, and `flywheel_fatal`.
+    pub const SELFHOSTED : &'static str = "flywheel_selfhosted";
+
+    /// The host accepts palette-encoded block batches, which are smaller and faster to
+    ///  decode than the original format. Batches from [`World::batch_set`](crate::game::prelude::World::batch_set)
+    ///  use them automatically.
+    ///
+    /// Provides `flywheel_world_set_blocks_palette`.
+    pub const PALETTE_BLOCKS : &'static str = "flywheel_set_blocks_palette";
+
+    /// The host carries out [`RegionOp`](crate::game::data::RegionOp)s itself. Without this, region
+    ///  operations are expanded into single blocks by the plot.
+    ///
+    /// Provides `flywheel_world_region_ops`.
+    pub const REGION_OPS : &'static str = "flywheel_world_region_ops";
+
+    /// The host can suspend the plot while it has nothing to do. Without this, the plot
+    ///  keeps polling for events.
+    ///
+    /// Provides `flywheel_idle`.
+    pub const IDLE : &'static str = "flywheel_idle";
+
+    /// The host lets the plot decide what happens to chat messages, with
+    ///  [`ChatMessage`](crate::game::ChatMessage). Without this, the host sends
+    ///  chat messages as they are.
+    ///
+    /// Provides `flywheel_player_chat_resolve`.
+    pub const CHAT_RESOLVE : &'static str = "flywheel_chat_resolve";
+
+    /// The host sends the plot's [`Command`](crate::game::command::Command)s to players' clients,
+    ///  so that they are suggested and highlighted.
+    ///
+    /// Provides `flywheel_player_set_commands`.
+    pub const COMMANDS : &'static str = "flywheel_commands";
+
+    /// The host lets the plot read blocks with [`World::get`](crate::game::prelude::World::get)
+    ///  and [`World::snapshot`](crate::game::prelude::World::snapshot).
+    ///
+    /// Provides `flywheel_world_get_block` and `flywheel_world_snapshot`.
+    pub const WORLD_READ : &'static str = "flywheel_world_read";
+
+    /// The host can find players by name, with [`Player::from_name`](crate::game::Player::from_name).
+    ///
+    /// Provides `flywheel_player_from_name`.
+    pub const PLAYER_LOOKUP : &'static str = "flywheel_player_lookup";
+
+    /// Decodes a list of capabilities.
+    ///
+    /// Each capability is its name, then a `u32` count of the functions it provides, then the
+    ///  name and the `u64` function pointer of each function. Names are prefixed by their length
+    ///  in bytes as a `u32`. Anything after a malformed capability is ignored.
+    fn decode(data : &[u8]) -> Self {
+        let mut args         = EventArgs::new(data);
+        let mut capabilities = Self::default();
+        while (! args.is_empty()) {
+            let Ok(name)  = args.str() else { break; };
+            let Ok(count) = args.u32() else { break; };
+            capabilities.names.insert(name.to_string());
+            for _ in 0..count {
+                let (Ok(function), Ok(ptr),) = (args.str(), args.u64(),) else { return capabilities; };
+                if let Ok(ptr) = usize::try_from(ptr) && (ptr != 0) {
+                    capabilities.functions.insert(function.to_string(), ptr);
+                }
+            }
+        }
+        capabilities
+    }
+
+    /// Returns `true` if the host supports the capability `name`.
+    pub fn contains(&self, name : &str) -> bool { self.names.contains(name) }
+
+    /// Returns the names of every capability the host supports.
+    pub fn iter(&self) -> impl Iterator<Item = &str> { self.names.iter().map(String::as_str) }
+
+    /// Returns `true` if the host provides the optional function `name`, such as `flywheel_idle`.
+    pub fn provides(&self, name : &str) -> bool { self.functions.contains_key(name) }
+
+    /// Returns the pointer to the optional function `name`, if the host provides it.
+    pub(crate) fn function(&self, name : &str) -> Option<usize> { self.functions.get(name).copied() }
+
+}
+
+
+/// Declares functions which hosts may provide with a capability, instead of as imports.
+///
+/// Each declared function takes no arguments, and returns the host function if the host
+///  provides it. See [`Capabilities`].
+pub(crate) macro optional_imports( $(
+    $( #[$meta:meta] )*
+    fn $ident:ident ( $( $arg:ident : $argty:ty ),* $(,)? ) $( -> $ret:ty )? ;
+)* ) { $(
+    $( #[$meta] )*
+    fn $ident() -> Option<unsafe extern "C" fn( $( $arg : $argty ),* ) $( -> $ret )?> {
+        let ptr = Server::capabilities().function(stringify!($ident))?;
+        // SAFETY: The host provided this function under this name, so it has this signature.
+        //  On wasm32 the value is an index into the plot's function table, which is only valid
+        //  because plots must be linked with `-C link-arg=--export-table`. Under the testkit it
+        //  is a native function pointer.
+        Some(unsafe { core::mem::transmute::<usize, unsafe extern "C" fn( $( $arg : $argty ),* ) $( -> $ret )?>(ptr) })
+    }
+)* }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(out : &mut Vec<u8>, name : &str, functions : &[(&str, u64,)]) {
+        out.extend((name.len() as u32).to_le_bytes());
+        out.extend(name.as_bytes());
+        out.extend((functions.len() as u32).to_le_bytes());
+        for (function, ptr,) in functions {
+            out.extend((function.len() as u32).to_le_bytes());
+            out.extend(function.as_bytes());
+            out.extend(ptr.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn decode_capabilities() {
+        let mut data = Vec::new();
+        entry(&mut data, Capabilities::SELFHOSTED, &[]);
+        entry(&mut data, Capabilities::WORLD_READ, &[("flywheel_world_get_block", 7,), ("flywheel_world_snapshot", 0,)]);
+        data.extend(100u32.to_le_bytes());
+        let capabilities = Capabilities::decode(&data);
+        assert_eq!(capabilities.iter().collect::<Vec<_>>(), [Capabilities::SELFHOSTED, Capabilities::WORLD_READ]);
+        assert_eq!(capabilities.function("flywheel_world_get_block"), Some(7));
+        assert!(! capabilities.provides("flywheel_world_snapshot"));
+        assert!(! capabilities.contains(Capabilities::IDLE));
+    }
+
+}
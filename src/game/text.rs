@@ -0,0 +1,369 @@
+//! Formatted text, such as chat messages and titles.
+//!
+//! The host formats text with an XML-like markup, in which `<green>...</>` colours its
+//!  contents green. Strings converted into a `Text` with `into()` are escaped, like
+//!  [`Text::literal`], so player input can be interpolated into a message without changing
+//!  its formatting:
+//! ```ignore
+//! player.send_chat(format!("Hello, {}!", profile.name));
+//! player.send_chat(Text::literal("Hello, ").color(Color::Green).append(Text::literal(&profile.name).bold()));
+//! ```
+//!
+//! Markup has to be passed to [`Text::markup`]:
+//! ```ignore
+//! player.send_chat(Text::markup("<green>Welcome!</>"));
+//! ```
+
+
+use core::fmt::{ self, Write };
+
+
+/// A piece of formatted text, with any number of children.
+///
+/// Styles apply to the text and all of its children, unless a child overrides them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Text {
+    content  : Content,
+    style    : Style,
+    children : Vec<Text>
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+enum Content {
+    #[default]
+    Empty,
+    Literal(String),
+    Markup(String),
+    Translate {
+        key  : String,
+        args : Vec<Text>
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Style {
+    color       : Option<Color>,
+    decorations : [Option<bool>; Decoration::ALL.len()],
+    hover       : Option<Box<Text>>,
+    click       : Option<ClickEvent>
+}
+
+impl Text {
+
+    /// Creates a new `Text` with no content.
+    pub fn empty() -> Self { Self::default() }
+
+    /// Creates a new `Text` containing `text`, which is escaped so that it is shown as it is.
+    ///
+    /// Strings converted into a `Text` with `into()` are literal too.
+    pub fn literal(text : impl Into<String>) -> Self {
+        Self { content : Content::Literal(text.into()), ..Self::default() }
+    }
+
+    /// Creates a new `Text` from markup which is already formatted.
+    ///
+    /// The markup is sent to the host as it is, so it must not contain any untrusted input.
+    ///  Use [`escape`] or [`Text::literal`] for that instead.
+    pub fn markup(markup : impl Into<String>) -> Self {
+        Self { content : Content::Markup(markup.into()), ..Self::default() }
+    }
+
+    /// Creates a new `Text` which is translated by the client, such as `block.minecraft.stone`.
+    ///
+    /// Arguments for the translation can be added with [`Text::arg`].
+    pub fn translate(key : impl Into<String>) -> Self {
+        Self { content : Content::Translate { key : key.into(), args : Vec::new() }, ..Self::default() }
+    }
+
+    /// Adds an argument to a translated `Text`.
+    ///
+    /// ### Panics
+    /// Panics if this `Text` was not created with [`Text::translate`].
+    #[track_caller]
+    pub fn arg(mut self, arg : impl Into<Text>) -> Self {
+        let Content::Translate { args, .. } = &mut self.content else {
+            panic!("`Text::arg` called on a `Text` which is not translated");
+        };
+        args.push(arg.into());
+        self
+    }
+
+    /// Appends a child to this `Text`.
+    pub fn append(mut self, child : impl Into<Text>) -> Self {
+        self.children.push(child.into());
+        self
+    }
+
+    /// Sets the colour of this `Text`.
+    pub fn color(mut self, color : Color) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    /// Enables or disables a decoration on this `Text`.
+    ///
+    /// Disabling a decoration overrides the decoration of a parent.
+    pub fn decoration(mut self, decoration : Decoration, enabled : bool) -> Self {
+        self.style.decorations[decoration as usize] = Some(enabled);
+        self
+    }
+
+    /// Makes this `Text` bold.
+    pub fn bold(self) -> Self { self.decoration(Decoration::Bold, true) }
+
+    /// Makes this `Text` italic.
+    pub fn italic(self) -> Self { self.decoration(Decoration::Italic, true) }
+
+    /// Underlines this `Text`.
+    pub fn underlined(self) -> Self { self.decoration(Decoration::Underlined, true) }
+
+    /// Strikes through this `Text`.
+    pub fn strikethrough(self) -> Self { self.decoration(Decoration::Strikethrough, true) }
+
+    /// Makes this `Text` constantly change to random characters.
+    pub fn obfuscated(self) -> Self { self.decoration(Decoration::Obfuscated, true) }
+
+    /// Shows `hover` when the player hovers over this `Text`.
+    pub fn hover(mut self, hover : impl Into<Text>) -> Self {
+        self.style.hover = Some(Box::new(hover.into()));
+        self
+    }
+
+    /// Does something when the player clicks on this `Text`.
+    pub fn click(mut self, click : ClickEvent) -> Self {
+        self.style.click = Some(click);
+        self
+    }
+
+    /// Formats this `Text` as markup for the host.
+    pub fn to_markup(&self) -> String {
+        let mut out = String::new();
+        self.write_markup(&mut out).expect("writing to a `String` failed");
+        out
+    }
+
+    fn write_markup(&self, out : &mut String) -> fmt::Result {
+        let mut opened = 0usize;
+        if let Some(color) = self.style.color {
+            write!(out, "<{color}>")?;
+            opened += 1;
+        }
+        for decoration in Decoration::ALL {
+            if let Some(enabled) = self.style.decorations[decoration as usize] {
+                write!(out, "<{}{}>", if (enabled) { "" } else { "!" }, decoration.tag())?;
+                opened += 1;
+            }
+        }
+        if let Some(hover) = &self.style.hover {
+            write!(out, "<hover text=\"{}\">", escape(&hover.to_markup()))?;
+            opened += 1;
+        }
+        if let Some(click) = &self.style.click {
+            write!(out, "<click action=\"{}\" value=\"{}\">", click.action(), escape(click.value()))?;
+            opened += 1;
+        }
+        match (&self.content) {
+            Content::Empty                   => { },
+            Content::Literal(text)           => out.push_str(&escape(text)),
+            Content::Markup(markup)          => out.push_str(markup),
+            Content::Translate { key, args } => {
+                write!(out, "<translate key=\"{}\">", escape(key))?;
+                for arg in args {
+                    out.push_str("<arg>");
+                    arg.write_markup(out)?;
+                    out.push_str("</>");
+                }
+                out.push_str("</>");
+            }
+        }
+        for child in &self.children {
+            child.write_markup(out)?;
+        }
+        for _ in 0..opened {
+            out.push_str("</>");
+        }
+        Ok(())
+    }
+
+}
+
+impl From<&str> for Text {
+    /// Creates a new `Text` containing `text`, which is escaped. See [`Text::literal`].
+    fn from(text : &str) -> Self { Self::literal(text) }
+}
+
+impl From<String> for Text {
+    /// Creates a new `Text` containing `text`, which is escaped. See [`Text::literal`].
+    fn from(text : String) -> Self { Self::literal(text) }
+}
+
+impl From<&String> for Text {
+    /// Creates a new `Text` containing `text`, which is escaped. See [`Text::literal`].
+    fn from(text : &String) -> Self { Self::literal(text.as_str()) }
+}
+
+
+/// Escapes `text`, so that it is shown as it is when used in markup.
+pub fn escape(text : &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match (ch) {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _   => out.push(ch)
+        }
+    }
+    out
+}
+
+
+/// A text colour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// `#000000`
+    Black,
+    /// `#0000AA`
+    DarkBlue,
+    /// `#00AA00`
+    DarkGreen,
+    /// `#00AAAA`
+    DarkAqua,
+    /// `#AA0000`
+    DarkRed,
+    /// `#AA00AA`
+    DarkPurple,
+    /// `#FFAA00`
+    Gold,
+    /// `#AAAAAA`
+    Gray,
+    /// `#555555`
+    DarkGray,
+    /// `#5555FF`
+    Blue,
+    /// `#55FF55`
+    Green,
+    /// `#55FFFF`
+    Aqua,
+    /// `#FF5555`
+    Red,
+    /// `#FF55FF`
+    LightPurple,
+    /// `#FFFF55`
+    Yellow,
+    /// `#FFFFFF`
+    White,
+    /// Any other colour, from its red, green, and blue components.
+    Rgb(u8, u8, u8)
+}
+
+impl fmt::Display for Color {
+    /// Formats this colour as its markup tag name.
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match (self) {
+            Self::Black        => "black",
+            Self::DarkBlue     => "dark_blue",
+            Self::DarkGreen    => "dark_green",
+            Self::DarkAqua     => "dark_aqua",
+            Self::DarkRed      => "dark_red",
+            Self::DarkPurple   => "dark_purple",
+            Self::Gold         => "gold",
+            Self::Gray         => "gray",
+            Self::DarkGray     => "dark_gray",
+            Self::Blue         => "blue",
+            Self::Green        => "green",
+            Self::Aqua         => "aqua",
+            Self::Red          => "red",
+            Self::LightPurple  => "light_purple",
+            Self::Yellow       => "yellow",
+            Self::White        => "white",
+            Self::Rgb(r, g, b) => { return write!(f, "#{r:02X}{g:02X}{b:02X}"); }
+        })
+    }
+}
+
+
+/// A text decoration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Decoration {
+    /// Bold text.
+    Bold,
+    /// Italic text.
+    Italic,
+    /// Underlined text.
+    Underlined,
+    /// Struck through text.
+    Strikethrough,
+    /// Text which constantly changes to random characters.
+    Obfuscated
+}
+
+impl Decoration {
+    const ALL : [Self; 5] = [Self::Bold, Self::Italic, Self::Underlined, Self::Strikethrough, Self::Obfuscated];
+
+    fn tag(self) -> &'static str {
+        match (self) {
+            Self::Bold          => "b",
+            Self::Italic        => "i",
+            Self::Underlined    => "u",
+            Self::Strikethrough => "st",
+            Self::Obfuscated    => "obf"
+        }
+    }
+}
+
+
+/// Something which happens when a player clicks on [`Text`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ClickEvent {
+    /// Runs a command as the player, such as `/spawn`.
+    RunCommand(String),
+    /// Puts a command in the player's chat box, without running it.
+    SuggestCommand(String),
+    /// Asks the player to open a URL.
+    OpenUrl(String),
+    /// Copies text to the player's clipboard.
+    CopyToClipboard(String)
+}
+
+impl ClickEvent {
+    fn action(&self) -> &'static str {
+        match (self) {
+            Self::RunCommand(_)      => "run_command",
+            Self::SuggestCommand(_)  => "suggest_command",
+            Self::OpenUrl(_)         => "open_url",
+            Self::CopyToClipboard(_) => "copy_to_clipboard"
+        }
+    }
+
+    fn value(&self) -> &str {
+        match (self) {
+            Self::RunCommand(value)
+            | Self::SuggestCommand(value)
+            | Self::OpenUrl(value)
+            | Self::CopyToClipboard(value) => value
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(Text::from("<green>hi</>").to_markup(), "&lt;green&gt;hi&lt;/&gt;");
+        assert_eq!(Text::from(String::from("<b>x</>")).to_markup(), "&lt;b&gt;x&lt;/&gt;");
+        assert_eq!(Text::from(&String::from("a & b")).to_markup(), "a &amp; b");
+        assert_eq!(Text::markup("<green>hi</>").to_markup(), "<green>hi</>");
+    }
+
+    #[test]
+    fn literals_are_escaped() {
+        let text = Text::literal("Hello, ").color(Color::Green).append(Text::literal("<b>&\"").bold());
+        assert_eq!(text.to_markup(), "<green>Hello, <b>&lt;b&gt;&amp;&quot;</></>");
+    }
+
+}
@@ -1,12 +1,164 @@
-use std::alloc::{ Layout, alloc, handle_alloc_error };
+//! Ownership of buffers passed between the plot and the host.
+//!
+//! Buffers handed to the plot by the host are allocated with [`flywheel_alloc`], and are
+//!  owned by the plot from then on. Buffers handed to the host by the plot are owned by the
+//!  host from then on, and are freed with [`flywheel_dealloc`].
+//!
+//! [`HostBuf`] and [`Out`] can be used to call host functions which this crate does not wrap yet.
 
 
+use core::ptr::NonNull;
+use core::slice;
+use std::alloc::{ Layout, alloc, dealloc, handle_alloc_error };
+use std::string::FromUtf8Error;
+
+
+/// Allocates a buffer for the host to write into.
+///
+/// Returns `0` if `align` is not a power of two, or if the layout is too large.
 #[unsafe(no_mangle)]
-pub fn flywheel_alloc(len : u32, align : u32) -> u32 {
-    let layout = unsafe { Layout::from_size_align_unchecked(len as usize, align as usize) };
-    let ptr    = unsafe { alloc(layout) };
+pub fn flywheel_alloc(len : u32, align : u32) -> usize {
+    let Ok(layout) = Layout::from_size_align(len as usize, align as usize) else { return 0; };
+    if (layout.size() == 0) {
+        return layout.align();
+    }
+    let ptr = unsafe { alloc(layout) };
     if (ptr.is_null()) {
         handle_alloc_error(layout);
     }
-    ptr as u32
+    ptr as usize
+}
+
+/// Frees a buffer which was allocated with [`flywheel_alloc`], or handed to the host by the plot.
+///
+/// ### Safety
+/// `ptr` must be owned by the caller, and `len` and `align` must be the same as when the buffer was allocated.
+#[unsafe(no_mangle)]
+pub unsafe fn flywheel_dealloc(ptr : usize, len : u32, align : u32) {
+    let Ok(layout) = Layout::from_size_align(len as usize, align as usize) else { return; };
+    if (ptr == 0 || layout.size() == 0) { return; }
+    unsafe { dealloc(ptr as (*mut u8), layout); }
+}
+
+
+/// A byte buffer allocated with [`flywheel_alloc`], which is freed with the same layout when dropped.
+pub struct HostBuf {
+    ptr : NonNull<u8>,
+    len : usize
+}
+
+impl HostBuf {
+
+    /// Takes ownership of a buffer which the host allocated with an alignment of `1`.
+    ///
+    /// ### Safety
+    /// If `len` is not `0`, `ptr` must point to `len` initialised bytes allocated with
+    ///  `flywheel_alloc(len, 1)`, which nothing else owns.
+    pub unsafe fn from_raw(ptr : usize, len : u32) -> Self {
+        match (NonNull::new(ptr as (*mut u8))) {
+            Some(ptr) if (len != 0) => Self { ptr, len : len as usize },
+            _                       => Self { ptr : NonNull::dangling(), len : 0 }
+        }
+    }
+
+    /// Hands this buffer over to the host, which must free it with `flywheel_dealloc(ptr, len, 1)`.
+    pub fn into_raw(self) -> (usize, u32,) {
+        let this = core::mem::ManuallyDrop::new(self);
+        (this.ptr.as_ptr() as usize, this.len as u32,)
+    }
+
+    /// Returns the contents of this buffer.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Converts this buffer into a `Vec`, without copying.
+    pub fn into_vec(self) -> Vec<u8> {
+        let (ptr, len,) = self.into_raw();
+        if (len == 0) { return Vec::new(); }
+        // `Vec<u8>` with a capacity of `len` uses the same layout as `flywheel_alloc(len, 1)`.
+        unsafe { Vec::from_raw_parts(ptr as (*mut u8), len as usize, len as usize) }
+    }
+
+    /// Converts this buffer into a `String`, without copying.
+    pub fn into_string(self) -> Result<String, FromUtf8Error> {
+        String::from_utf8(self.into_vec())
+    }
+
+}
+
+impl Drop for HostBuf {
+    fn drop(&mut self) {
+        unsafe { flywheel_dealloc(self.ptr.as_ptr() as usize, self.len as u32, 1); }
+    }
+}
+
+
+/// A value which the host writes through an out-pointer.
+///
+/// The value starts zeroed, so reading it is sound even if the host never writes to it.
+pub struct Out<T : OutValue> {
+    value : T
+}
+
+impl<T : OutValue> Out<T> {
+
+    /// Creates a new zeroed value.
+    pub fn new() -> Self { Self { value : T::ZERO } }
+
+    /// Returns the address for the host to write to.
+    pub fn ptr(&mut self) -> usize { (&raw mut self.value) as usize }
+
+    /// Returns the value written by the host, converted from little-endian.
+    pub fn get(&self) -> T { self.value.le_to_native() }
+
+}
+
+impl<T : OutValue> Default for Out<T> {
+    fn default() -> Self { Self::new() }
+}
+
+/// A type which the host can write through an [`Out`].
+///
+/// ### Safety
+/// Every bit pattern must be a valid value of the type.
+pub unsafe trait OutValue : Copy {
+    /// The value with every bit cleared.
+    const ZERO : Self;
+    /// Converts a value written by the host from little-endian.
+    fn le_to_native(self) -> Self;
+}
+
+macro out_value( $( $ty:ty ),* $(,)? ) { $(
+    unsafe impl OutValue for $ty {
+        const ZERO : Self = 0;
+        #[inline]
+        fn le_to_native(self) -> Self { <$ty>::from_le(self) }
+    }
+)* }
+
+out_value!{ u8, u16, u32, u64, u128, usize }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_buf_takes_ownership() {
+        let ptr = flywheel_alloc(5, 1);
+        unsafe { core::ptr::copy_nonoverlapping(b"hello".as_ptr(), ptr as (*mut u8), 5); }
+        let buf = unsafe { HostBuf::from_raw(ptr, 5) };
+        assert_eq!(buf.as_slice(), b"hello");
+        assert_eq!(buf.into_string().as_deref(), Ok("hello"));
+        assert_eq!(unsafe { HostBuf::from_raw(0, 0) }.into_vec(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn out_reads_little_endian() {
+        let mut out = Out::<u32>::new();
+        unsafe { core::ptr::write_unaligned(out.ptr() as (*mut [u8; 4]), [1, 2, 0, 0]); }
+        assert_eq!(out.get(), 0x0201);
+    }
+
 }
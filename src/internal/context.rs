@@ -0,0 +1,588 @@
+use super::executor::{ self, TaskHandle };
+use super::state::{ Handler, HandlerError, State };
+use crate::event::{ self, EventArgs, EventArgsError, FlywheelEvent };
+use crate::game::{ Player, ChatMessage };
+use crate::game::command::{ self, Command };
+use crate::game::data::ChunkPos;
+use crate::time::{ Duration, Instant };
+use core::any::{ Any, TypeId };
+use core::cell::{ Cell, RefCell };
+use core::pin::Pin;
+use std::collections::{ BTreeMap, HashMap };
+use std::rc::Rc;
+
+
+thread_local! {
+    /// The context of the `App` which is currently running.
+    static CURRENT : RefCell<Option<AppContext>> = const { RefCell::new(None) };
+}
+
+
+type Callback<Args> = dyn Fn(&AppContext, Args) -> Pin<Box<dyn Future<Output = ()>>>;
+
+/// Decodes an event payload and spawns a callback with it.
+type EventDispatcher = dyn Fn(&AppContext, &[u8]) -> Result<TaskHandle<()>, EventArgsError>;
+
+type ErrorHook = dyn Fn(&HandlerError);
+
+struct Entry<F : ?Sized> {
+    id      : u64,
+    enabled : bool,
+    once    : bool,
+    f       : Rc<F>
+}
+
+#[derive(Default)]
+struct Callbacks {
+    next_id     : u64,
+    on_start    : Vec<Entry<Callback<()>>>,
+    on_stopping : Vec<Entry<Callback<(Instant,)>>>,
+    on_event    : HashMap<String, Vec<Entry<EventDispatcher>>>,
+    on_error    : Option<Rc<ErrorHook>>,
+    commands    : BTreeMap<String, Rc<Command>>
+}
+
+/// Returns the ID, `once` flag, and callback of every enabled entry.
+fn snapshot<F : ?Sized>(entries : &[Entry<F>]) -> Vec<(Listener, bool, Rc<F>,)> {
+    entries.iter()
+        .filter(|entry| entry.enabled)
+        .map(|entry| (Listener { id : entry.id }, entry.once, Rc::clone(&entry.f),))
+        .collect()
+}
+
+fn find<F : ?Sized>(entries : &mut [Entry<F>], listener : Listener) -> Option<&mut Entry<F>> {
+    entries.iter_mut().find(|entry| entry.id == listener.id)
+}
+
+fn take<F : ?Sized>(entries : &mut Vec<Entry<F>>, listener : Listener) -> Option<Entry<F>> {
+    let index = entries.iter().position(|entry| entry.id == listener.id)?;
+    Some(entries.remove(index))
+}
+
+
+/// A handle to a callback registered with an [`AppContext`].
+///
+/// A `Listener` can be used to remove or disable the callback with
+///  [`AppContext::remove`] and [`AppContext::set_enabled`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Listener {
+    id : u64
+}
+
+
+/// A shared handle to the callbacks of an [`App`](crate::App).
+///
+/// Unlike the `App` itself, an `AppContext` can be used while the `App` is running.
+///  Tasks can get the context of the running `App` with [`AppContext::current`],
+///  and add or remove callbacks through it.
+#[derive(Clone, Default)]
+pub struct AppContext {
+    callbacks    : Rc<RefCell<Callbacks>>,
+    states       : Rc<RefCell<HashMap<TypeId, Rc<dyn Any>>>>,
+    stop_request : Rc<Cell<Option<Duration>>>
+}
+
+impl AppContext {
+
+    /// Returns the context of the `App` which is currently running,
+    ///  or `None` if called outside of [`App::run`](crate::App::run).
+    pub fn current() -> Option<Self> {
+        CURRENT.with_borrow(|current| current.clone())
+    }
+
+    /// Makes this the current context until the returned guard is dropped.
+    pub(super) fn enter(&self) -> ContextGuard {
+        ContextGuard { prev : CURRENT.replace(Some(self.clone())) }
+    }
+
+    /// Requests that the `App` stops.
+    ///
+    /// The `on_stopping` callbacks are fired with a deadline `grace` from now,
+    ///  and [`App::run`](crate::App::run) returns once they finish or the deadline passes.
+    ///
+    /// This can be called from inside tasks, through [`AppContext::current`] or a callback's
+    ///  `AppContext` argument.
+    pub fn request_stop(&self, grace : Duration) {
+        self.stop_request.set(Some(grace));
+    }
+
+    /// Takes the grace period passed to [`AppContext::request_stop`], if it was called.
+    pub(super) fn take_stop_request(&self) -> Option<Duration> {
+        self.stop_request.take()
+    }
+
+    /// Adds shared state of type `T`, which callbacks can take as a [`State<T>`] argument.
+    ///
+    /// This replaces any state of type `T` which was already added. Existing [`State`]
+    ///  handles keep referring to the old state.
+    pub fn set_state<T : 'static>(&self, value : T) {
+        let old = self.states.borrow_mut().insert(TypeId::of::<T>(), Rc::new(RefCell::new(value)));
+        drop(old);
+    }
+
+    /// Returns the shared state of type `T`, or `None` if none was added.
+    pub fn state<T : 'static>(&self) -> Option<State<T>> {
+        let state = Rc::clone(self.states.borrow().get(&TypeId::of::<T>())?);
+        Some(State::new(state.downcast::<RefCell<T>>().ok()?))
+    }
+
+    /// Removes a callback.
+    ///
+    /// Returns `false` if the callback was already removed.
+    pub fn remove(&self, listener : Listener) -> bool {
+        let mut callbacks = self.callbacks.borrow_mut();
+        let start    = take(&mut callbacks.on_start, listener);
+        let stopping = take(&mut callbacks.on_stopping, listener);
+        let event    = callbacks.on_event.values_mut().find_map(|entries| take(entries, listener));
+        // The callbacks are dropped after the borrow ends, as they could own other `Listener`s.
+        drop(callbacks);
+        start.is_some() || stopping.is_some() || event.is_some()
+    }
+
+    /// Enables or disables a callback. Disabled callbacks are skipped when their event fires.
+    ///
+    /// Returns `false` if the callback was removed.
+    pub fn set_enabled(&self, listener : Listener, enabled : bool) -> bool {
+        let mut callbacks = self.callbacks.borrow_mut();
+        let     callbacks = &mut *callbacks;
+        let entry = find(&mut callbacks.on_start, listener).map(|entry| &mut entry.enabled)
+            .or_else(|| find(&mut callbacks.on_stopping, listener).map(|entry| &mut entry.enabled))
+            .or_else(|| callbacks.on_event.values_mut().find_map(|entries| find(entries, listener)).map(|entry| &mut entry.enabled));
+        match (entry) {
+            Some(entry) => { *entry = enabled; true },
+            None        => false
+        }
+    }
+
+    /// Sets the hook which is called when a callback returns an error.
+    ///
+    /// This replaces any hook which was already set. Without a hook, errors are logged
+    ///  on selfhosted servers, and ignored otherwise.
+    pub fn on_error<F>(&self, f : F)
+    where
+        F : Fn(&HandlerError) + 'static
+    {
+        let old = self.callbacks.borrow_mut().on_error.replace(Rc::new(f));
+        drop(old);
+    }
+
+    /// Registers a command, replacing any command with the same name.
+    ///
+    /// The commands are sent to each player when they join. Players who are already on the server
+    ///  only see the new command once [`AppContext::send_commands`] is called for them.
+    ///
+    /// ### Panics
+    /// Panics if `command` is not a literal node. See [`Command::new`].
+    #[track_caller]
+    pub fn command(&self, command : Command) {
+        assert!(command.is_literal(), "the root of a command must be a literal");
+        let old = self.callbacks.borrow_mut().commands.insert(command.name().to_string(), Rc::new(command));
+        drop(old);
+    }
+
+    /// Removes the command named `name`.
+    ///
+    /// Returns `false` if there was no such command.
+    pub fn remove_command(&self, name : &str) -> bool {
+        let old = self.callbacks.borrow_mut().commands.remove(name);
+        old.is_some()
+    }
+
+    /// Sends the commands which `player` can use to their client, so that they are completed as they are typed.
+    ///
+    /// This is called automatically when a player joins.
+    pub fn send_commands(&self, player : Player) {
+        let commands = self.callbacks.borrow().commands.clone();
+        command::send(&commands, player);
+    }
+
+}
+
+impl AppContext {
+
+    fn register<F : ?Sized>(&self, list : fn(&mut Callbacks) -> &mut Vec<Entry<F>>, once : bool, f : Rc<F>) -> Listener {
+        let mut callbacks = self.callbacks.borrow_mut();
+        let     id        = callbacks.next_id;
+        callbacks.next_id += 1;
+        list(&mut callbacks).push(Entry { id, enabled : true, once, f });
+        Listener { id }
+    }
+
+    fn register_event(&self, event_id : &str, once : bool, f : Rc<EventDispatcher>) -> Listener {
+        let mut callbacks = self.callbacks.borrow_mut();
+        let     id        = callbacks.next_id;
+        callbacks.next_id += 1;
+        callbacks.on_event.entry(event_id.to_string()).or_default().push(Entry { id, enabled : true, once, f });
+        Listener { id }
+    }
+
+    fn listen<E, F>(&self, once : bool, f : F) -> Listener
+    where
+        E : FlywheelEvent,
+        F : (Fn(&AppContext, E) -> Pin<Box<dyn Future<Output = Result<(), String>>>>) + 'static
+    {
+        let event_id = Rc::<str>::from(E::ID);
+        self.register_event(E::ID, once, Rc::new(move |ctx, args| {
+            let event = E::decode(&mut EventArgs::new(args))?;
+            let scope = event.scope();
+            Ok(executor::spawn_scoped(ctx.report(&event_id, f(ctx, event)), scope))
+        }))
+    }
+
+    fn listen_raw<H, M>(&self, id : &str, once : bool, handler : H) -> Listener
+    where
+        H : Handler<(Vec<u8>,), M>
+    {
+        let event_id = Rc::<str>::from(id);
+        self.register_event(id, once, Rc::new(move |ctx, args| {
+            Ok(executor::spawn(ctx.report(&event_id, handler.call(ctx, (args.to_vec(),)))))
+        }))
+    }
+
+    /// Wraps the future of a callback, passing the error it fails with to the error hook.
+    pub(crate) fn report(&self, event_id : &Rc<str>, fut : Pin<Box<dyn Future<Output = Result<(), String>>>>) -> Pin<Box<dyn Future<Output = ()>>> {
+        let ctx      = self.clone();
+        let event_id = Rc::clone(event_id);
+        Box::pin(async move {
+            if let Err(message) = fut.await {
+                ctx.handle_error(HandlerError::new(event_id, message));
+            }
+        })
+    }
+
+    /// Passes an error to the error hook, or logs it if there is none.
+    pub(crate) fn handle_error(&self, err : HandlerError) {
+        let on_error = self.callbacks.borrow().on_error.clone();
+        match (on_error) {
+            Some(on_error) => on_error(&err),
+            None           => {
+                #[cfg(feature = "selfhosted")]
+                crate::selfhosted::error!("{}", err);
+                #[cfg(not(feature = "selfhosted"))]
+                let _ = err;
+            }
+        }
+    }
+
+    fn fire<T : Clone>(&self, list : fn(&mut Callbacks) -> &mut Vec<Entry<Callback<T>>>, args : T) -> Vec<TaskHandle<()>> {
+        let entries = snapshot(list(&mut self.callbacks.borrow_mut()));
+        let mut handles = Vec::with_capacity(entries.len());
+        for (listener, once, f,) in entries {
+            handles.push(executor::spawn(f(self, args.clone())));
+            if (once) { self.remove(listener); }
+        }
+        handles
+    }
+
+    /// Fires the `on_start` callbacks.
+    pub(super) fn fire_start(&self) -> Vec<TaskHandle<()>> {
+        self.fire(|callbacks| &mut callbacks.on_start, ())
+    }
+
+    /// Fires the `on_stopping` callbacks.
+    pub(super) fn fire_stopping(&self, deadline : Instant) -> Vec<TaskHandle<()>> {
+        self.fire(|callbacks| &mut callbacks.on_stopping, (deadline,))
+    }
+
+    /// Returns `true` if any commands are registered.
+    pub(super) fn has_commands(&self) -> bool {
+        ! self.callbacks.borrow().commands.is_empty()
+    }
+
+    /// Parses and runs a command sent by `player`.
+    pub(super) fn run_command(&self, player : Player, input : &str) {
+        let commands = self.callbacks.borrow().commands.clone();
+        command::execute(self, &commands, player, input);
+    }
+
+    /// Decodes an event payload and fires the callbacks for it.
+    ///
+    /// If a callback can not decode the payload, the error is passed to the error hook as its
+    ///  error, and the other callbacks are still fired. `once` callbacks are removed either way.
+    pub(super) fn dispatch(&self, id : &str, args : &[u8]) -> Vec<TaskHandle<()>> {
+        let entries = self.callbacks.borrow().on_event.get(id).map(|entries| snapshot(entries)).unwrap_or_default();
+        let mut handles = Vec::with_capacity(entries.len());
+        for (listener, once, dispatch,) in entries {
+            match (dispatch(self, args)) {
+                Ok(handle) => handles.push(handle),
+                Err(err)   => self.handle_error(HandlerError::new(Rc::from(id), format!("malformed event: {err}")))
+            }
+            if (once) { self.remove(listener); }
+        }
+        handles
+    }
+
+}
+
+/// Restores the previously current context when dropped.
+pub(super) struct ContextGuard {
+    prev : Option<AppContext>
+}
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        CURRENT.set(self.prev.take());
+    }
+}
+
+
+macro callback_fn( $ident:ident ( $( $argident:ident : $argty:ty ),* ) => $list:ident ( $event_id:literal ), $once:literal, $doc:expr ) {
+    #[doc = $doc]
+    pub fn $ident<H, M>(&self, handler : H) -> Listener
+    where
+        H : Handler<( $( $argty , )* ), M>
+    {
+        let event_id = Rc::<str>::from($event_id);
+        let f : Rc<Callback<( $( $argty , )* )>> = Rc::new(move |ctx, args| ctx.report(&event_id, handler.call(ctx, args)));
+        self.register(|callbacks| &mut callbacks.$list, $once, f)
+    }
+}
+
+macro event_callback_fn( $ident:ident ( $( $argident:ident : $argty:ty ),* ) => $event:ident, $generic:ident, $once:literal, $doc:expr ) {
+    #[doc = $doc]
+    ///
+    #[doc = concat!( "This is a shorthand for [`AppContext::", stringify!( $generic ), "`] with [`event::", stringify!( $event ), "`]." )]
+    pub fn $ident<H, M>(&self, handler : H) -> Listener
+    where
+        H : Handler<( $( $argty , )* ), M>
+    {
+        self.listen($once, move |ctx, event : event::$event| handler.call(ctx, ( $( event.$argident , )* )))
+    }
+}
+
+macro event_fns {
+    ( $on:ident ( $( $args:tt )* ) => $list:ident ( $event_id:literal ) ) => {
+        callback_fn!{ $on ( $( $args )* ) => $list ( $event_id ), false,
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event." ) }
+    },
+    ( $on:ident / $once:ident ( $( $args:tt )* ) => $list:ident ( $event_id:literal ) ) => {
+        callback_fn!{ $on ( $( $args )* ) => $list ( $event_id ), false,
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event." ) }
+        callback_fn!{ $once ( $( $args )* ) => $list ( $event_id ), true,
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event, which is removed after it is called once." ) }
+    },
+    ( $on:ident / $once:ident ( $( $args:tt )* ) => event::$event:ident ) => {
+        event_callback_fn!{ $on ( $( $args )* ) => $event, on, false,
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event." ) }
+        event_callback_fn!{ $once ( $( $args )* ) => $event, once, true,
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event, which is removed after it is called once." ) }
+    }
+}
+
+impl AppContext {
+    event_fns!{ on_start() => on_start ("flywheel_start") }
+    event_fns!{ on_stopping / once_stopping (deadline : Instant) => on_stopping ("flywheel_stopping") }
+    event_fns!{ on_player_joined / once_player_joined (player : Player) => event::PlayerJoined }
+    event_fns!{ on_player_left / once_player_left (player : Player) => event::PlayerLeft }
+    event_fns!{ on_player_chat / once_player_chat (player : Player, message : ChatMessage) => event::PlayerChat }
+    event_fns!{ on_world_chunk_loading / once_world_chunk_loading (player : Player, pos : ChunkPos) => event::WorldChunkLoading }
+    event_fns!{ on_world_chunk_unloaded / once_world_chunk_unloaded (player : Player, pos : ChunkPos) => event::WorldChunkUnloaded }
+}
+
+impl AppContext {
+
+    /// Registers a new callback for the event `E`.
+    ///
+    /// If the host sends a malformed `E` event, the callback is not called and the error is logged.
+    pub fn on<E, H, M>(&self, handler : H) -> Listener
+    where
+        E : FlywheelEvent,
+        H : Handler<(E,), M>
+    { self.listen(false, move |ctx, event| handler.call(ctx, (event,))) }
+
+    /// Registers a new callback for the event `E`, which is removed after it is called once.
+    ///
+    /// If the host sends a malformed `E` event, the callback is not called and the error is logged.
+    pub fn once<E, H, M>(&self, handler : H) -> Listener
+    where
+        E : FlywheelEvent,
+        H : Handler<(E,), M>
+    { self.listen(true, move |ctx, event| handler.call(ctx, (event,))) }
+
+    /// Registers a new callback for the event with the given ID, which receives the raw argument payload.
+    ///
+    /// This can be used to handle events which do not have a [`FlywheelEvent`] type yet.
+    ///  The payload can be decoded with [`EventArgs`].
+    pub fn on_event<H, M>(&self, id : &str, handler : H) -> Listener
+    where
+        H : Handler<(Vec<u8>,), M>
+    { self.listen_raw(id, false, handler) }
+
+    /// Registers a new callback for the event with the given ID, which receives the raw argument payload.
+    ///  The callback is removed after it is called once.
+    ///
+    /// See [`AppContext::on_event`].
+    pub fn once_event<H, M>(&self, id : &str, handler : H) -> Listener
+    where
+        H : Handler<(Vec<u8>,), M>
+    { self.listen_raw(id, true, handler) }
+
+}
+
+/// A group of callbacks which are enabled, disabled, and removed together.
+///
+/// This is useful for callbacks which only apply to one phase of a plot, such as a
+///  minigame lobby. Every callback in the set is removed when the set is dropped.
+pub struct ListenerSet {
+    context   : AppContext,
+    listeners : Vec<Listener>
+}
+
+impl ListenerSet {
+
+    /// Creates a new, empty `ListenerSet` for callbacks registered with `context`.
+    pub fn new(context : AppContext) -> Self {
+        Self { context, listeners : Vec::new() }
+    }
+
+    /// Adds a callback to this set.
+    pub fn add(&mut self, listener : Listener) -> &mut Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    /// Enables or disables every callback in this set.
+    pub fn set_enabled(&self, enabled : bool) {
+        for &listener in &self.listeners {
+            self.context.set_enabled(listener, enabled);
+        }
+    }
+
+    /// Removes every callback in this set.
+    pub fn clear(&mut self) {
+        for listener in self.listeners.drain(..) {
+            self.context.remove(listener);
+        }
+    }
+
+}
+
+impl Drop for ListenerSet {
+    fn drop(&mut self) { self.clear(); }
+}
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use crate::prelude::*;
+    use crate::event::{ EventArgs, EventArgsError, FlywheelEvent };
+    use crate::testkit::{ self, HostCall };
+    use core::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Custom(u64);
+    impl FlywheelEvent for Custom {
+        const ID : &'static str = "custom";
+        fn decode(args : &mut EventArgs<'_>) -> Result<Self, EventArgsError> { Ok(Self(args.u64()?)) }
+    }
+
+    fn chat(msg : impl Into<Text>) {
+        unsafe { Player::from_session_id(0) }.send_chat(msg);
+    }
+
+    /// Takes the messages sent with [`chat`].
+    fn take_chats() -> Vec<String> {
+        testkit::take_calls().into_iter().filter_map(|call| match (call) {
+            HostCall::Chat { msg, .. } => Some(msg),
+            _                          => None
+        }).collect()
+    }
+
+    fn push_custom(value : u64) {
+        testkit::push_event("custom", &value.to_le_bytes());
+    }
+
+    #[test]
+    fn running_tasks_can_remove_listeners() {
+        let mut app = App::new();
+        let ctx     = app.context();
+        let first   = ctx.on(|event : Custom| async move { chat(format!("first {}", event.0)); });
+        ctx.on(move |event : Custom| async move {
+            let removed = AppContext::current().unwrap().remove(first);
+            chat(format!("second {} {removed}", event.0));
+        });
+        push_custom(1);
+        push_custom(2);
+        app.run();
+        assert_eq!(take_chats(), ["first 1", "second 1 true", "second 2 false"]);
+        assert!(! ctx.set_enabled(first, true));
+    }
+
+    #[test]
+    fn disabled_listeners_are_skipped() {
+        let mut app  = App::new();
+        let ctx      = app.context();
+        let listener = ctx.on(|event : Custom| async move { chat(format!("{}", event.0)); });
+        assert!(ctx.set_enabled(listener, false));
+        push_custom(1);
+        app.run();
+        assert!(ctx.set_enabled(listener, true));
+        push_custom(2);
+        app.run();
+        assert!(ctx.remove(listener));
+        push_custom(3);
+        app.run();
+        assert_eq!(take_chats(), ["2"]);
+        assert!(! ctx.set_enabled(listener, true));
+        assert!(! ctx.remove(listener));
+    }
+
+    #[test]
+    fn once_listeners_fire_once() {
+        let mut app = App::new();
+        let ctx     = app.context();
+        let once    = ctx.once(|event : Custom| async move { chat(format!("once {}", event.0)); });
+        ctx.once_event("custom", |args : Vec<u8>| async move { chat(format!("raw {}", args[0])); });
+        ctx.once_player_joined(|player : Player| async move { chat(format!("joined {}", player.session_id())); });
+        ctx.on(|event : Custom| async move { chat(format!("on {}", event.0)); });
+        push_custom(1);
+        push_custom(2);
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        testkit::player_joined(Uuid::max(), "b");
+        app.run();
+        assert_eq!(take_chats(), ["once 1", "raw 1", "on 1", "on 2", format!("joined {}", player.session_id()).as_str()]);
+        assert!(! ctx.remove(once));
+    }
+
+    #[test]
+    fn listener_sets_are_removed_when_dropped() {
+        let mut app = App::new();
+        let ctx     = app.context();
+        let mut set = ListenerSet::new(ctx.clone());
+        let a       = ctx.on(|event : Custom| async move { chat(format!("a {}", event.0)); });
+        set.add(a).add(ctx.on_event("custom", |_ : Vec<u8>| async move { chat("b"); }));
+        ctx.on(|event : Custom| async move { chat(format!("other {}", event.0)); });
+        set.set_enabled(false);
+        push_custom(1);
+        app.run();
+        set.set_enabled(true);
+        push_custom(2);
+        app.run();
+        drop(set);
+        push_custom(3);
+        app.run();
+        assert_eq!(take_chats(), ["other 1", "a 2", "b", "other 2", "other 3"]);
+        assert!(! ctx.remove(a));
+    }
+
+    #[test]
+    fn malformed_events_reach_other_listeners() {
+        let errors  = Rc::new(RefCell::new(Vec::new()));
+        let mut app = App::new();
+        app.on_error({
+            let errors = Rc::clone(&errors);
+            move |err| errors.borrow_mut().push(err.to_string())
+        });
+        let ctx = app.context();
+        ctx.once(|event : Custom| async move { unsafe { Player::from_session_id(event.0) }.send_chat("decoded"); });
+        ctx.on_event("custom", |args : Vec<u8>| async move { unsafe { Player::from_session_id(0) }.send_chat(format!("{args:?}")); });
+        testkit::push_event("custom", &[1, 2]);
+        testkit::push_event("custom", &[3]);
+        app.run();
+        assert_eq!(*errors.borrow(), ["\"custom\" callback failed: malformed event: expected 8 more bytes at offset 0"]);
+        assert_eq!(testkit::take_calls(), [
+            HostCall::Chat { session_id : 0, msg : "[1, 2]".to_string() },
+            HostCall::Chat { session_id : 0, msg : "[3]".to_string() }
+        ]);
+    }
+
+}
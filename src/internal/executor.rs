@@ -0,0 +1,254 @@
+use crate::game::Player;
+use crate::game::data::ChunkPos;
+use core::cell::RefCell;
+use core::pin::Pin;
+use core::sync::atomic::{ AtomicBool, Ordering };
+use core::task::{ Context, Poll, Waker };
+use std::collections::{ BTreeMap, VecDeque };
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::Wake;
+
+
+type Token = u64;
+
+thread_local! {
+    static EXECUTOR : RefCell<Executor> = RefCell::new(Executor::default());
+}
+
+#[derive(Default)]
+struct Executor {
+    next_token : Token,
+    tasks      : BTreeMap<Token, TaskSlot>,
+    ready      : VecDeque<Token>
+}
+
+struct TaskSlot {
+    fut   : Option<Pin<Box<dyn Future<Output = ()>>>>,
+    waker : Arc<TaskWaker>,
+    scope : Option<TaskScope>
+}
+
+struct TaskWaker {
+    token     : Token,
+    scheduled : AtomicBool
+}
+
+impl Wake for TaskWaker {
+    fn wake(self : Arc<Self>) { self.wake_by_ref(); }
+    fn wake_by_ref(self : &Arc<Self>) {
+        if (! self.scheduled.swap(true, Ordering::Relaxed)) {
+            // Ignored if the thread is being torn down.
+            let _ = EXECUTOR.try_with(|executor| executor.borrow_mut().ready.push_back(self.token));
+        }
+    }
+}
+
+
+/// A handle to a spawned task.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Task {
+    token : Token
+}
+
+/// A handle to a spawned task, which can be awaited to get its output.
+///
+/// Dropping a `TaskHandle` does not cancel the task.
+pub struct TaskHandle<T> {
+    task  : Task,
+    state : Rc<RefCell<JoinState<T>>>
+}
+
+impl<T> TaskHandle<T> {
+    /// Returns the [`Task`] this handle refers to.
+    pub fn task(&self) -> Task { self.task.clone() }
+
+    /// Returns `true` if the task has finished or was cancelled.
+    pub fn is_finished(&self) -> bool {
+        ! matches!(&*self.state.borrow(), JoinState::Pending(_))
+    }
+}
+
+/// An error returned by [`TaskHandle`] if the task did not finish.
+#[derive(Clone, Debug)]
+pub enum JoinError {
+    /// The task was dropped before it finished.
+    Canceled
+}
+
+enum JoinState<T> {
+    Pending(Option<Waker>),
+    Ready(T),
+    Canceled,
+    Taken
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = Result<T, JoinError>;
+    fn poll(self : Pin<&mut Self>, ctx : &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.borrow_mut();
+        match (&mut *state) {
+            JoinState::Pending(waker) => {
+                *waker = Some(ctx.waker().clone());
+                Poll::Pending
+            },
+            JoinState::Canceled => Poll::Ready(Err(JoinError::Canceled)),
+            JoinState::Taken    => panic!("`TaskHandle` polled after completion"),
+            JoinState::Ready(_) => {
+                let JoinState::Ready(out) = core::mem::replace(&mut *state, JoinState::Taken) else { unreachable!() };
+                Poll::Ready(Ok(out))
+            }
+        }
+    }
+}
+
+/// Reports the output of a task to its [`TaskHandle`], or cancellation if dropped first.
+struct JoinSender<T> {
+    state : Rc<RefCell<JoinState<T>>>
+}
+
+impl<T> JoinSender<T> {
+    fn finish(&self, next : JoinState<T>) {
+        let prev = core::mem::replace(&mut *self.state.borrow_mut(), next);
+        if let JoinState::Pending(Some(waker)) = prev {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for JoinSender<T> {
+    fn drop(&mut self) {
+        if (matches!(&*self.state.borrow(), JoinState::Pending(_))) {
+            self.finish(JoinState::Canceled);
+        }
+    }
+}
+
+
+/// Something which tasks can be tied to, so that they are cancelled when it ends.
+///
+/// See [`spawn_in`].
+#[derive(Clone, Copy)]
+pub enum TaskScope {
+    /// Ends when the player leaves the server.
+    Player(Player),
+    /// Ends when the chunk is unloaded from the player's world, or when the player leaves.
+    Chunk(Player, ChunkPos)
+}
+
+impl TaskScope {
+    fn session_id(&self) -> u64 {
+        match (self) {
+            Self::Player(player) | Self::Chunk(player, _) => player.session_id()
+        }
+    }
+
+    /// Returns `true` if ending `self` also ends `other`.
+    fn contains(&self, other : &TaskScope) -> bool {
+        self.session_id() == other.session_id() && match (self, other) {
+            (Self::Player(_), _)                   => true,
+            (Self::Chunk(_, a), Self::Chunk(_, b)) => a == b,
+            (Self::Chunk(_, _), Self::Player(_))   => false
+        }
+    }
+}
+
+
+/// Spawns a new task.
+///
+/// The task starts running the next time the scheduler runs tasks, and keeps running
+///  until it finishes, even if the returned [`TaskHandle`] is dropped.
+pub fn spawn<F, T>(fut : F) -> TaskHandle<T>
+where
+    F : Future<Output = T> + 'static,
+    T : 'static
+{ spawn_scoped(fut, None) }
+
+/// Spawns a new task which is cancelled once `scope` ends.
+///
+/// A cancelled task is dropped without being polled again, along with any
+///  [`Sleep`](crate::task::Sleep)s it is waiting on, and its [`TaskHandle`] resolves to
+///  [`JoinError::Canceled`].
+///
+/// Tasks spawned by the task are not tied to `scope`, unless they are also spawned with `spawn_in`.
+pub fn spawn_in<F, T>(scope : TaskScope, fut : F) -> TaskHandle<T>
+where
+    F : Future<Output = T> + 'static,
+    T : 'static
+{ spawn_scoped(fut, Some(scope)) }
+
+pub(crate) fn spawn_scoped<F, T>(fut : F, scope : Option<TaskScope>) -> TaskHandle<T>
+where
+    F : Future<Output = T> + 'static,
+    T : 'static
+{
+    let state  = Rc::new(RefCell::new(JoinState::Pending(None)));
+    let sender = JoinSender { state : Rc::clone(&state) };
+    let fut    = Box::pin(async move {
+        let out = fut.await;
+        sender.finish(JoinState::Ready(out));
+    });
+    let token = EXECUTOR.with_borrow_mut(|executor| {
+        let token = executor.next_token;
+        executor.next_token += 1;
+        let waker = Arc::new(TaskWaker { token, scheduled : AtomicBool::new(true) });
+        executor.tasks.insert(token, TaskSlot { fut : Some(fut), waker, scope });
+        executor.ready.push_back(token);
+        token
+    });
+    TaskHandle { task : Task { token }, state }
+}
+
+
+/// Polls every task which has been woken, until none are left.
+pub(crate) fn run_ready() {
+    while let Some((token, mut fut, waker,)) = EXECUTOR.with_borrow_mut(|executor| {
+        while let Some(token) = executor.ready.pop_front() {
+            if let Some(slot) = executor.tasks.get_mut(&token)
+                && let Some(fut) = slot.fut.take()
+            {
+                slot.waker.scheduled.store(false, Ordering::Relaxed);
+                return Some((token, fut, Waker::from(Arc::clone(&slot.waker)),));
+            }
+        }
+        None
+    }) {
+        let done = fut.as_mut().poll(&mut Context::from_waker(&waker)).is_ready();
+        // Finished tasks are dropped outside of the borrow, as dropping them can spawn or wake other tasks.
+        let fut = EXECUTOR.with_borrow_mut(|executor| {
+            match (executor.tasks.get_mut(&token)) {
+                Some(slot) if (! done) => {
+                    slot.fut = Some(fut);
+                    None
+                },
+                _ => {
+                    executor.tasks.remove(&token);
+                    Some(fut)
+                }
+            }
+        });
+        drop(fut);
+    }
+}
+
+/// Cancels every task in `scope`, including the tasks of any chunk scopes inside a player scope.
+pub(crate) fn cancel_scope(scope : TaskScope) {
+    let canceled = EXECUTOR.with_borrow_mut(|executor| {
+        let tokens = executor.tasks.iter()
+            .filter(|(_, slot,)| slot.scope.is_some_and(|inner| scope.contains(&inner)))
+            .map(|(&token, _,)| token)
+            .collect::<Vec<_>>();
+        tokens.into_iter()
+            .filter_map(|token| executor.tasks.remove(&token))
+            .map(|slot| slot.fut)
+            .collect::<Vec<_>>()
+    });
+    // Tasks are dropped outside of the borrow, as dropping them can spawn or wake other tasks.
+    // A task which is currently being polled is dropped by `run_ready` once it yields.
+    drop(canceled);
+}
+
+/// Returns `true` if no tasks are waiting to be polled.
+pub(crate) fn is_idle() -> bool {
+    EXECUTOR.with_borrow(|executor| executor.ready.is_empty())
+}
@@ -0,0 +1,53 @@
+//! Logging to the console of selfhosted servers.
+//!
+//! The log functions are provided with [`Capabilities::SELFHOSTED`](crate::game::Capabilities::SELFHOSTED),
+//!  so messages are dropped on hosts which do not support it.
+
+
+use crate::game::optional_imports;
+
+
+optional_imports! {
+    fn flywheel_trace(in_msg : usize, msg_len : u32);
+    fn flywheel_debug(in_msg : usize, msg_len : u32);
+    fn flywheel_info(in_msg : usize, msg_len : u32);
+    fn flywheel_pass(in_msg : usize, msg_len : u32);
+    fn flywheel_warn(in_msg : usize, msg_len : u32);
+    fn flywheel_error(in_msg : usize, msg_len : u32);
+    fn flywheel_fatal(in_msg : usize, msg_len : u32);
+}
+
+
+/// The level of a message logged to the console.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    /// Logged with `trace!`.
+    Trace,
+    /// Logged with `debug!`.
+    Debug,
+    /// Logged with `info!`.
+    Info,
+    /// Logged with `pass!`.
+    Pass,
+    /// Logged with `warn!`.
+    Warn,
+    /// Logged with `error!`.
+    Error,
+    /// Logged with `fatal!`, and used for panics.
+    Fatal
+}
+
+/// Sends a message to the console at `level`, if the host supports [`Capabilities::SELFHOSTED`](crate::game::Capabilities::SELFHOSTED).
+pub fn log(level : Level, msg : &str) {
+    let f = match (level) {
+        Level::Trace => flywheel_trace(),
+        Level::Debug => flywheel_debug(),
+        Level::Info  => flywheel_info(),
+        Level::Pass  => flywheel_pass(),
+        Level::Warn  => flywheel_warn(),
+        Level::Error => flywheel_error(),
+        Level::Fatal => flywheel_fatal()
+    };
+    let Some(f) = f else { return; };
+    unsafe { f(msg.as_ptr() as usize, msg.len() as u32); }
+}
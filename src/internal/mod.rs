@@ -1,4 +1,16 @@
 mod scheduler;
 pub use scheduler::App;
+mod context;
+pub use context::{ AppContext, Listener, ListenerSet };
+mod state;
+pub use state::{ State, FromContext, Handler, HandlerOutput, HandlerError };
 
-mod alloc;
+pub(crate) mod executor;
+pub(crate) mod timer;
+
+pub mod alloc;
+
+pub(crate) mod panic;
+// Only the `FATAL` level is used without the `selfhosted` logging macros.
+#[cfg_attr(not(feature = "selfhosted"), allow(dead_code))]
+pub(crate) mod log;
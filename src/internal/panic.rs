@@ -0,0 +1,49 @@
+use std::panic;
+use std::sync::Once;
+
+
+/// Installs a panic hook which reports panics to the host, so they are not only seen as an opaque trap.
+///
+/// Panics are logged to the console at the `FATAL` level if the host supports
+///  [`Capabilities::SELFHOSTED`](crate::game::Capabilities::SELFHOSTED), with or without the
+///  `selfhosted` feature. Any previously set panic hook is still called afterwards.
+pub(crate) fn install_hook() {
+    static INSTALLED : Once = Once::new();
+    INSTALLED.call_once(|| {
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let payload = info.payload();
+            let msg     = payload.downcast_ref::<&str>().copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("Box<dyn Any>");
+            let msg = match (info.location()) {
+                Some(loc) => format!("Panicked at {}:{}:{}: {msg}", loc.file(), loc.line(), loc.column()),
+                None      => format!("Panicked: {msg}")
+            };
+            sink(&msg);
+            prev(info);
+        }));
+    });
+}
+
+/// Sends a panic message to the host, if it has a console to log it to.
+fn sink(msg : &str) {
+    super::log::log(super::log::Level::Fatal, msg);
+}
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use crate::testkit::{ self, HostCall, LogLevel };
+    use std::panic;
+
+    #[test]
+    fn panics_are_logged() {
+        super::install_hook();
+        assert!(panic::catch_unwind(|| panic!("boom")).is_err());
+        assert!(testkit::take_calls().into_iter().any(|call| matches!(call,
+            HostCall::Log { level : LogLevel::Fatal, msg } if msg.starts_with("Panicked at ") && msg.ends_with(": boom")
+        )));
+    }
+
+}
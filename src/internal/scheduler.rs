@@ -1,28 +1,38 @@
-use crate::game::Player;
+use super::{ executor, timer };
+use super::alloc::{ HostBuf, Out };
+use super::context::AppContext;
+use super::state::{ Handler, HandlerError };
+use super::executor::{ TaskHandle, TaskScope };
+use crate::event::{ self, EventArgs, EventArgsError, FlywheelEvent };
+use crate::game::{ Player, ChatMessage, optional_imports };
+use crate::game::command::Command;
 use crate::game::data::ChunkPos;
-use core::marker::Tuple;
-use core::pin::Pin;
-use core::task::{ Context, Poll };
-use wasm_rs_async_executor::single_threaded as task;
+use crate::time::{ Duration, Instant };
 
 
 unsafe extern "C" {
     safe fn flywheel_refuel();
-    unsafe fn flywheel_next_event(out_id_ptr : u32, out_id_len : u32, out_args_ptr : u32, out_args_len : u32) -> u32;
+    unsafe fn flywheel_next_event(out_id_ptr : usize, out_id_len : usize, out_args_ptr : usize, out_args_len : usize) -> u32;
 }
 
+optional_imports! {
+    /// Tells the host that the plot has nothing to do until the next event, or until the
+    ///  given deadline if `deadline_secs` is not `u64::MAX`. The host may suspend the plot until then.
+    fn flywheel_idle(deadline_secs : u64, deadline_nanos : u32);
+}
 
-type EventCallbacks<Args> = Vec<Box<dyn Fn<Args, Output = Pin<Box<dyn Future<Output = ()>>>>>>;
 
 /// A Flywheel application.
 #[derive(Default)]
 pub struct App {
-    on_start                : EventCallbacks<()>,
-    //on_stopping             : EventCallbacks<()>,
-    on_player_joined        : EventCallbacks<(Player,)>,
-    on_player_left          : EventCallbacks<(Player,)>,
-    on_world_chunk_loading  : EventCallbacks<(Player, ChunkPos,)>,
-    on_world_chunk_unloaded : EventCallbacks<(Player, ChunkPos,)>
+    started  : bool,
+    stopping : Option<Stopping>,
+    context  : AppContext
+}
+
+struct Stopping {
+    deadline : Instant,
+    handlers : Vec<TaskHandle<()>>
 }
 
 impl App {
@@ -30,104 +40,510 @@ impl App {
     /// Creates a new `App`.
     pub fn new() -> Self { Self::default() }
 
+    /// Returns a handle to the callbacks of this `App`, which can be used to add
+    ///  or remove callbacks while it is running.
+    pub fn context(&self) -> AppContext { self.context.clone() }
+
+    /// Sets the hook which is called when a callback returns an error.
+    ///
+    /// See [`AppContext::on_error`].
+    pub fn on_error<F>(&mut self, f : F) -> &mut Self
+    where
+        F : Fn(&HandlerError) + 'static
+    {
+        self.context.on_error(f);
+        self
+    }
+
+    /// Adds shared state of type `T`, which callbacks can take as a [`State<T>`](crate::State) argument.
+    ///
+    /// See [`AppContext::set_state`].
+    pub fn with_state<T : 'static>(&mut self, value : T) -> &mut Self {
+        self.context.set_state(value);
+        self
+    }
+
     /// Runs the `App`.
+    ///
+    /// The `on_start` callbacks are only fired the first time this is called.
+    ///
+    /// This returns once the plot has stopped, either because the host is shutting it down,
+    ///  or because [`App::request_stop`] was called. Before returning, the `on_stopping`
+    ///  callbacks are given until the stopping deadline to finish.
+    ///
+    /// This also installs a panic hook which logs panics to the console at the `FATAL` level, if
+    ///  the host supports [`Capabilities::SELFHOSTED`](crate::game::Capabilities::SELFHOSTED).
+    ///  This does not need the `selfhosted` feature. On other hosts, panics are dropped and only
+    ///  seen as a trap.
+    ///
+    /// With the `testkit` feature enabled, this also returns once the mock host has no
+    ///  more events and every task is waiting.
     pub fn run(&mut self) {
-        task::block_on(async {
-            Self::fire(&self.on_start, ());
-            AppRunFuture { app : self }.await;
-        })
+        super::panic::install_hook();
+        let _guard = self.context.enter();
+        if (! self.started) {
+            self.started = true;
+            self.context.fire_start();
+        }
+        loop {
+            let handled = self.next_event();
+            timer::fire_expired();
+            executor::run_ready();
+            if let Some(grace) = self.context.take_stop_request() {
+                self.begin_stopping(Instant::now() + grace);
+            }
+            if let Some(stopping) = &mut self.stopping {
+                stopping.handlers.retain(|handle| ! handle.is_finished());
+                if (stopping.handlers.is_empty() || Instant::now() >= stopping.deadline) {
+                    self.stopping = None;
+                    return;
+                }
+            }
+            if (! handled && executor::is_idle()) {
+                let deadline = timer::next_deadline().into_iter()
+                    .chain(self.stopping.as_ref().map(|stopping| stopping.deadline))
+                    .min();
+                if let Some(idle) = flywheel_idle() {
+                    match (deadline) {
+                        Some(deadline) => {
+                            let deadline = deadline.duration_since(Instant::UNIX_EPOCH);
+                            unsafe { idle(deadline.as_secs(), deadline.subsec_nanos()); }
+                        },
+                        None => unsafe { idle(u64::MAX, 0); }
+                    }
+                }
+                #[cfg(feature = "testkit")]
+                return;
+            }
+            flywheel_refuel();
+        }
+    }
+
+    /// Requests that this `App` stops.
+    ///
+    /// See [`AppContext::request_stop`].
+    pub fn request_stop(&self, grace : Duration) {
+        self.context.request_stop(grace);
     }
 
-    fn fire<T : Tuple + Clone>(callbacks : &EventCallbacks<T>, args : T) -> usize {
-        for callback in callbacks {
-            task::spawn(callback.call(args.clone()));
+    fn begin_stopping(&mut self, deadline : Instant) {
+        match (&mut self.stopping) {
+            Some(stopping) => { stopping.deadline = stopping.deadline.min(deadline); },
+            None           => {
+                let handlers  = self.context.fire_stopping(deadline);
+                self.stopping = Some(Stopping { deadline, handlers });
+            }
         }
-        callbacks.len()
     }
 
 }
 
-macro event_fn( $ident:ident ( $( $argident:ident : $argty:ty ),* $(,)? ) ) {
-    #[doc = concat!( "Registers a new callback for the `", stringify!( $ident ), "` event." )]
-    pub fn $ident<F, Fut>(&mut self, f : F) -> &mut Self
+macro callback_fn( $ident:ident ( $( $argty:ty ),* ), $doc:expr ) {
+    #[doc = $doc]
+    ///
+    #[doc = concat!( "See [`AppContext::", stringify!( $ident ), "`]." )]
+    pub fn $ident<H, M>(&mut self, handler : H) -> &mut Self
     where
-        F   : (Fn( $( $argty , )* ) -> Fut) + 'static,
-        Fut : Future<Output = ()> + 'static
+        H : Handler<( $( $argty , )* ), M>
     {
-        self.$ident.push(Box::new(move | $( $argident , )* | Box::pin(f( $( $argident , )* ))));
+        self.context.$ident(handler);
         self
     }
 }
 
+macro event_fn {
+    ( $on:ident ( $( $argident:ident : $argty:ty ),* $(,)? ) ) => {
+        callback_fn!{ $on ( $( $argty ),* ),
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event." ) }
+    },
+    ( $on:ident / $once:ident ( $( $argident:ident : $argty:ty ),* $(,)? ) ) => {
+        callback_fn!{ $on ( $( $argty ),* ),
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event." ) }
+        callback_fn!{ $once ( $( $argty ),* ),
+            concat!( "Registers a new callback for the `", stringify!( $on ), "` event, which is removed after it is called once." ) }
+    }
+}
+
 impl App {
-    event_fn!{ on_start(,) }
-    event_fn!{ on_player_joined(player : Player) }
-    event_fn!{ on_player_left(player : Player) }
-    event_fn!{ on_world_chunk_loading(player : Player, pos : ChunkPos) }
-    event_fn!{ on_world_chunk_unloaded(player : Player, pos : ChunkPos) }
+    event_fn!{ on_start() }
+    event_fn!{ on_stopping / once_stopping (deadline : Instant) }
+    event_fn!{ on_player_joined / once_player_joined (player : Player) }
+    event_fn!{ on_player_left / once_player_left (player : Player) }
+    event_fn!{ on_player_chat / once_player_chat (player : Player, message : ChatMessage) }
+    event_fn!{ on_world_chunk_loading / once_world_chunk_loading (player : Player, pos : ChunkPos) }
+    event_fn!{ on_world_chunk_unloaded / once_world_chunk_unloaded (player : Player, pos : ChunkPos) }
 }
 
+impl App {
+
+    /// Registers a new callback for the event `E`.
+    ///
+    /// If the host sends a malformed `E` event, the callback is not called and the error is logged.
+    pub fn on<E, H, M>(&mut self, handler : H) -> &mut Self
+    where
+        E : FlywheelEvent,
+        H : Handler<(E,), M>
+    {
+        self.context.on(handler);
+        self
+    }
+
+    /// Registers a new callback for the event `E`, which is removed after it is called once.
+    ///
+    /// If the host sends a malformed `E` event, the callback is not called and the error is logged.
+    pub fn once<E, H, M>(&mut self, handler : H) -> &mut Self
+    where
+        E : FlywheelEvent,
+        H : Handler<(E,), M>
+    {
+        self.context.once(handler);
+        self
+    }
+
+    /// Registers a command, replacing any command with the same name.
+    ///
+    /// See [`AppContext::command`].
+    #[track_caller]
+    pub fn command(&mut self, command : Command) -> &mut Self {
+        self.context.command(command);
+        self
+    }
+
+    /// Registers a new callback for the event with the given ID, which receives the raw argument payload.
+    ///
+    /// This can be used to handle events which do not have a [`FlywheelEvent`] type yet.
+    ///  The payload can be decoded with [`EventArgs`].
+    pub fn on_event<H, M>(&mut self, id : &str, handler : H) -> &mut Self
+    where
+        H : Handler<(Vec<u8>,), M>
+    {
+        self.context.on_event(id, handler);
+        self
+    }
+
+    /// Registers a new callback for the event with the given ID, which receives the raw argument payload.
+    ///  The callback is removed after it is called once.
+    ///
+    /// See [`App::on_event`].
+    pub fn once_event<H, M>(&mut self, id : &str, handler : H) -> &mut Self
+    where
+        H : Handler<(Vec<u8>,), M>
+    {
+        self.context.once_event(id, handler);
+        self
+    }
 
-struct AppRunFuture<'l> {
-    app : &'l mut App
 }
 
-impl Future for AppRunFuture<'_> {
-    type Output = !;
-    fn poll(self : Pin<&mut Self>, _ : &mut Context<'_>) -> Poll<Self::Output> {
-        let mut id_ptr   = 0u32;
-        let mut id_len   = 0u32;
-        let mut args_ptr = 0u32;
-        let mut args_len = 0u32;
+
+impl App {
+
+    /// Handles the next event from the host, if there is one.
+    ///
+    /// Returns `false` if there were no events waiting.
+    fn next_event(&mut self) -> bool {
+        let mut id_ptr   = Out::<usize>::new();
+        let mut id_len   = Out::<u32>::new();
+        let mut args_ptr = Out::<usize>::new();
+        let mut args_len = Out::<u32>::new();
         if (unsafe { flywheel_next_event(
-            (&mut id_ptr) as (*mut _) as u32,
-            (&mut id_len) as (*mut _) as u32,
-            (&mut args_ptr) as (*mut _) as u32,
-            (&mut args_len) as (*mut _) as u32
-        ) } != 0) {
-            let id_len   = id_len as usize;
-            let id       = unsafe { String::from_raw_parts(id_ptr as (*mut u8), id_len, id_len) };
-            let args_len = args_len as usize;
-            let args     = unsafe { Vec::from_raw_parts(args_ptr as (*mut u8), args_len, args_len) };
-            match (id.as_str()) {
-
-                "flywheel_player_joined" => {
-                    let session_id = u64::from_le_bytes(*unsafe { args.as_chunks_unchecked::<8>().get_unchecked(0) });
-                    let player     = unsafe { Player::from_session_id(session_id) };
-                    App::fire(&self.app.on_player_joined, (player,));
-                },
-                "flywheel_player_left" => {
-                    let session_id = u64::from_le_bytes(*unsafe { args.as_chunks_unchecked::<8>().get_unchecked(0) });
-                    let player     = unsafe { Player::from_session_id(session_id) };
-                    App::fire(&self.app.on_player_left, (player,));
-                },
-
-                "flywheel_world_chunk_loading" => {
-                    let session_id = u64::from_le_bytes(*unsafe { args.as_chunks_unchecked::<8>().get_unchecked(0) });
-                    let player     = unsafe { Player::from_session_id(session_id) };
-                    let x          = i32::from_le_bytes(*unsafe { args.get_unchecked(8..).as_chunks_unchecked::<4>().get_unchecked(0) });
-                    let z          = i32::from_le_bytes(*unsafe { args.get_unchecked(12..).as_chunks_unchecked::<4>().get_unchecked(0) });
-                    let pos        = ChunkPos::new(x, z);
-                    if (App::fire(&self.app.on_world_chunk_loading, (player, pos,)) == 0) {
-                        player.world().mark_ready(pos);
-                    }
-                },
-                "flywheel_world_chunk_unloaded" => {
-                    let session_id = u64::from_le_bytes(*unsafe { args.as_chunks_unchecked::<8>().get_unchecked(0) });
-                    let player     = unsafe { Player::from_session_id(session_id) };
-                    let x          = i32::from_le_bytes(*unsafe { args.get_unchecked(8..).as_chunks_unchecked::<4>().get_unchecked(0) });
-                    let z          = i32::from_le_bytes(*unsafe { args.get_unchecked(12..).as_chunks_unchecked::<4>().get_unchecked(0) });
-                    let pos        = ChunkPos::new(x, z);
-                    App::fire(&self.app.on_world_chunk_unloaded, (player, pos,));
-                },
-
-                _ => {
-                    #[cfg(feature = "selfhosted")]
-                    crate::selfhosted::error!("Unknown event {:?} triggered", id);
+            id_ptr.ptr(),
+            id_len.ptr(),
+            args_ptr.ptr(),
+            args_len.ptr()
+        ) } == 0) { return false; }
+        let id   = unsafe { HostBuf::from_raw(id_ptr.get(), id_len.get()) };
+        let args = unsafe { HostBuf::from_raw(args_ptr.get(), args_len.get()) };
+        let Ok(id) = id.into_string() else {
+            #[cfg(feature = "selfhosted")]
+            crate::selfhosted::error!("Event with a non-UTF-8 ID triggered");
+            return true;
+        };
+        if let Err(err) = self.handle_event(&id, args.as_slice()) {
+            #[cfg(feature = "selfhosted")]
+            crate::selfhosted::error!("Malformed {:?} event triggered: {}", id, err);
+            #[cfg(not(feature = "selfhosted"))]
+            let _ = err;
+        }
+        true
+    }
+
+    fn handle_event(&mut self, id : &str, args : &[u8]) -> Result<(), EventArgsError> {
+        // Chat messages are decoded before their callbacks are fired, so that the message is held
+        //  until every callback has a handle to it. It is then resolved when the last handle is
+        //  dropped, even if no callback decoded it.
+        let chat     = (id == event::PlayerChat::ID).then(|| event::PlayerChat::decode(&mut EventArgs::new(args)));
+        let handlers = self.context.dispatch(id, args);
+        match (id) {
+
+            "flywheel_stopping" => {
+                let deadline = EventArgs::new(args).instant()?;
+                self.begin_stopping(deadline);
+            },
+
+            event::WorldChunkLoading::ID => {
+                // Chunks which nothing is generating are marked ready straight away.
+                if (handlers.is_empty()) {
+                    let event = event::WorldChunkLoading::decode(&mut EventArgs::new(args))?;
+                    event.player.world().mark_ready(event.pos);
+                }
+            },
+
+            event::PlayerChat::ID => {
+                drop(chat.transpose()?);
+            },
+
+            event::PlayerLeft::ID => {
+                let event = event::PlayerLeft::decode(&mut EventArgs::new(args))?;
+                executor::cancel_scope(TaskScope::Player(event.player));
+            },
+
+            event::WorldChunkUnloaded::ID => {
+                let event = event::WorldChunkUnloaded::decode(&mut EventArgs::new(args))?;
+                executor::cancel_scope(TaskScope::Chunk(event.player, event.pos));
+            },
+
+            event::PlayerJoined::ID => {
+                if (self.context.has_commands()) {
+                    let event = event::PlayerJoined::decode(&mut EventArgs::new(args))?;
+                    self.context.send_commands(event.player);
                 }
+            },
+
+            event::PlayerCommand::ID => {
+                let event = event::PlayerCommand::decode(&mut EventArgs::new(args))?;
+                self.context.run_command(event.player, &event.command);
+            },
+
+            _ => if (handlers.is_empty()) {
+                #[cfg(feature = "selfhosted")]
+                crate::selfhosted::error!("Unknown event {:?} triggered", id);
             }
         }
-        flywheel_refuel();
-        Poll::Pending
+        Ok(())
+    }
+
+}
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use crate::prelude::*;
+    use crate::internal::timer;
+    use crate::task::TaskScope;
+    use crate::testkit::{ self, HostCall };
+    use core::cell::Cell;
+
+    thread_local! {
+        /// The number of `DropGuard`s which have been dropped.
+        static DROPPED : Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counts how many tasks holding one were dropped, in `DROPPED`.
+    struct DropGuard;
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) { DROPPED.set(DROPPED.get() + 1); }
+    }
+
+    /// Sleeps for a minute while holding a `DropGuard`, then sends a chat message to `player`.
+    async fn sleep_then_chat(player : Player) {
+        let _guard = DropGuard;
+        task::sleep(Duration::from_secs(60)).await;
+        player.send_chat("woke");
+    }
+
+    /// Counts the chat messages recorded by the mock host.
+    fn chats() -> usize {
+        testkit::take_calls().into_iter().filter(|call| matches!(call, HostCall::Chat { .. })).count()
+    }
+
+    #[test]
+    fn start_fires_once() {
+        let mut app = App::new();
+        app.on_start(|| async { unsafe { Player::from_session_id(0) }.send_chat("started"); });
+        app.run();
+        app.run();
+        assert_eq!(chats(), 1);
+    }
+
+    #[test]
+    fn events_are_dispatched() {
+        let mut app = App::new();
+        app.on_player_joined(|player : Player| async move { player.send_chat("joined"); });
+        app.on_event("custom", |args : Vec<u8>| async move { unsafe { Player::from_session_id(0) }.send_chat(format!("{args:?}")); });
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        testkit::push_event("custom", &[1, 2]);
+        app.run();
+        assert_eq!(testkit::take_calls(), [
+            HostCall::Chat { session_id : player.session_id(), msg : "joined".to_string() },
+            HostCall::Chat { session_id : 0, msg : "[1, 2]".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn timers_wake_tasks() {
+        let mut app = App::new();
+        app.on_start(|| async {
+            for secs in [1, 2, 3] {
+                task::spawn(async move {
+                    task::sleep(Duration::from_secs(secs)).await;
+                    unsafe { Player::from_session_id(0) }.send_chat("woke");
+                });
+            }
+        });
+        app.run();
+        assert_eq!(chats(), 0);
+        testkit::advance(Duration::from_millis(2500));
+        app.run();
+        assert_eq!(chats(), 2);
+        testkit::advance(Duration::from_secs(1));
+        app.run();
+        assert_eq!(chats(), 1);
+    }
+
+    #[test]
+    fn chunks_without_callbacks_are_marked_ready() {
+        let mut app = App::new();
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        testkit::chunk_loading(player, ChunkPos::new(-1, 2));
+        app.run();
+        assert_eq!(testkit::take_calls(), [
+            HostCall::MarkReady { session_id : player.session_id(), chunk : ChunkPos::new(-1, 2) }
+        ]);
+
+        // With a callback, marking the chunk as ready is up to the plot.
+        app.on_world_chunk_loading(|_ : Player, _ : ChunkPos| async { });
+        testkit::chunk_loading(player, ChunkPos::new(0, 0));
+        app.run();
+        assert_eq!(testkit::take_calls(), []);
+    }
+
+    #[test]
+    fn stopping_waits_for_callbacks() {
+        let mut app = App::new();
+        app.on_stopping(|deadline : Instant| async move {
+            task::sleep(Duration::from_secs(1)).await;
+            unsafe { Player::from_session_id(0) }.send_chat(format!("{:?}", deadline.duration_since(Instant::UNIX_EPOCH)));
+        });
+        app.on_player_joined(|player : Player| async move { player.send_chat("joined"); });
+        testkit::stopping(Instant::UNIX_EPOCH + Duration::from_secs(10));
+        app.run();
+        assert_eq!(chats(), 0);
+
+        // Once the callbacks finish, `App::run` returns before handling more events.
+        testkit::advance(Duration::from_secs(1));
+        testkit::player_joined(Uuid::nil(), "a");
+        testkit::player_joined(Uuid::nil(), "b");
+        app.run();
+        assert_eq!(testkit::take_calls(), [
+            HostCall::Chat { session_id : 0, msg : "joined".to_string() },
+            HostCall::Chat { session_id : 0, msg : "10s".to_string() }
+        ]);
+    }
+
+    #[test]
+    fn chat_is_resolved_without_typed_callbacks() {
+        let mut app = App::new();
+        app.on_event("flywheel_player_chat", |_ : Vec<u8>| async { });
+        let player     = testkit::player_joined(Uuid::nil(), "a");
+        let message_id = testkit::player_chat(player, "hello");
+        app.run();
+        assert_eq!(testkit::take_calls(), [HostCall::ChatResolved {
+            session_id : player.session_id(), message_id,
+            cancelled  : false,
+            rewrite    : None,
+            recipients : None
+        }]);
+    }
+
+    #[test]
+    fn chat_is_resolved_after_every_callback() {
+        let mut app = App::new();
+        app.on_player_chat(|_ : Player, message : ChatMessage| async move { message.cancel(); });
+        app.on_event("flywheel_player_chat", |_ : Vec<u8>| async { });
+        let player     = testkit::player_joined(Uuid::nil(), "a");
+        let message_id = testkit::player_chat(player, "hello");
+        app.run();
+        assert_eq!(testkit::take_calls(), [HostCall::ChatResolved {
+            session_id : player.session_id(), message_id,
+            cancelled  : true,
+            rewrite    : None,
+            recipients : None
+        }]);
     }
+
+    #[test]
+    fn request_stop_waits_for_grace_deadline() {
+        let mut app = App::new();
+        app.on_start(|ctx : AppContext| async move { ctx.request_stop(Duration::from_secs(5)); });
+        app.on_stopping(|_ : Instant| async { task::sleep(Duration::from_secs(100)).await; });
+        app.on_player_joined(|player : Player| async move { player.send_chat("hi"); });
+        app.run();
+
+        // Before the deadline, the `App` keeps handling events.
+        testkit::advance(Duration::from_secs(4));
+        testkit::player_joined(Uuid::nil(), "a");
+        testkit::player_joined(Uuid::nil(), "b");
+        app.run();
+        assert_eq!(chats(), 2);
+
+        // Once it passes, `App::run` returns although the `on_stopping` callback is not finished.
+        testkit::advance(Duration::from_secs(2));
+        testkit::player_joined(Uuid::nil(), "c");
+        testkit::player_joined(Uuid::nil(), "d");
+        app.run();
+        assert_eq!(chats(), 1);
+    }
+
+    #[test]
+    fn player_tasks_are_dropped_when_the_player_leaves() {
+        let mut app = App::new();
+        app.on_player_joined(|player : Player| async move {
+            player.spawn(sleep_then_chat(player));
+            task::spawn_in(TaskScope::Player(player), sleep_then_chat(player));
+            task::spawn_in(TaskScope::Chunk(player, ChunkPos::ZERO), sleep_then_chat(player));
+        });
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        app.run();
+        assert_eq!(DROPPED.get(), 0);
+        assert!(timer::next_deadline().is_some());
+
+        testkit::player_left(player);
+        app.run();
+        assert_eq!(DROPPED.get(), 3);
+        assert!(timer::next_deadline().is_none());
+        testkit::take_calls();
+
+        testkit::advance(Duration::from_secs(120));
+        app.run();
+        assert_eq!(testkit::take_calls(), []);
+    }
+
+    #[test]
+    fn chunk_tasks_are_dropped_when_the_chunk_unloads() {
+        let mut app = App::new();
+        app.on_world_chunk_loading(|player : Player, chunk : ChunkPos| async move {
+            task::spawn_in(TaskScope::Chunk(player, chunk), sleep_then_chat(player));
+        });
+        let player = testkit::player_joined(Uuid::nil(), "a");
+        testkit::chunk_loading(player, ChunkPos::new(0, 0));
+        testkit::chunk_loading(player, ChunkPos::new(-1, 0));
+        app.run();
+
+        testkit::chunk_unloaded(player, ChunkPos::new(-1, 0));
+        app.run();
+        assert_eq!(DROPPED.get(), 1);
+        testkit::take_calls();
+
+        // Only the task of the chunk which is still loaded wakes.
+        testkit::advance(Duration::from_secs(120));
+        app.run();
+        assert_eq!(DROPPED.get(), 2);
+        assert!(timer::next_deadline().is_none());
+        assert_eq!(testkit::take_calls(), [
+            HostCall::Chat { session_id : player.session_id(), msg : "woke".to_string() }
+        ]);
+    }
+
 }
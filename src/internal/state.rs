@@ -0,0 +1,243 @@
+use super::context::AppContext;
+use core::any::type_name;
+use core::cell::{ RefCell, Ref, RefMut };
+use core::error::Error;
+use core::fmt;
+use core::marker::Tuple;
+use core::pin::Pin;
+use std::rc::Rc;
+
+
+/// A value which a callback can take as an extra argument, after the arguments of its event.
+///
+/// The value is extracted from the [`AppContext`] of the `App` each time the callback is called.
+pub trait FromContext : Sized + 'static {
+
+    /// Extracts this value from `ctx`.
+    fn from_context(ctx : &AppContext) -> Self;
+
+}
+
+impl FromContext for AppContext {
+    fn from_context(ctx : &AppContext) -> Self { ctx.clone() }
+}
+
+
+/// Shared state added to an `App` with [`App::with_state`](crate::App::with_state).
+///
+/// Callbacks can get the state by taking a `State<T>` argument after the arguments of their event:
+/// ```ignore
+/// struct Game { players : u32 }
+///
+/// async fn player_joined(player : Player, game : State<Game>) {
+///     game.borrow_mut().players += 1;
+/// }
+/// ```
+///
+/// A `State` can be cloned and held across `.await`s, but borrows of the state should not be.
+///  Borrowing the state mutably while it is already borrowed elsewhere panics.
+pub struct State<T : 'static> {
+    inner : Rc<RefCell<T>>
+}
+
+impl<T : 'static> State<T> {
+
+    pub(super) fn new(inner : Rc<RefCell<T>>) -> Self { Self { inner } }
+
+    /// Returns the state of type `T` of the `App` which is currently running.
+    ///
+    /// ### Panics
+    /// Panics if called outside of [`App::run`](crate::App::run), or if no state of type `T` was added.
+    pub fn current() -> Self {
+        let ctx = AppContext::current().expect("`State::current` called outside of `App::run`");
+        Self::from_context(&ctx)
+    }
+
+    /// Immutably borrows the state.
+    ///
+    /// ### Panics
+    /// Panics if the state is currently mutably borrowed.
+    #[track_caller]
+    pub fn borrow(&self) -> Ref<'_, T> { self.inner.borrow() }
+
+    /// Mutably borrows the state.
+    ///
+    /// ### Panics
+    /// Panics if the state is currently borrowed.
+    #[track_caller]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> { self.inner.borrow_mut() }
+
+}
+
+impl<T : 'static> Clone for State<T> {
+    fn clone(&self) -> Self { Self { inner : Rc::clone(&self.inner) } }
+}
+
+impl<T : fmt::Debug + 'static> fmt::Debug for State<T> {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("State").field(&self.inner).finish()
+    }
+}
+
+impl<T : 'static> FromContext for State<T> {
+    #[track_caller]
+    fn from_context(ctx : &AppContext) -> Self {
+        ctx.state().unwrap_or_else(|| panic!("no `{}` state was added to the `App`", type_name::<T>()))
+    }
+}
+
+
+/// A value which a callback can return.
+pub trait HandlerOutput : 'static {
+
+    /// Converts this value into the error message of the callback, if it failed.
+    fn into_result(self) -> Result<(), String>;
+
+}
+
+impl HandlerOutput for () {
+    #[inline]
+    fn into_result(self) -> Result<(), String> { Ok(()) }
+}
+
+impl<E : fmt::Display + 'static> HandlerOutput for Result<(), E> {
+    fn into_result(self) -> Result<(), String> { self.map_err(|err| err.to_string()) }
+}
+
+
+/// An error returned by a callback.
+///
+/// Errors are passed to the [`App::on_error`](crate::App::on_error) hook, or logged
+///  if there is none.
+#[derive(Clone, Debug)]
+pub struct HandlerError {
+    event   : Rc<str>,
+    message : String
+}
+
+impl HandlerError {
+
+    pub(crate) fn new(event : Rc<str>, message : String) -> Self { Self { event, message } }
+
+    /// Returns the ID of the event which the callback was registered for.
+    ///
+    /// For operations which the host could not carry out, such as a
+    ///  [`RegionOp::Replace`](crate::game::data::RegionOp::Replace) on a host without
+    ///  [`Capabilities::WORLD_READ`](crate::game::Capabilities::WORLD_READ), this is the
+    ///  missing capability instead.
+    pub fn event(&self) -> &str { &self.event }
+
+    /// Returns the error message.
+    pub fn message(&self) -> &str { &self.message }
+
+}
+
+impl fmt::Display for HandlerError {
+    fn fmt(&self, f : &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} callback failed: {}", self.event, self.message)
+    }
+}
+
+impl Error for HandlerError { }
+
+
+/// A callback for an event with arguments `Args`.
+///
+/// This is implemented for every `async fn` (or closure returning a future) which takes the
+///  arguments of the event, followed by up to four [`FromContext`] arguments such as [`State`].
+///  The callback can return anything implementing [`HandlerOutput`], such as `()` or `Result<(), E>`.
+///  `Marker` only tells these implementations apart, and is always inferred.
+///
+/// Closures need their argument types written out, as they can not be inferred through this trait.
+pub trait Handler<Args : Tuple, Marker> : 'static {
+
+    /// Calls this callback, extracting any extra arguments from `ctx`.
+    ///
+    /// The returned future resolves to the error message of the callback, if it failed.
+    fn call(&self, ctx : &AppContext, args : Args) -> Pin<Box<dyn Future<Output = Result<(), String>>>>;
+
+}
+
+macro handler_impl( ( $( $arg:ident ),* ) ( $( $extra:ident ),* ) ) {
+    impl<F, Fut, $( $arg , )* $( $extra , )*> Handler<( $( $arg , )* ), ( $( $extra , )* )> for F
+    where
+        F           : (Fn( $( $arg , )* $( $extra , )* ) -> Fut) + 'static,
+        Fut         : Future + 'static,
+        Fut::Output : HandlerOutput,
+        $( $extra : FromContext , )*
+    {
+        #[allow(non_snake_case, unused_variables)]
+        fn call(&self, ctx : &AppContext, ( $( $arg , )* ) : ( $( $arg , )* )) -> Pin<Box<dyn Future<Output = Result<(), String>>>> {
+            let fut = self( $( $arg , )* $( $extra::from_context(ctx) , )* );
+            Box::pin(async move { fut.await.into_result() })
+        }
+    }
+}
+
+macro handler_impls( $( $args:tt )* ) {
+    $(
+        handler_impl!{ $args () }
+        handler_impl!{ $args (X1) }
+        handler_impl!{ $args (X1, X2) }
+        handler_impl!{ $args (X1, X2, X3) }
+        handler_impl!{ $args (X1, X2, X3, X4) }
+    )*
+}
+
+handler_impls!{ () (A1) (A1, A2) }
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use crate::prelude::*;
+    use crate::testkit::{ self, HostCall };
+
+    struct Counter(u32);
+
+    #[test]
+    fn state_is_shared_between_callbacks() {
+        let mut app = App::new();
+        app.with_state(Counter(0)).with_state("name");
+        let ctx = app.context();
+        ctx.on_player_joined(|player : Player, counter : State<Counter>, name : State<&'static str>| async move {
+            counter.borrow_mut().0 += 1;
+            player.send_chat(format!("{} {}", *name.borrow(), counter.borrow().0));
+        });
+        ctx.on_player_left(|player : Player, _ : AppContext, counter : State<Counter>| async move {
+            counter.borrow_mut().0 -= 1;
+            player.send_chat(format!("{}", State::<Counter>::current().borrow().0));
+        });
+        let a = testkit::player_joined(Uuid::nil(), "a");
+        let b = testkit::player_joined(Uuid::max(), "b");
+        testkit::player_left(a);
+        app.run();
+        assert_eq!(testkit::take_calls(), [
+            HostCall::Chat { session_id : a.session_id(), msg : "name 1".to_string() },
+            HostCall::Chat { session_id : b.session_id(), msg : "name 2".to_string() },
+            HostCall::Chat { session_id : a.session_id(), msg : "1".to_string() }
+        ]);
+        assert_eq!(ctx.state::<Counter>().unwrap().borrow().0, 1);
+    }
+
+    #[test]
+    fn replaced_state_is_not_shared() {
+        let ctx = App::new().context();
+        assert!(ctx.state::<Counter>().is_none());
+        ctx.set_state(Counter(1));
+        let old = ctx.state::<Counter>().unwrap();
+        ctx.set_state(Counter(2));
+        old.borrow_mut().0 += 10;
+        assert_eq!(old.borrow().0, 11);
+        assert_eq!(ctx.state::<Counter>().unwrap().borrow().0, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "tests::Counter` state was added to the `App`")]
+    fn missing_state_panics() {
+        let mut app = App::new();
+        app.context().on_player_joined(|_ : Player, _ : State<Counter>| async { });
+        testkit::player_joined(Uuid::nil(), "a");
+        app.run();
+    }
+
+}
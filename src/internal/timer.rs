@@ -0,0 +1,84 @@
+use crate::time::Instant;
+use core::cell::RefCell;
+use core::task::Waker;
+use std::collections::BTreeMap;
+
+
+thread_local! {
+    static TIMERS : RefCell<Timers> = RefCell::new(Timers::default());
+}
+
+#[derive(Default)]
+struct Timers {
+    next_id  : u64,
+    /// Every pending timer, ordered by deadline.
+    pending  : BTreeMap<TimerKey, Waker>,
+    /// The time at which expired timers were last fired.
+    last_now : Option<Instant>
+}
+
+/// A timer registered with [`register`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TimerKey {
+    deadline : Instant,
+    id       : u64
+}
+
+
+/// Returns `true` if `deadline` is known to have passed, without asking the host for the time.
+pub(crate) fn is_known_expired(deadline : Instant) -> bool {
+    TIMERS.with_borrow(|timers| timers.last_now.is_some_and(|now| now >= deadline))
+}
+
+/// Registers a timer which will wake `waker` once `deadline` passes.
+pub(crate) fn register(deadline : Instant, waker : &Waker) -> TimerKey {
+    TIMERS.with_borrow_mut(|timers| {
+        let key = TimerKey { deadline, id : timers.next_id };
+        timers.next_id += 1;
+        timers.pending.insert(key, waker.clone());
+        key
+    })
+}
+
+/// Updates the waker of a registered timer.
+///
+/// Returns `false` if the timer has already fired.
+pub(crate) fn update(key : TimerKey, waker : &Waker) -> bool {
+    TIMERS.with_borrow_mut(|timers| {
+        let Some(old) = timers.pending.get_mut(&key) else { return false; };
+        if (! old.will_wake(waker)) {
+            *old = waker.clone();
+        }
+        true
+    })
+}
+
+/// Removes a registered timer without firing it.
+pub(crate) fn cancel(key : TimerKey) {
+    // This can run while the thread is being torn down, after `TIMERS` is already gone.
+    // The waker is dropped outside of the borrow, as it could own a task.
+    let waker = TIMERS.try_with(|timers| timers.borrow_mut().pending.remove(&key));
+    drop(waker);
+}
+
+
+/// Wakes every timer whose deadline has passed.
+///
+/// The host is only asked for the time if there are any pending timers.
+pub(crate) fn fire_expired() {
+    if (TIMERS.with_borrow(|timers| timers.pending.is_empty())) { return; }
+    let now     = Instant::now();
+    let expired = TIMERS.with_borrow_mut(|timers| {
+        timers.last_now = Some(now);
+        let later = timers.pending.split_off(&TimerKey { deadline : now, id : u64::MAX });
+        core::mem::replace(&mut timers.pending, later)
+    });
+    for (_, waker,) in expired {
+        waker.wake();
+    }
+}
+
+/// Returns the deadline of the earliest pending timer.
+pub(crate) fn next_deadline() -> Option<Instant> {
+    TIMERS.with_borrow(|timers| timers.pending.first_key_value().map(|(key, _,)| key.deadline))
+}
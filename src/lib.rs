@@ -4,20 +4,22 @@
 #![feature(
     // Language
     decl_macro,
-    never_type,
     unboxed_closures,
     // Standard Library,
-    fn_traits,
     tuple_trait,
     // Documentation
     doc_cfg
 )]
 
 
+pub use flywheel_sys_macros::main;
+
 mod internal;
-pub use internal::App;
+pub use internal::{ App, AppContext, Listener, ListenerSet, State, FromContext, Handler, HandlerOutput, HandlerError };
+pub use internal::alloc;
 
 pub mod task;
+pub mod event;
 
 pub mod game;
 
@@ -28,17 +30,30 @@ pub use uuid;
 #[cfg(any(doc, feature = "selfhosted"))]
 mod selfhosted;
 
+#[cfg(any(doc, feature = "testkit"))]
+#[doc(cfg(feature = "testkit"))]
+pub mod testkit;
+
+
+#[doc(hidden)]
+pub mod __private {
+    /// Sets up the plot before the entry point runs. Used by [`main`](crate::main).
+    pub fn init() {
+        crate::internal::panic::install_hook();
+    }
+}
+
 
 /// Commonly used items.
 pub mod prelude {
-    pub use super::internal::App;
+    pub use super::internal::{ App, AppContext, ListenerSet, State };
 
     pub use super::task;
 
     pub use super::game::prelude::*;
 
     pub use super::time::{ Duration, DurationExt, Instant };
-    #[cfg(any(doc, feature = "chrono"))]
+    #[cfg(feature = "chrono")]
     #[doc(cfg(feature = "chrono"))]
     pub use chrono;
     pub use super::rand::{ self, GetRandom };
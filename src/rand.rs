@@ -1,18 +1,19 @@
 //! Random value generators.
 
 
+use crate::internal::alloc::Out;
 use crate::uuid::Uuid;
 
 
 unsafe extern "C" {
-    unsafe fn flywheel_rand_bool(out_u8 : u32);
-    unsafe fn flywheel_rand_u8(out_u8 : u32);
-    unsafe fn flywheel_rand_u16(out_u16 : u32);
-    unsafe fn flywheel_rand_u32(out_u32 : u32);
-    unsafe fn flywheel_rand_u64(out_u64 : u32);
-    unsafe fn flywheel_rand_u128(out_u128 : u32);
-    unsafe fn flywheel_rand_f32(out_f32 : u32);
-    unsafe fn flywheel_rand_f64(out_f64 : u32);
+    unsafe fn flywheel_rand_bool(out_u8 : usize);
+    unsafe fn flywheel_rand_u8(out_u8 : usize);
+    unsafe fn flywheel_rand_u16(out_u16 : usize);
+    unsafe fn flywheel_rand_u32(out_u32 : usize);
+    unsafe fn flywheel_rand_u64(out_u64 : usize);
+    unsafe fn flywheel_rand_u128(out_u128 : usize);
+    unsafe fn flywheel_rand_f32(out_f32 : usize);
+    unsafe fn flywheel_rand_f64(out_f64 : usize);
 }
 
 
@@ -26,17 +27,17 @@ pub trait GetRandom {
 
 impl GetRandom for bool {
     fn random() -> Self {
-        let mut out = 0u8;
-        unsafe { flywheel_rand_bool((&mut out) as (*mut _) as u32); }
-        u8::from_le(out) != 0
+        let mut out = Out::<u8>::new();
+        unsafe { flywheel_rand_bool(out.ptr()); }
+        out.get() != 0
     }
 }
 
 impl GetRandom for u8 {
     fn random() -> Self {
-        let mut out = 0u8;
-        unsafe { flywheel_rand_u8((&mut out) as (*mut _) as u32); }
-        Self::from_le(out)
+        let mut out = Out::<u8>::new();
+        unsafe { flywheel_rand_u8(out.ptr()); }
+        out.get()
     }
 }
 impl GetRandom for i8 {
@@ -45,9 +46,9 @@ impl GetRandom for i8 {
 
 impl GetRandom for u16 {
     fn random() -> Self {
-        let mut out = 0u16;
-        unsafe { flywheel_rand_u16((&mut out) as (*mut _) as u32); }
-        Self::from_le(out)
+        let mut out = Out::<u16>::new();
+        unsafe { flywheel_rand_u16(out.ptr()); }
+        out.get()
     }
 }
 impl GetRandom for i16 {
@@ -56,9 +57,9 @@ impl GetRandom for i16 {
 
 impl GetRandom for u32 {
     fn random() -> Self {
-        let mut out = 0u32;
-        unsafe { flywheel_rand_u32((&mut out) as (*mut _) as u32); }
-        Self::from_le(out)
+        let mut out = Out::<u32>::new();
+        unsafe { flywheel_rand_u32(out.ptr()); }
+        out.get()
     }
 }
 impl GetRandom for i32 {
@@ -67,9 +68,9 @@ impl GetRandom for i32 {
 
 impl GetRandom for u64 {
     fn random() -> Self {
-        let mut out = 0u64;
-        unsafe { flywheel_rand_u64((&mut out) as (*mut _) as u32); }
-        Self::from_le(out)
+        let mut out = Out::<u64>::new();
+        unsafe { flywheel_rand_u64(out.ptr()); }
+        out.get()
     }
 }
 impl GetRandom for i64 {
@@ -78,9 +79,9 @@ impl GetRandom for i64 {
 
 impl GetRandom for u128 {
     fn random() -> Self {
-        let mut out = 0u128;
-        unsafe { flywheel_rand_u128((&mut out) as (*mut _) as u32); }
-        Self::from_le(out)
+        let mut out = Out::<u128>::new();
+        unsafe { flywheel_rand_u128(out.ptr()); }
+        out.get()
     }
 }
 impl GetRandom for i128 {
@@ -89,17 +90,17 @@ impl GetRandom for i128 {
 
 impl GetRandom for f32 {
     fn random() -> Self {
-        let mut out = 0u32;
-        unsafe { flywheel_rand_f32((&mut out) as (*mut _) as u32); }
-        Self::from_bits(u32::from_le(out))
+        let mut out = Out::<u32>::new();
+        unsafe { flywheel_rand_f32(out.ptr()); }
+        Self::from_bits(out.get())
     }
 }
 
 impl GetRandom for f64 {
     fn random() -> Self {
-        let mut out = 0u64;
-        unsafe { flywheel_rand_f64((&mut out) as (*mut _) as u32); }
-        Self::from_bits(u64::from_le(out))
+        let mut out = Out::<u64>::new();
+        unsafe { flywheel_rand_f64(out.ptr()); }
+        Self::from_bits(out.get())
     }
 }
 
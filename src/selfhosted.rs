@@ -1,16 +1,10 @@
 use crate::prelude::*;
+use crate::game::optional_imports;
+use crate::internal::log::{ log, Level };
 
 
-#[allow(dead_code)]
-unsafe extern "C" {
-    pub unsafe fn flywheel_system_set_motd(in_motd : u32, motd_len : u32);
-    pub unsafe fn flywheel_trace(in_msg : u32, msg_len : u32);
-    pub unsafe fn flywheel_debug(in_msg : u32, msg_len : u32);
-    pub unsafe fn flywheel_info(in_msg : u32, msg_len : u32);
-    pub unsafe fn flywheel_pass(in_msg : u32, msg_len : u32);
-    pub unsafe fn flywheel_warn(in_msg : u32, msg_len : u32);
-    pub unsafe fn flywheel_error(in_msg : u32, msg_len : u32);
-    pub unsafe fn flywheel_fatal(in_msg : u32, msg_len : u32);
+optional_imports! {
+    fn flywheel_system_set_motd(in_motd : usize, motd_len : u32);
 }
 
 
@@ -18,8 +12,11 @@ impl Server {
 
     /// Changes the server list MOTD text.
     #[doc(cfg(feature = "selfhosted"))]
+    ///
+    /// Does nothing if the host does not support [`Capabilities::SELFHOSTED`](crate::game::Capabilities::SELFHOSTED).
     pub fn set_motd(motd : &str) {
-        unsafe { flywheel_system_set_motd(motd.as_ptr() as u32, motd.len() as u32); }
+        let Some(set_motd) = flywheel_system_set_motd() else { return; };
+        unsafe { set_motd(motd.as_ptr() as usize, motd.len() as u32); }
     }
 
 }
@@ -31,7 +28,7 @@ impl Server {
 pub macro trace( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_trace(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Trace, &msg);
 } }
 
 /// Logs a message to the console at the `DEBUG` level.
@@ -41,7 +38,7 @@ pub macro trace( $( $fmt:tt )* ) { {
 pub macro debug( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_debug(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Debug, &msg);
 } }
 
 /// Logs a message to the console at the `INFO` level.
@@ -51,7 +48,7 @@ pub macro debug( $( $fmt:tt )* ) { {
 pub macro info( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_info(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Info, &msg);
 } }
 
 /// Logs a message to the console at the `PASS` level.
@@ -61,7 +58,7 @@ pub macro info( $( $fmt:tt )* ) { {
 pub macro pass( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_pass(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Pass, &msg);
 } }
 
 /// Logs a message to the console at the `WARN` level.
@@ -71,7 +68,7 @@ pub macro pass( $( $fmt:tt )* ) { {
 pub macro warn( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_warn(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Warn, &msg);
 } }
 
 /// Logs a message to the console at the `ERROR` level.
@@ -81,7 +78,7 @@ pub macro warn( $( $fmt:tt )* ) { {
 pub macro error( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_error(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Error, &msg);
 } }
 
 /// Logs a message to the console at the `FATAL` level.
@@ -91,5 +88,29 @@ pub macro error( $( $fmt:tt )* ) { {
 pub macro fatal( $( $fmt:tt )* ) { {
     let msg = format!( $( $fmt )* );
     let msg = format!("[{}:{}:{}] {msg}", module_path!(), line!(), column!());
-    unsafe { flywheel_fatal(msg.as_ptr() as u32, msg.len() as u32); }
+    log(Level::Fatal, &msg);
 } }
+
+
+#[cfg(all(test, feature = "testkit"))]
+mod tests {
+    use crate::prelude::*;
+    use crate::game::Capabilities;
+    use crate::testkit::{ self, HostCall, LogLevel };
+
+    #[test]
+    fn selfhosted_functions_are_capabilities() {
+        Server::set_motd("Hello");
+        info!("ready");
+        assert!(matches!(&testkit::take_calls()[..], [
+            HostCall::SetMotd { motd },
+            HostCall::Log { level : LogLevel::Info, msg }
+        ] if motd == "Hello" && msg.ends_with("] ready")));
+
+        testkit::set_capabilities([Capabilities::IDLE]);
+        Server::set_motd("Hello");
+        info!("ready");
+        assert_eq!(testkit::take_calls(), []);
+    }
+
+}
@@ -1,6 +1,7 @@
 //! Task and future related utilities.
 
 
+use crate::internal::timer::{ self, TimerKey };
 use crate::time::Instant;
 use core::pin::Pin;
 use core::task::{ Context, Poll };
@@ -8,7 +9,7 @@ use core::time::Duration;
 use pin_project::pin_project;
 
 
-pub use wasm_rs_async_executor::single_threaded::{ spawn, TaskHandle, Task, JoinError };
+pub use crate::internal::executor::{ spawn, spawn_in, TaskScope, TaskHandle, Task, JoinError };
 
 
 /// Pauses the current task for some duration.
@@ -16,29 +17,51 @@ pub use wasm_rs_async_executor::single_threaded::{ spawn, TaskHandle, Task, Join
 /// Execution might not be paused for the exact duration given,
 ///  but it will never unpause before the duration expires.
 #[inline]
-pub fn sleep(duration : Duration) -> Sleep { Sleep { timeout : Instant::now() + duration } }
+pub fn sleep(duration : Duration) -> Sleep { sleep_until(Instant::now() + duration) }
 
 /// Pauses the current task until some point in time.
 ///
 /// Execution might not be paused until the exact point in time given,
 ///  but it will never unpause before the timeout expires.
 #[inline]
-pub fn sleep_until(timeout : Instant) -> Sleep { Sleep { timeout } }
+pub fn sleep_until(timeout : Instant) -> Sleep { Sleep { timeout, timer : None } }
 
 /// A `Future` which waits until a specific point in time.
 ///
+/// A `Sleep` does not ask the host for the time when polled. Instead, it registers a timer
+///  with the scheduler, which wakes the task once the timeout passes.
+///
 /// See [`sleep`] and [`sleep_until`].
 pub struct Sleep {
-    timeout : Instant
+    timeout : Instant,
+    timer   : Option<TimerKey>
+}
+impl Sleep {
+    /// Returns the point in time this `Sleep` waits until.
+    #[inline]
+    pub fn deadline(&self) -> Instant { self.timeout }
 }
 impl Future for Sleep {
     type Output = ();
-    fn poll(self : Pin<&mut Self>, _ctx : &mut Context<'_>) -> Poll<Self::Output> {
-        if (Instant::now() >= self.timeout) {
-            Poll::Ready(())
-        } else {
-            //ctx.waker().wake_by_ref();
-            Poll::Pending
+    fn poll(mut self : Pin<&mut Self>, ctx : &mut Context<'_>) -> Poll<Self::Output> {
+        match (self.timer) {
+            Some(key) => {
+                if (timer::update(key, ctx.waker())) { return Poll::Pending; }
+                self.timer = None;
+                Poll::Ready(())
+            },
+            None => {
+                if (timer::is_known_expired(self.timeout)) { return Poll::Ready(()); }
+                self.timer = Some(timer::register(self.timeout, ctx.waker()));
+                Poll::Pending
+            }
+        }
+    }
+}
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.timer.take() {
+            timer::cancel(key);
         }
     }
 }
@@ -52,7 +75,7 @@ impl Future for Sleep {
 pub fn timeout<F, T>(duration : Duration, fut : F) -> Timeout<F, T>
 where
     F : Future<Output = T>
-{ Timeout { timeout : Instant::now() + duration, fut } }
+{ timeout_at(Instant::now() + duration, fut) }
 
 /// Polls a `Future` until some point in time.
 ///
@@ -62,7 +85,7 @@ where
 pub fn timeout_at<F, T>(timeout : Instant, fut : F) -> Timeout<F, T>
 where
     F : Future<Output = T>
-{ Timeout { timeout, fut } }
+{ Timeout { sleep : sleep_until(timeout), fut } }
 
 /// A `Future` which polls another `Future` until some specific point in time.
 ///
@@ -72,9 +95,9 @@ pub struct Timeout<F, T>
 where
     F : Future<Output = T>
 {
-    timeout : Instant,
+    sleep : Sleep,
     #[pin]
-    fut     :  F
+    fut   : F
 }
 
 /// An error returned by [`Timeout`] if the `Future` exceeded the maximum execution time.
@@ -88,13 +111,13 @@ where
     F : Future<Output = T>
 {
     type Output = Result<T, TimeoutError>;
-    fn poll(mut self : Pin<&mut Self>, ctx : &mut Context<'_>) -> Poll<Self::Output> {
-        match (self.as_mut().project().fut.poll(ctx)) {
+    fn poll(self : Pin<&mut Self>, ctx : &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match (this.fut.poll(ctx)) {
             Poll::Ready(out) => Poll::Ready(Ok(out)),
-            Poll::Pending => {
-                if (Instant::now() >= self.timeout) {
-                    Poll::Ready(Err(TimeoutError { at : self.timeout }))
-                } else { Poll::Pending }
+            Poll::Pending    => {
+                let at = this.sleep.deadline();
+                Pin::new(this.sleep).poll(ctx).map(|_| Err(TimeoutError { at }))
             }
         }
     }
@@ -0,0 +1,518 @@
+//! Native definitions of every host function imported by this crate, and of every optional
+//!  function provided with a capability.
+
+
+use super::{ HOST, HostCall, LogLevel };
+use crate::game::Capabilities;
+use crate::game::data::{ ChunkPos, BlockPos, SoundCategory, Block, BlockBox, BlockFilter, RegionOp };
+use crate::time::{ Duration, DurationExt };
+use core::{ ptr, slice };
+use std::collections::BTreeSet;
+
+
+/// Copies a string out of plot memory.
+unsafe fn read_str(in_ptr : usize, len : u32) -> String {
+    let bytes = unsafe { slice::from_raw_parts(in_ptr as (*const u8), len as usize) };
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Writes a value to an out-pointer in plot memory.
+unsafe fn write_out<T>(out_ptr : usize, value : T) {
+    unsafe { ptr::write_unaligned(out_ptr as (*mut T), value); }
+}
+
+/// Hands a buffer over to the plot, which takes ownership of it.
+fn leak_buf(buf : Vec<u8>) -> (usize, u32,) {
+    let buf = Box::into_raw(buf.into_boxed_slice());
+    (buf as (*mut u8) as usize, buf.len() as u32,)
+}
+
+
+/// Sequentially reads little-endian values out of plot memory.
+struct Reader(*const u8);
+impl Reader {
+    unsafe fn bytes<const N : usize>(&mut self) -> [u8; N] {
+        let out = unsafe { ptr::read_unaligned(self.0 as (*const [u8; N])) };
+        self.0 = unsafe { self.0.add(N) };
+        out
+    }
+    unsafe fn u8(&mut self) -> u8 { u8::from_le_bytes(unsafe { self.bytes() }) }
+    unsafe fn u32(&mut self) -> u32 { u32::from_le_bytes(unsafe { self.bytes() }) }
+    unsafe fn u64(&mut self) -> u64 { u64::from_le_bytes(unsafe { self.bytes() }) }
+    unsafe fn i64(&mut self) -> i64 { i64::from_le_bytes(unsafe { self.bytes() }) }
+    unsafe fn str(&mut self) -> String {
+        let len = unsafe { self.u32() };
+        let out = unsafe { read_str(self.0 as usize, len) };
+        self.0 = unsafe { self.0.add(len as usize) };
+        out
+    }
+    unsafe fn varint(&mut self) -> u64 {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = unsafe { self.u8() };
+            value |= ((byte & 0x7F) as u64) << shift;
+            if (byte & 0x80 == 0) { return value; }
+            shift += 7;
+        }
+    }
+    unsafe fn zigzag(&mut self) -> i64 {
+        let value = unsafe { self.varint() };
+        ((value >> 1) as i64) ^ -((value & 1) as i64)
+    }
+    unsafe fn varint_str(&mut self) -> String {
+        let len = unsafe { self.varint() } as u32;
+        let out = unsafe { read_str(self.0 as usize, len) };
+        self.0 = unsafe { self.0.add(len as usize) };
+        out
+    }
+    unsafe fn varint_block(&mut self) -> Block {
+        let mut block = Block::new(&unsafe { self.varint_str() });
+        for _ in 0..unsafe { self.u8() } {
+            let state = unsafe { self.varint_str() };
+            let value = unsafe { self.varint_str() };
+            block.set(&state, &value);
+        }
+        block
+    }
+}
+
+
+#[unsafe(no_mangle)]
+extern "C" fn flywheel_refuel() { }
+
+extern "C" fn flywheel_idle(_deadline_secs : u64, _deadline_nanos : u32) { }
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_next_event(out_id_ptr : usize, out_id_len : usize, out_args_ptr : usize, out_args_len : usize) -> u32 {
+    let Some((id, args,)) = HOST.with_borrow_mut(|host| host.events.pop_front()) else { return 0; };
+    let (id_ptr, id_len,)     = leak_buf(id.into_bytes());
+    let (args_ptr, args_len,) = leak_buf(args);
+    unsafe {
+        write_out(out_id_ptr, id_ptr);
+        write_out(out_id_len, id_len);
+        write_out(out_args_ptr, args_ptr);
+        write_out(out_args_len, args_len);
+    }
+    1
+}
+
+
+/// Returns the optional functions which are provided with the capability `name`.
+fn capability_functions(name : &str) -> Vec<(&'static str, usize,)> {
+    match (name) {
+        Capabilities::SELFHOSTED     => vec![
+            ("flywheel_system_set_motd", flywheel_system_set_motd as (*const ()) as usize,),
+            ("flywheel_trace", flywheel_trace as (*const ()) as usize,),
+            ("flywheel_debug", flywheel_debug as (*const ()) as usize,),
+            ("flywheel_info", flywheel_info as (*const ()) as usize,),
+            ("flywheel_pass", flywheel_pass as (*const ()) as usize,),
+            ("flywheel_warn", flywheel_warn as (*const ()) as usize,),
+            ("flywheel_error", flywheel_error as (*const ()) as usize,),
+            ("flywheel_fatal", flywheel_fatal as (*const ()) as usize,)
+        ],
+        Capabilities::PALETTE_BLOCKS => vec![ ("flywheel_world_set_blocks_palette", flywheel_world_set_blocks_palette as (*const ()) as usize,) ],
+        Capabilities::REGION_OPS     => vec![ ("flywheel_world_region_ops", flywheel_world_region_ops as (*const ()) as usize,) ],
+        Capabilities::IDLE           => vec![ ("flywheel_idle", flywheel_idle as (*const ()) as usize,) ],
+        Capabilities::CHAT_RESOLVE   => vec![ ("flywheel_player_chat_resolve", flywheel_player_chat_resolve as (*const ()) as usize,) ],
+        Capabilities::COMMANDS       => vec![ ("flywheel_player_set_commands", flywheel_player_set_commands as (*const ()) as usize,) ],
+        Capabilities::WORLD_READ     => vec![
+            ("flywheel_world_get_block", flywheel_world_get_block as (*const ()) as usize,),
+            ("flywheel_world_snapshot", flywheel_world_snapshot as (*const ()) as usize,)
+        ],
+        Capabilities::PLAYER_LOOKUP  => vec![ ("flywheel_player_from_name", flywheel_player_from_name as (*const ()) as usize,) ],
+        _                            => Vec::new()
+    }
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_server_capabilities(out_ptr : usize, out_len : usize) {
+    let mut buf = Vec::new();
+    HOST.with_borrow(|host| for name in &host.capabilities {
+        buf.extend((name.len() as u32).to_le_bytes());
+        buf.extend(name.as_bytes());
+        let functions = capability_functions(name);
+        buf.extend((functions.len() as u32).to_le_bytes());
+        for (function, ptr,) in functions {
+            buf.extend((function.len() as u32).to_le_bytes());
+            buf.extend(function.as_bytes());
+            buf.extend((ptr as u64).to_le_bytes());
+        }
+    });
+    let (ptr, len,) = leak_buf(buf);
+    unsafe {
+        write_out(out_ptr, ptr);
+        write_out(out_len, len);
+    }
+}
+
+
+#[unsafe(no_mangle)]
+extern "C" fn flywheel_player_exists(session_id : u64) -> u32 {
+    HOST.with_borrow(|host| host.players.contains_key(&session_id)) as u32
+}
+
+unsafe extern "C" fn flywheel_player_from_name(in_name : usize, name_len : u32, out_session_id : usize) -> u32 {
+    let name = unsafe { read_str(in_name, name_len) };
+    let Some(session_id) = HOST.with_borrow(|host| host.players.iter()
+        .find(|(_, (_, player_name,),)| player_name.eq_ignore_ascii_case(&name))
+        .map(|(session_id, _,)| *session_id)
+    ) else { return 0; };
+    unsafe { write_out(out_session_id, session_id); }
+    1
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_profile_from_session(session_id : u64, out_uuid : usize, out_name_ptr : usize, out_name_len : usize) -> u32 {
+    let Some((uuid, name,)) = HOST.with_borrow(|host| host.players.get(&session_id).cloned()) else { return 0; };
+    let (name_ptr, name_len,) = leak_buf(name.into_bytes());
+    unsafe {
+        write_out(out_uuid, uuid.to_u128_le());
+        write_out(out_name_ptr, name_ptr);
+        write_out(out_name_len, name_len);
+    }
+    1
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_player_send_chat(session_id : u64, in_msg : usize, msg_len : u32) {
+    let msg = unsafe { read_str(in_msg, msg_len) };
+    HOST.with_borrow_mut(|host| host.record(HostCall::Chat { session_id, msg }));
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_player_send_actionbar(session_id : u64, in_msg : usize, msg_len : u32) {
+    let msg = unsafe { read_str(in_msg, msg_len) };
+    HOST.with_borrow_mut(|host| host.record(HostCall::Actionbar { session_id, msg }));
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_player_send_title(
+    session_id   : u64,
+    in_title     : usize,
+    title_len    : u32,
+    in_subtitle  : usize,
+    subtitle_len : u32,
+    fade_in      : u32,
+    stay         : u32,
+    fade_out     : u32
+) {
+    let title    = unsafe { read_str(in_title, title_len) };
+    let subtitle = unsafe { read_str(in_subtitle, subtitle_len) };
+    HOST.with_borrow_mut(|host| host.record(HostCall::Title {
+        session_id, title, subtitle,
+        fade_in  : Duration::from_ticks(fade_in),
+        stay     : Duration::from_ticks(stay),
+        fade_out : Duration::from_ticks(fade_out)
+    }));
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_player_send_sound(
+    session_id : u64,
+    in_id      : usize,
+    id_len     : u32,
+    category   : u32,
+    volume     : f32,
+    pitch      : f32,
+    seed       : u64
+) {
+    let id       = unsafe { read_str(in_id, id_len) };
+    let category = match (category) {
+        0 => SoundCategory::Master,
+        1 => SoundCategory::Music,
+        2 => SoundCategory::Records,
+        3 => SoundCategory::Weather,
+        4 => SoundCategory::Blocks,
+        5 => SoundCategory::Hostile,
+        6 => SoundCategory::Neutral,
+        7 => SoundCategory::Players,
+        8 => SoundCategory::Ambient,
+        9 => SoundCategory::Voice,
+        _ => panic!("unknown sound category {category}")
+    };
+    HOST.with_borrow_mut(|host| host.record(HostCall::Sound { session_id, id, category, volume, pitch, seed }));
+}
+
+unsafe extern "C" fn flywheel_player_set_commands(session_id : u64, in_tree : usize, _tree_len : u32) {
+    let mut data  = Reader(in_tree as (*const u8));
+    let     count = unsafe { data.u32() };
+    let mut nodes = Vec::with_capacity(count as usize);
+    for _ in 0..count { unsafe {
+        let kind       = data.u8();
+        let executable = data.u8() != 0;
+        let children   = (0..data.u32()).map(|_| data.u32()).collect::<Vec<_>>();
+        let name       = if (kind == 0) { String::new() } else { data.str() };
+        if (kind == 2) {
+            data.str();
+            let properties = data.u32();
+            data.0 = data.0.add(properties as usize);
+            for _ in 0..data.u32() { data.str(); }
+        }
+        nodes.push((kind, executable, children, name,));
+    } }
+    let mut usage = Vec::new();
+    if let Some((_, _, roots, _,)) = nodes.first() {
+        for &root in roots {
+            command_usage(&nodes, root as usize, &mut format!("/{}", nodes[root as usize].3), &mut usage);
+        }
+    }
+    HOST.with_borrow_mut(|host| host.record(HostCall::SetCommands { session_id, usage }));
+}
+
+/// Lists each way a decoded command node can be run, in the same format as `Command::usage`.
+fn command_usage(nodes : &[(u8, bool, Vec<u32>, String,)], index : usize, prefix : &mut String, out : &mut Vec<String>) {
+    let (_, executable, children, _,) = &nodes[index];
+    if (*executable) {
+        out.push(prefix.clone());
+    }
+    for &child in children {
+        let (kind, _, _, name,) = &nodes[child as usize];
+        let len = prefix.len();
+        if (*kind == 2) {
+            prefix.push_str(&format!(" <{name}>"));
+        } else {
+            prefix.push_str(&format!(" {name}"));
+        }
+        command_usage(nodes, child as usize, prefix, out);
+        prefix.truncate(len);
+    }
+}
+
+unsafe extern "C" fn flywheel_player_chat_resolve(
+    session_id      : u64,
+    message_id      : u64,
+    flags           : u32,
+    in_msg          : usize,
+    msg_len         : u32,
+    in_recipients   : usize,
+    recipient_count : u32
+) {
+    let rewrite    = (flags & 0b010 != 0).then(|| unsafe { read_str(in_msg, msg_len) });
+    let recipients = (flags & 0b100 != 0).then(|| {
+        let mut data = Reader(in_recipients as (*const u8));
+        (0..recipient_count).map(|_| unsafe { data.u64() }).collect()
+    });
+    HOST.with_borrow_mut(|host| host.record(HostCall::ChatResolved {
+        session_id, message_id,
+        cancelled : flags & 0b001 != 0,
+        rewrite, recipients
+    }));
+}
+
+
+#[unsafe(no_mangle)]
+extern "C" fn flywheel_world_mark_ready(session_id : u64, x : i32, z : i32) {
+    HOST.with_borrow_mut(|host| host.record(HostCall::MarkReady { session_id, chunk : ChunkPos::new(x, z) }));
+}
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_world_set_blocks(session_id : u64, in_data : usize) {
+    let mut data   = Reader(in_data as (*const u8));
+    let     count  = unsafe { data.u32() };
+    let mut blocks = Vec::with_capacity(count as usize);
+    for _ in 0..count { unsafe {
+        let pos       = BlockPos::new(data.i64(), data.i64(), data.i64());
+        let mut block = Block::new(&data.str());
+        for _ in 0..data.u8() {
+            let state = data.str();
+            let value = data.str();
+            block.set(&state, &value);
+        }
+        blocks.push((pos, block,));
+    } }
+    record_set_blocks(session_id, blocks);
+}
+
+unsafe extern "C" fn flywheel_world_set_blocks_palette(session_id : u64, in_data : usize, _data_len : u32) {
+    let mut data    = Reader(in_data as (*const u8));
+    let palette  = (0..unsafe { data.varint() }).map(|_| unsafe { data.varint_block() }).collect::<Vec<_>>();
+    let mut blocks = Vec::new();
+    for _ in 0..unsafe { data.varint() } { unsafe {
+        let section = BlockPos::new(data.zigzag() * 16, data.zigzag() * 16, data.zigzag() * 16);
+        for _ in 0..data.varint() {
+            let start = u16::from_le_bytes(data.bytes());
+            let len   = data.varint() as u16;
+            let block = &palette[data.varint() as usize];
+            for local in start..(start + len) {
+                let local = local as i64;
+                blocks.push((BlockPos::new(section.x + (local & 15), section.y + (local >> 8), section.z + ((local >> 4) & 15)), block.clone(),));
+            }
+        }
+    } }
+    record_set_blocks(session_id, blocks);
+}
+
+unsafe extern "C" fn flywheel_world_region_ops(session_id : u64, in_data : usize, _data_len : u32) {
+    let mut data = Reader(in_data as (*const u8));
+    let mut ops  = Vec::new();
+    for _ in 0..unsafe { data.varint() } { unsafe {
+        let kind   = data.u8();
+        let region = BlockBox::new(
+            BlockPos::new(data.zigzag(), data.zigzag(), data.zigzag()),
+            BlockPos::new(data.zigzag(), data.zigzag(), data.zigzag())
+        );
+        ops.push(match (kind) {
+            0 => RegionOp::Fill { region, block : data.varint_block() },
+            1 => {
+                let filter = match (data.u8()) {
+                    0 => BlockFilter::Id(data.varint_str()),
+                    1 => BlockFilter::Exact(data.varint_block()),
+                    tag => panic!("unknown block filter {tag}")
+                };
+                RegionOp::Replace { region, filter, block : data.varint_block() }
+            },
+            2 => RegionOp::Hollow { region, block : data.varint_block() },
+            3 => RegionOp::Outline { region, block : data.varint_block() },
+            4 => RegionOp::Clone { src : region, dst : BlockPos::new(data.zigzag(), data.zigzag(), data.zigzag()) },
+            _ => panic!("unknown region operation {kind}")
+        });
+    } }
+    HOST.with_borrow_mut(|host| {
+        for op in &ops {
+            host.apply_region_op(session_id, op);
+        }
+        host.record(HostCall::RegionOps { session_id, ops });
+    });
+}
+
+fn record_set_blocks(session_id : u64, blocks : Vec<(BlockPos, Block,)>) {
+    HOST.with_borrow_mut(|host| {
+        let world = host.worlds.entry(session_id).or_default();
+        for (pos, block,) in &blocks {
+            world.insert(*pos, block.clone());
+        }
+        host.record(HostCall::SetBlocks { session_id, blocks });
+    });
+}
+
+/// Encodes a block in the same format as `EventArgs::block`.
+fn encode_block(out : &mut Vec<u8>, block : &Block) {
+    out.extend((block.id().len() as u32).to_le_bytes());
+    out.extend(block.id().as_bytes());
+    out.push(block.states_len() as u8);
+    for (state, value,) in block.states() {
+        out.extend((state.len() as u32).to_le_bytes());
+        out.extend(state.as_bytes());
+        out.extend((value.len() as u32).to_le_bytes());
+        out.extend(value.as_bytes());
+    }
+}
+
+unsafe extern "C" fn flywheel_world_get_block(session_id : u64, x : i64, y : i64, z : i64, out_ptr : usize, out_len : usize) -> u32 {
+    let Some(block) = HOST.with_borrow(|host| host.players.contains_key(&session_id).then(|| host.block(session_id, BlockPos::new(x, y, z)))) else { return 0; };
+    let mut buf = Vec::new();
+    encode_block(&mut buf, &block);
+    let (ptr, len,) = leak_buf(buf);
+    unsafe {
+        write_out(out_ptr, ptr);
+        write_out(out_len, len);
+    }
+    1
+}
+
+unsafe extern "C" fn flywheel_world_snapshot(session_id : u64, x : i32, z : i32, out_ptr : usize, out_len : usize) -> u32 {
+    let Some(buf) = HOST.with_borrow(|host| {
+        if (! host.players.contains_key(&session_id)) { return None; }
+        let min = ChunkPos::new(x, z).min_block();
+        // Only sections which contain a block set by the plot or the test are sent.
+        let sections = host.worlds.get(&session_id).into_iter()
+            .flat_map(|world| world.keys())
+            .filter(|pos| pos.chunk() == ChunkPos::new(x, z))
+            .map(|pos| pos.y.div_euclid(16))
+            .collect::<BTreeSet<_>>();
+        let mut buf = Vec::new();
+        buf.extend((sections.len() as u32).to_le_bytes());
+        for section_y in sections {
+            let mut palette = Vec::<Block>::new();
+            let mut indices = Vec::with_capacity(4096);
+            for y in 0..16 { for z in 0..16 { for x in 0..16 {
+                let block = host.block(session_id, BlockPos::new(min.x + x, section_y * 16 + y, min.z + z));
+                let index = palette.iter().position(|other| *other == block).unwrap_or_else(|| {
+                    palette.push(block);
+                    palette.len() - 1
+                });
+                indices.push(index as u64);
+            } } }
+            buf.extend((section_y as i32).to_le_bytes());
+            buf.extend((palette.len() as u32).to_le_bytes());
+            for block in &palette {
+                encode_block(&mut buf, block);
+            }
+            let bits     = (usize::BITS - (palette.len() - 1).leading_zeros()).max(1);
+            let per_word = (64 / bits) as usize;
+            let words    = indices.chunks(per_word)
+                .map(|chunk| chunk.iter().enumerate().fold(0u64, |word, (i, index,)| word | (index << (i as u32 * bits))))
+                .collect::<Vec<_>>();
+            buf.push(bits as u8);
+            buf.extend((words.len() as u32).to_le_bytes());
+            for word in words {
+                buf.extend(word.to_le_bytes());
+            }
+        }
+        Some(buf)
+    }) else { return 0; };
+    let (ptr, len,) = leak_buf(buf);
+    unsafe {
+        write_out(out_ptr, ptr);
+        write_out(out_len, len);
+    }
+    1
+}
+
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_system_dur_since_epoch(out_secs : usize, out_nanos : usize) {
+    let now = HOST.with_borrow(|host| host.now);
+    unsafe {
+        write_out(out_secs, now.as_secs());
+        write_out(out_nanos, now.subsec_nanos());
+    }
+}
+
+
+macro rand_fn( $ident:ident ( $out:ident ) => | $rand:ident | $expr:expr ) {
+    #[unsafe(no_mangle)]
+    unsafe extern "C" fn $ident($out : usize) {
+        let $rand = HOST.with_borrow_mut(|host| host.next_rand());
+        unsafe { write_out($out, ($expr).to_le()); }
+    }
+}
+
+rand_fn!{ flywheel_rand_bool(out_u8) => |r| (r & 1) as u8 }
+rand_fn!{ flywheel_rand_u8(out_u8) => |r| r as u8 }
+rand_fn!{ flywheel_rand_u16(out_u16) => |r| r as u16 }
+rand_fn!{ flywheel_rand_u32(out_u32) => |r| r as u32 }
+rand_fn!{ flywheel_rand_u64(out_u64) => |r| r }
+rand_fn!{ flywheel_rand_f32(out_f32) => |r| ((r >> 40) as f32 / (1u64 << 24) as f32).to_bits() }
+rand_fn!{ flywheel_rand_f64(out_f64) => |r| ((r >> 11) as f64 / (1u64 << 53) as f64).to_bits() }
+
+#[unsafe(no_mangle)]
+unsafe extern "C" fn flywheel_rand_u128(out_u128 : usize) {
+    let r = HOST.with_borrow_mut(|host| ((host.next_rand() as u128) << 64) | (host.next_rand() as u128));
+    unsafe { write_out(out_u128, r.to_le()); }
+}
+
+
+unsafe extern "C" fn flywheel_system_set_motd(in_motd : usize, motd_len : u32) {
+    let motd = unsafe { read_str(in_motd, motd_len) };
+    HOST.with_borrow_mut(|host| host.record(HostCall::SetMotd { motd }));
+}
+
+macro log_fn( $ident:ident => $level:ident ) {
+    unsafe extern "C" fn $ident(in_msg : usize, msg_len : u32) {
+        let msg = unsafe { read_str(in_msg, msg_len) };
+        // This can be called by the panic hook while the mock host is already borrowed.
+        HOST.with(|host| if let Ok(mut host) = host.try_borrow_mut() {
+            host.record(HostCall::Log { level : LogLevel::$level, msg });
+        });
+    }
+}
+
+log_fn!{ flywheel_trace => Trace }
+log_fn!{ flywheel_debug => Debug }
+log_fn!{ flywheel_info  => Info  }
+log_fn!{ flywheel_pass  => Pass  }
+log_fn!{ flywheel_warn  => Warn  }
+log_fn!{ flywheel_error => Error }
+log_fn!{ flywheel_fatal => Fatal }
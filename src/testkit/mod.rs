@@ -0,0 +1,368 @@
+//! An in-process mock of the Flywheel host, for testing plots off-WASM.
+//!
+//! Enabling the `testkit` feature provides a native implementation of every
+//!  host function this crate imports, and of every optional function provided with a capability. Plots can then be compiled and run by
+//!  `cargo test` like any other Rust crate.
+//!
+//! A test queues events with functions like [`player_joined`] and [`chunk_loading`],
+//!  then calls [`App::run`](crate::App::run). While the testkit is active, `App::run`
+//!  returns once every queued event has been handled and every task is waiting.
+//!  Everything the plot sent to the host can then be inspected with [`calls`] or [`take_calls`].
+//!
+//! The mock clock only moves when told to. Tasks sleeping with [`task::sleep`](crate::task::sleep)
+//!  are woken by calling [`advance`], then [`App::run`](crate::App::run) again.
+//!
+//! The mock host is thread-local. Each test runs on its own thread, and so gets its
+//!  own players, clock, and recorded calls.
+
+
+use crate::game::{ Player, Server, Capabilities };
+use crate::game::data::{ ChunkPos, BlockPos, SoundCategory, Block, RegionOp };
+use crate::time::{ Duration, Instant };
+use crate::uuid::Uuid;
+use core::cell::RefCell;
+use std::collections::{ BTreeMap, VecDeque };
+
+
+mod host;
+
+
+thread_local! {
+    static HOST : RefCell<HostState> = RefCell::new(HostState::default());
+}
+
+struct HostState {
+    events       : VecDeque<(String, Vec<u8>,)>,
+    players      : BTreeMap<u64, (Uuid, String,)>,
+    next_session : u64,
+    next_message : u64,
+    calls        : Vec<HostCall>,
+    now          : Duration,
+    rand         : u64,
+    capabilities : Vec<String>,
+    worlds       : BTreeMap<u64, BTreeMap<BlockPos, Block>>
+}
+
+impl Default for HostState {
+    fn default() -> Self { Self {
+        events       : VecDeque::new(),
+        players      : BTreeMap::new(),
+        next_session : 0,
+        next_message : 0,
+        calls        : Vec::new(),
+        now          : Duration::ZERO,
+        rand         : 0x_F1E7_4EE1,
+        capabilities : [
+            Capabilities::SELFHOSTED,
+            Capabilities::PALETTE_BLOCKS,
+            Capabilities::REGION_OPS,
+            Capabilities::IDLE,
+            Capabilities::CHAT_RESOLVE,
+            Capabilities::COMMANDS,
+            Capabilities::WORLD_READ,
+            Capabilities::PLAYER_LOOKUP
+        ].into_iter().map(str::to_string).collect(),
+        worlds       : BTreeMap::new()
+    } }
+}
+
+impl HostState {
+
+    fn record(&mut self, call : HostCall) {
+        self.calls.push(call);
+    }
+
+    /// Returns the block at `pos` in a player's world, which is air unless it was set.
+    fn block(&self, session_id : u64, pos : BlockPos) -> Block {
+        self.worlds.get(&session_id).and_then(|world| world.get(&pos)).cloned()
+            .unwrap_or_else(|| Block::new("minecraft:air"))
+    }
+
+    /// Carries out a region operation on a player's world.
+    fn apply_region_op(&mut self, session_id : u64, op : &RegionOp) {
+        let changes = match (op) {
+            RegionOp::Fill { region, block } => region.iter().map(|pos| (pos, block.clone(),)).collect::<Vec<_>>(),
+            RegionOp::Replace { region, filter, block } => region.iter()
+                .filter(|&pos| filter.matches(&self.block(session_id, pos)))
+                .map(|pos| (pos, block.clone(),))
+                .collect(),
+            RegionOp::Hollow { region, block } => region.iter()
+                .map(|pos| (pos, if (region.is_on_surface(pos)) { block.clone() } else { Block::new("minecraft:air") },))
+                .collect(),
+            RegionOp::Outline { region, block } => region.iter()
+                .filter(|&pos| region.is_on_surface(pos))
+                .map(|pos| (pos, block.clone(),))
+                .collect(),
+            RegionOp::Clone { src, dst } => src.iter()
+                .map(|pos| (BlockPos::new(dst.x + (pos.x - src.min().x), dst.y + (pos.y - src.min().y), dst.z + (pos.z - src.min().z)), self.block(session_id, pos),))
+                .collect()
+        };
+        self.worlds.entry(session_id).or_default().extend(changes);
+    }
+
+    fn next_rand(&mut self) -> u64 {
+        // splitmix64
+        self.rand = self.rand.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.rand;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+}
+
+
+/// A call made by the plot to the host, recorded by the testkit.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostCall {
+    /// [`Player::send_chat`] was called.
+    Chat {
+        /// The session ID of the receiving player.
+        session_id : u64,
+        /// The message that was sent.
+        msg        : String
+    },
+    /// [`Player::send_actionbar`] was called.
+    Actionbar {
+        /// The session ID of the receiving player.
+        session_id : u64,
+        /// The message that was sent.
+        msg        : String
+    },
+    /// [`Player::send_title`] was called.
+    Title {
+        /// The session ID of the receiving player.
+        session_id : u64,
+        /// The title that was sent.
+        title      : String,
+        /// The subtitle that was sent.
+        subtitle   : String,
+        /// The fade in time, rounded down to whole ticks.
+        fade_in    : Duration,
+        /// The stay time, rounded down to whole ticks.
+        stay       : Duration,
+        /// The fade out time, rounded down to whole ticks.
+        fade_out   : Duration
+    },
+    /// [`Player::send_sound`] was called.
+    Sound {
+        /// The session ID of the receiving player.
+        session_id : u64,
+        /// The ID of the sound.
+        id         : String,
+        /// The category of the sound.
+        category   : SoundCategory,
+        /// The volume of the sound.
+        volume     : f32,
+        /// The pitch of the sound.
+        pitch      : f32,
+        /// The seed of the sound.
+        seed       : u64
+    },
+    /// The plot decided what to do with a chat message queued with [`player_chat`].
+    ChatResolved {
+        /// The session ID of the player who sent the message.
+        session_id : u64,
+        /// The ID of the message.
+        message_id : u64,
+        /// Whether the message was cancelled.
+        cancelled  : bool,
+        /// The markup the message was rewritten to, if it was.
+        rewrite    : Option<String>,
+        /// The session IDs the message was restricted to, if it was.
+        recipients : Option<Vec<u64>>
+    },
+    /// The commands a player can use were sent to their client.
+    SetCommands {
+        /// The session ID of the player.
+        session_id : u64,
+        /// Each way the player can run the commands, as returned by
+        ///  [`Command::usage`](crate::game::command::Command::usage).
+        usage      : Vec<String>
+    },
+    /// A batch of blocks was submitted to a player's world.
+    SetBlocks {
+        /// The session ID of the player owning the world.
+        session_id : u64,
+        /// The decoded blocks, in the order they were sent.
+        blocks     : Vec<(BlockPos, Block,)>
+    },
+    /// Region operations were submitted to a player's world.
+    RegionOps {
+        /// The session ID of the player owning the world.
+        session_id : u64,
+        /// The operations, in the order they were sent.
+        ops        : Vec<RegionOp>
+    },
+    /// [`World::mark_ready`](crate::game::prelude::World::mark_ready) was called,
+    ///  or a chunk with no loading callbacks was marked ready automatically.
+    MarkReady {
+        /// The session ID of the player owning the world.
+        session_id : u64,
+        /// The chunk that was marked as ready.
+        chunk      : ChunkPos
+    },
+    /// `Server::set_motd` was called.
+    SetMotd {
+        /// The new MOTD text.
+        motd : String
+    },
+    /// A message was logged through one of the selfhosted logging macros, or a panic was logged.
+    Log {
+        /// The level the message was logged at.
+        level : LogLevel,
+        /// The message, including the location prefix.
+        msg   : String
+    }
+}
+
+/// The level of a [`HostCall::Log`] message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Logged with `trace!`.
+    Trace,
+    /// Logged with `debug!`.
+    Debug,
+    /// Logged with `info!`.
+    Info,
+    /// Logged with `pass!`.
+    Pass,
+    /// Logged with `warn!`.
+    Warn,
+    /// Logged with `error!`.
+    Error,
+    /// Logged with `fatal!`.
+    Fatal
+}
+
+
+/// Queues a raw event to be handled by [`App::run`](crate::App::run).
+///
+/// `args` is the little-endian encoded argument payload, exactly as the host would send it.
+pub fn push_event(id : &str, args : &[u8]) {
+    HOST.with_borrow_mut(|host| host.events.push_back((id.to_string(), args.to_vec(),)));
+}
+
+/// Connects a new player and queues a `flywheel_player_joined` event for them.
+///
+/// Returns the newly connected [`Player`].
+pub fn player_joined(uuid : Uuid, name : &str) -> Player {
+    let session_id = HOST.with_borrow_mut(|host| {
+        let session_id = host.next_session;
+        host.next_session += 1;
+        host.players.insert(session_id, (uuid, name.to_string(),));
+        session_id
+    });
+    push_event("flywheel_player_joined", &session_id.to_le_bytes());
+    unsafe { Player::from_session_id(session_id) }
+}
+
+/// Disconnects a player and queues a `flywheel_player_left` event for them.
+///
+/// [`Player::exists`] returns `false` for this player from now on.
+pub fn player_left(player : Player) {
+    HOST.with_borrow_mut(|host| host.players.remove(&player.session_id()));
+    push_event("flywheel_player_left", &player.session_id().to_le_bytes());
+}
+
+/// Queues a `flywheel_player_chat` event, as if `player` typed `text` into chat.
+///
+/// Returns the ID of the message, which the plot resolves with a [`HostCall::ChatResolved`].
+pub fn player_chat(player : Player, text : &str) -> u64 {
+    let message_id = HOST.with_borrow_mut(|host| {
+        let message_id = host.next_message;
+        host.next_message += 1;
+        message_id
+    });
+    let mut args = Vec::with_capacity(20 + text.len());
+    args.extend(player.session_id().to_le_bytes());
+    args.extend(message_id.to_le_bytes());
+    args.extend((text.len() as u32).to_le_bytes());
+    args.extend(text.as_bytes());
+    push_event("flywheel_player_chat", &args);
+    message_id
+}
+
+/// Queues a `flywheel_player_command` event, as if `player` ran `command`.
+pub fn player_command(player : Player, command : &str) {
+    let mut args = Vec::with_capacity(12 + command.len());
+    args.extend(player.session_id().to_le_bytes());
+    args.extend((command.len() as u32).to_le_bytes());
+    args.extend(command.as_bytes());
+    push_event("flywheel_player_command", &args);
+}
+
+/// Queues a `flywheel_world_chunk_loading` event.
+pub fn chunk_loading(player : Player, chunk : ChunkPos) {
+    push_event("flywheel_world_chunk_loading", &chunk_args(player, chunk));
+}
+
+/// Queues a `flywheel_world_chunk_unloaded` event.
+pub fn chunk_unloaded(player : Player, chunk : ChunkPos) {
+    push_event("flywheel_world_chunk_unloaded", &chunk_args(player, chunk));
+}
+
+/// Queues a `flywheel_stopping` event, asking the plot to stop before `deadline`.
+pub fn stopping(deadline : Instant) {
+    let deadline = deadline.duration_since(Instant::UNIX_EPOCH);
+    let mut args = Vec::with_capacity(12);
+    args.extend(deadline.as_secs().to_le_bytes());
+    args.extend(deadline.subsec_nanos().to_le_bytes());
+    push_event("flywheel_stopping", &args);
+}
+
+fn chunk_args(player : Player, chunk : ChunkPos) -> Vec<u8> {
+    let mut args = Vec::with_capacity(16);
+    args.extend(player.session_id().to_le_bytes());
+    args.extend(chunk.x.to_le_bytes());
+    args.extend(chunk.z.to_le_bytes());
+    args
+}
+
+
+/// Sets a block in a player's world, without recording a call.
+///
+/// The mock host remembers every block set in each world, by the plot or with this function,
+///  and returns them from [`World::get`](crate::game::prelude::World::get) and
+///  [`World::snapshot`](crate::game::prelude::World::snapshot). Every other block is air.
+pub fn set_block(player : Player, pos : BlockPos, block : Block) {
+    HOST.with_borrow_mut(|host| host.worlds.entry(player.session_id()).or_default().insert(pos, block));
+}
+
+
+/// Returns every call recorded since the last [`take_calls`].
+pub fn calls() -> Vec<HostCall> {
+    HOST.with_borrow(|host| host.calls.clone())
+}
+
+/// Returns and clears every call recorded since the last [`take_calls`].
+pub fn take_calls() -> Vec<HostCall> {
+    HOST.with_borrow_mut(|host| host.calls.drain(..).collect())
+}
+
+
+/// Sets the time returned by [`Instant::now`].
+///
+/// The mock clock never moves by itself. It starts at [`Instant::UNIX_EPOCH`].
+pub fn set_time(now : Instant) {
+    HOST.with_borrow_mut(|host| host.now = now.duration_since(Instant::UNIX_EPOCH));
+}
+
+/// Moves the time returned by [`Instant::now`] forward.
+pub fn advance(duration : Duration) {
+    HOST.with_borrow_mut(|host| host.now += duration);
+}
+
+/// Reseeds the random number generator used by [`GetRandom`](crate::rand::GetRandom).
+pub fn seed_rand(seed : u64) {
+    HOST.with_borrow_mut(|host| host.rand = seed);
+}
+
+/// Sets the capabilities returned by [`Server::capabilities`].
+///
+/// By default, the mock host supports every capability. The optional functions of each
+///  capability are provided with it.
+pub fn set_capabilities<'l>(names : impl IntoIterator<Item = &'l str>) {
+    HOST.with_borrow_mut(|host| host.capabilities = names.into_iter().map(str::to_string).collect());
+    Server::forget_capabilities();
+}
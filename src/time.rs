@@ -1,12 +1,13 @@
 //! Reimplementations and extensions to [`std::time`].
 
 
+use crate::internal::alloc::Out;
 use core::ops::{ Add, AddAssign, Sub, SubAssign };
 pub use core::time::Duration;
 
 
 unsafe extern "C" {
-    unsafe fn flywheel_system_dur_since_epoch( out_secs : u32, out_nanos : u32 );
+    unsafe fn flywheel_system_dur_since_epoch( out_secs : usize, out_nanos : usize );
 }
 
 
@@ -27,10 +28,10 @@ impl Instant {
 
     /// See [`Instant::now`](https://doc.rust-lang.org/stable/std/time/struct.Instant.html#method.now).
     pub fn now() -> Self {
-        let mut secs  = 0u64;
-        let mut nanos = 0u32;
-        unsafe { flywheel_system_dur_since_epoch((&mut secs) as (*mut _) as u32, (&mut nanos) as (*mut _) as u32); };
-        Self { after_epoch : Duration::new(secs, nanos) }
+        let mut secs  = Out::<u64>::new();
+        let mut nanos = Out::<u32>::new();
+        unsafe { flywheel_system_dur_since_epoch(secs.ptr(), nanos.ptr()); };
+        Self { after_epoch : Duration::new(secs.get(), nanos.get()) }
     }
 
     /// See [`Instant::duration_since`](https://doc.rust-lang.org/stable/std/time/struct.Instant.html#method.duration_since).
@@ -67,8 +68,8 @@ impl Instant {
     }
 
     /// Converts this `Instant` to a [`chrono::DateTime<Utc>`](chrono::DateTime).
-    #[cfg(any(doc, feature = "chrono"))]
-    #[doc(cfg(feature = "chorno"))]
+    #[cfg(feature = "chrono")]
+    #[doc(cfg(feature = "chrono"))]
     pub fn as_chrono(&self) -> chrono::DateTime<chrono::Utc> {
         chrono::DateTime::UNIX_EPOCH + self.after_epoch
     }